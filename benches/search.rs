@@ -0,0 +1,120 @@
+// search benchmarks: linear query scan vs the inverted index over a synthetic timeline.
+// run with `cargo bench --bench search`
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rusty_lens::index::SearchIndex;
+use rusty_lens::query::Query;
+use rusty_lens::timeline::TimelineEvent;
+
+const ROWS: usize = 200_000;
+
+const ACTION_TYPES: &[&str] = &[
+    "ProcessCreated",
+    "FileCreated",
+    "ConnectionSuccess",
+    "RegistryValueSet",
+    "ImageLoaded",
+];
+
+fn timeline() -> Vec<TimelineEvent> {
+    (0..ROWS)
+        .map(|i| TimelineEvent {
+            event_time: Some(format!(
+                "2024-01-01T{:02}:{:02}:00Z",
+                i / 3600 % 24,
+                i / 60 % 60
+            )),
+            action_type: Some(ACTION_TYPES[i % ACTION_TYPES.len()].into()),
+            file_name: Some(format!("file{}.exe", i)),
+            folder_path: Some(format!("C:\\Windows\\Temp\\dir{}", i % 100)),
+            sha256: Some(sha256(i)),
+            process_command_line: Some(format!("cmd.exe /c copy file{} out{}.tmp", i, i % 50)),
+            initiating_process_file_name: Some(format!("parent{}.exe", i % 500)),
+            account_name: Some(if i % 1000 == 0 { "admin" } else { "svc" }.into()),
+            ..TimelineEvent::default()
+        })
+        .collect()
+}
+
+/// a distinct, evenly spread 64-digit hex value standing in for a file hash
+fn sha256(i: usize) -> String {
+    (1..=4u64)
+        .map(|k| {
+            format!(
+                "{:016x}",
+                (i as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15 ^ k)
+            )
+        })
+        .collect()
+}
+
+/// rows matching `query`, answered from `index` and checking only undecided rows
+fn indexed(query: &Query, index: &SearchIndex, events: &[TimelineEvent]) -> usize {
+    let found = query.candidates(index);
+    found.sure.count()
+        + found
+            .maybe
+            .iter()
+            .filter(|&i| query.matches(&events[i]))
+            .count()
+}
+
+fn search(c: &mut Criterion) {
+    let events = timeline();
+    let mut index = SearchIndex::default();
+    for (i, ev) in events.iter().enumerate() {
+        index.add(i, ev);
+    }
+    let mut group = c.benchmark_group("search");
+    group.sample_size(10);
+    for q in [
+        "admin",
+        "file4242.exe",
+        "parent17*",
+        "account_name:admin",
+        "cmd.exe AND NOT svc",
+        "\"copy file99 \"",
+    ] {
+        let query = Query::parse(q, &[]).unwrap();
+        let expected = events.iter().filter(|e| query.matches(e)).count();
+        assert_eq!(indexed(&query, &index, &events), expected, "{}", q);
+        group.bench_with_input(BenchmarkId::new("linear", q), &query, |b, query| {
+            b.iter(|| events.iter().filter(|e| query.matches(e)).count())
+        });
+        group.bench_with_input(BenchmarkId::new("indexed", q), &query, |b, query| {
+            b.iter(|| indexed(query, &index, &events))
+        });
+    }
+    group.finish();
+}
+
+fn build(c: &mut Criterion) {
+    let events = timeline();
+    // every distinct token (here a hash and a file name per row) stays for as long as the
+    // timeline is open, up to the dictionary cap; hashes skip the trigrams
+    let mut index = SearchIndex::default();
+    for (i, ev) in events.iter().enumerate() {
+        index.add(i, ev);
+    }
+    println!(
+        "index over {} rows: {} tokens, about {} MiB",
+        ROWS,
+        index.token_count(),
+        index.heap_bytes() >> 20
+    );
+    let mut group = c.benchmark_group("index");
+    group.sample_size(10);
+    group.bench_function("build", |b| {
+        b.iter(|| {
+            let mut index = SearchIndex::default();
+            for (i, ev) in events.iter().enumerate() {
+                index.add(i, ev);
+            }
+            black_box(index.token_count())
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, search, build);
+criterion_main!(benches);
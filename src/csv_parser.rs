@@ -1,27 +1,604 @@
-// streaming csv reader for defender timeline; collects rows into a vec (with optional cap)
+// streaming csv reader for defender timeline; visits every row with its byte offset and reports bad rows.
+// header rows are schema-detected: advanced hunting exports are mapped onto the portal timeline columns.
 
 use crate::error::Result;
+use crate::input::{self, ByteCounter, Format, Source};
+use crate::json_parser;
 use crate::timeline::TimelineEvent;
-use std::fs::File;
-use std::io::BufReader;
+use std::io::{self, BufRead, Read};
 use std::path::Path;
+use std::sync::Arc;
 
-/// load timeline events from a csv path; malformed rows are skipped
-pub fn load_timeline(path: &Path, max_rows: Option<usize>) -> Result<Vec<TimelineEvent>> {
-    let file = File::open(path)?;
-    let mut rdr = csv::Reader::from_reader(BufReader::new(file));
+/// running counters while a csv is scanned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScanProgress {
+    /// raw (possibly compressed) bytes consumed so far / total raw size
+    pub bytes_read: u64,
+    pub total_bytes: u64,
+    pub rows_parsed: usize,
+    pub rows_skipped: usize,
+}
+
+/// cap on rejected rows kept with full detail; the count stays exact past it
+pub const MAX_REJECTED_DETAILS: usize = 10_000;
+/// max chars of raw record text kept per rejected row
+const SNIPPET_CHARS: usize = 200;
+
+/// a row that could not be parsed into a `TimelineEvent`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedRow {
+    /// label of the source file (see `Source::label`)
+    pub source: String,
+    /// 1-based line where the record starts
+    pub line: u64,
+    pub byte_offset: u64,
+    /// csv or serde error message
+    pub error: String,
+    /// start of the raw record (fields re-joined with commas)
+    pub snippet: String,
+}
+
+/// what went wrong during a load: every rejected row (detail capped at `MAX_REJECTED_DETAILS`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadReport {
+    pub rejected: Vec<RejectedRow>,
+    /// total rejected rows, including ones past the detail cap
+    pub rejected_total: usize,
+}
+
+impl LoadReport {
+    pub fn add(&mut self, row: RejectedRow) {
+        self.rejected_total += 1;
+        if self.rejected.len() < MAX_REJECTED_DETAILS {
+            self.rejected.push(row);
+        }
+    }
+}
+
+/// one row seen by `scan_timeline` (rows are the common case, so the event is not boxed)
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum ScanStep {
+    /// well-formed row starting at byte `offset`
+    Row { offset: u64, event: TimelineEvent },
+    /// malformed row (bad field count, invalid utf-8, ...)
+    Skipped(RejectedRow),
+}
+
+/// column names of the portal device timeline export (what `TimelineEvent` deserializes)
+pub const TIMELINE_HEADERS: &[&str] = &[
+    "Event Time",
+    "Machine Id",
+    "Computer Name",
+    "Action Type",
+    "File Name",
+    "Folder Path",
+    "Sha1",
+    "Sha256",
+    "MD5",
+    "Process Command Line",
+    "Account Domain",
+    "Account Name",
+    "Account Sid",
+    "Logon Id",
+    "Process Id",
+    "Process Creation Time",
+    "Process Token Elevation",
+    "Registry Key",
+    "Registry Value Name",
+    "Registry Value Data",
+    "Remote Url",
+    "Remote Computer Name",
+    "Remote IP",
+    "Remote Port",
+    "Local IP",
+    "Local Port",
+    "File Origin Url",
+    "File Origin IP",
+    "Initiating Process SHA1",
+    "Initiating Process SHA256",
+    "Initiating Process File Name",
+    "Initiating Process Folder Path",
+    "Initiating Process Id",
+    "Initiating Process Command Line",
+    "Initiating Process Creation Time",
+    "Initiating Process Integrity Level",
+    "Initiating Process Token Elevation",
+    "Initiating Process Parent Id",
+    "Initiating Process Parent File Name",
+    "Initiating Process Parent Creation Time",
+    "Initiating Process MD5",
+    "Initiating Process Account Domain",
+    "Initiating Process Account Name",
+    "Initiating Process Account Sid",
+    "Initiating Process Logon Id",
+    "Report Id",
+    "Additional Fields",
+    "Typed Details",
+    "App Guard Container Id",
+    "Protocol",
+    "Logon Type",
+    "Process Integrity Level",
+    "Registry Value Type",
+    "Previous Registry Value Name",
+    "Previous Registry Value Data",
+    "Previous Registry Key",
+    "File Origin Referrer Url",
+    "Sensitivity Label",
+    "Sensitivity Sub Label",
+    "Is Endpoint Dlp Applied",
+    "Is Azure Info Protection Applied",
+    "Alert Ids",
+    "Categories",
+    "Severities",
+    "Is Marked",
+    "Data Type",
+];
+
+/// advanced hunting columns whose name differs from the portal one by more than spacing/case
+const HUNTING_ALIASES: &[(&str, &str)] = &[
+    ("Timestamp", "Event Time"),
+    ("DeviceId", "Machine Id"),
+    ("DeviceName", "Computer Name"),
+    ("RemoteDeviceName", "Remote Computer Name"),
+];
+
+/// advanced hunting tables, each recognized by a column only it (or the catch-all
+/// DeviceEvents) carries; checked in order. tables with no column of their own
+/// (DeviceImageLoadEvents, DeviceFileCertificateInfo, …) are left unnamed.
+const HUNTING_TABLES: &[(&str, &[&str])] = &[
+    (
+        "DeviceEvents",
+        &["AdditionalFields", "RegistryKey", "RemotePort"],
+    ),
+    ("DeviceRegistryEvents", &["PreviousRegistryKey"]),
+    ("DeviceLogonEvents", &["LogonType"]),
+    ("DeviceNetworkEvents", &["RemotePort"]),
+    ("DeviceFileEvents", &["PreviousFileName"]),
+    ("DeviceProcessEvents", &["ProcessCommandLine"]),
+];
+
+/// layout of an export, detected from its header row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schema {
+    /// portal device timeline ("Event Time", "Action Type", ...)
+    Timeline,
+    /// advanced hunting table export ("Timestamp", "ActionType", ...); the table name when
+    /// the columns identify one
+    AdvancedHunting(Option<&'static str>),
+    /// neither; columns that happen to match a timeline field are still used
+    Unknown,
+}
+
+impl Schema {
+    pub fn name(&self) -> String {
+        match self {
+            Schema::Timeline => "device timeline".to_string(),
+            Schema::AdvancedHunting(Some(table)) => format!("advanced hunting ({})", table),
+            Schema::AdvancedHunting(None) => "advanced hunting".to_string(),
+            Schema::Unknown => "unknown".to_string(),
+        }
+    }
+}
+
+/// lowercase alphanumerics only, so "Initiating Process SHA1" == "InitiatingProcessSHA1"
+pub(crate) fn squash(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// the timeline column a header refers to, in either naming scheme (None if unknown)
+pub fn timeline_column(name: &str) -> Option<&'static str> {
+    let name = name.trim_start_matches('\u{feff}').trim();
+    if let Some(&(_, portal)) = HUNTING_ALIASES
+        .iter()
+        .find(|(ah, _)| ah.eq_ignore_ascii_case(name))
+    {
+        return Some(portal);
+    }
+    let key = squash(name);
+    TIMELINE_HEADERS.iter().copied().find(|h| squash(h) == key)
+}
+
+/// which export layout a header row belongs to
+pub fn detect_schema<'a>(headers: impl IntoIterator<Item = &'a str>) -> Schema {
+    let names: Vec<&str> = headers
+        .into_iter()
+        .map(|h| h.trim_start_matches('\u{feff}').trim())
+        .collect();
+    let has = |col: &str| names.iter().any(|n| n.eq_ignore_ascii_case(col));
+    if has("Event Time") {
+        return Schema::Timeline;
+    }
+    if has("Timestamp") && (has("ActionType") || has("DeviceName")) {
+        let table = HUNTING_TABLES
+            .iter()
+            .find(|(_, cols)| cols.iter().all(|c| has(c)))
+            .map(|&(table, _)| table);
+        return Schema::AdvancedHunting(table);
+    }
+    Schema::Unknown
+}
+
+/// a header row resolved against the timeline fields: the names serde sees (advanced hunting
+/// names rewritten to portal ones) plus the columns that have no field and are kept as extras
+#[derive(Debug, Clone)]
+pub struct HeaderMap {
+    pub schema: Schema,
+    headers: csv::ByteRecord,
+    /// (column index, header as written) of columns with no timeline field
+    extra: Vec<(usize, Arc<str>)>,
+}
+
+impl HeaderMap {
+    pub fn new(raw: &csv::StringRecord) -> Self {
+        let mut headers = csv::StringRecord::new();
+        let mut extra = Vec::new();
+        for (i, h) in raw.iter().enumerate() {
+            match timeline_column(h) {
+                // a second column for the same field (e.g. both naming schemes) stays an extra
+                Some(col) if !headers.iter().any(|taken| taken == col) => headers.push_field(col),
+                _ => {
+                    let name = h.trim_start_matches('\u{feff}').trim();
+                    extra.push((i, Arc::from(name)));
+                    // blank name: no field matches, so serde skips the column
+                    headers.push_field("");
+                }
+            }
+        }
+        Self {
+            schema: detect_schema(raw.iter()),
+            headers: headers.into_byte_record(),
+            extra,
+        }
+    }
+
+    /// number of columns a record must have
+    pub fn columns(&self) -> usize {
+        self.headers.len()
+    }
+
+    /// headers of the columns kept as extra fields, in column order
+    pub fn unmapped(&self) -> impl Iterator<Item = &str> {
+        self.extra.iter().map(|(_, name)| &**name)
+    }
+
+    /// deserialize one record, collecting non-empty unmapped columns into `extra`
+    pub fn event(
+        &self,
+        record: &csv::ByteRecord,
+    ) -> std::result::Result<TimelineEvent, csv::Error> {
+        let mut event: TimelineEvent = record.deserialize(Some(&self.headers))?;
+        for (i, name) in &self.extra {
+            let value = String::from_utf8_lossy(record.get(*i).unwrap_or_default());
+            let value = value.trim();
+            if !value.is_empty() {
+                event.extra.push((name.clone(), value.to_string()));
+            }
+        }
+        Ok(event)
+    }
+}
+
+/// what the header row (or first json record) of an input says about its columns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub schema: Schema,
+    /// csv field separator (sniffed; `,` for json)
+    pub delimiter: u8,
+    /// columns with no timeline field (kept per event as extra fields)
+    pub unmapped: Vec<String>,
+}
+
+/// read the layout of `source` from its header row, or the keys of its first json record
+pub fn read_layout(source: &Source) -> Result<Layout> {
+    input::with_reader(source, |reader, _, _| {
+        if input::sniff_format(reader)? == Format::Json {
+            let keys = json_parser::first_keys(reader)?;
+            return Ok(Layout {
+                schema: detect_schema(keys.iter().map(String::as_str)),
+                delimiter: b',',
+                unmapped: keys
+                    .into_iter()
+                    .filter(|k| timeline_column(k).is_none())
+                    .collect(),
+            });
+        }
+        let delimiter = sniff_delimiter(reader.fill_buf()?);
+        let mut rdr = reader_builder(delimiter).from_reader(reader);
+        let headers = csv::StringRecord::from_byte_record_lossy(rdr.byte_headers()?.clone());
+        let map = HeaderMap::new(&headers);
+        Ok(Layout {
+            schema: map.schema,
+            delimiter,
+            unmapped: map.unmapped().map(str::to_string).collect(),
+        })
+    })
+}
+
+/// separators tried when sniffing (excel uses `;` under european locales, tab for "unicode text")
+const DELIMITERS: &[u8] = b",;\t";
+
+/// field separator of a csv from its header line: the candidate seen most often outside
+/// quotes (`,` when none appears)
+pub fn sniff_delimiter(head: &[u8]) -> u8 {
+    let mut counts = [0usize; DELIMITERS.len()];
+    let mut quoted = false;
+    for &b in head {
+        if b == b'"' {
+            quoted = !quoted;
+        } else if !quoted && b == b'\n' {
+            break;
+        } else if !quoted {
+            if let Some(i) = DELIMITERS.iter().position(|&d| d == b) {
+                counts[i] += 1;
+            }
+        }
+    }
+    let best = (0..DELIMITERS.len()).max_by_key(|&i| (counts[i], usize::MAX - i));
+    match best {
+        Some(i) if counts[i] > 0 => DELIMITERS[i],
+        _ => b',',
+    }
+}
+
+/// csv reader settings shared by the scanner and the on-demand store.
+/// flexible so that short/long rows reach us and can be reported, not just skipped.
+pub fn reader_builder(delimiter: u8) -> csv::ReaderBuilder {
+    let mut b = csv::ReaderBuilder::new();
+    b.flexible(true).delimiter(delimiter);
+    b
+}
+
+/// stream every row of `source` (plain, gzip or zip member; csv or json) through `on_step`;
+/// return false from `on_step` to stop early
+pub fn scan_timeline(
+    source: &Source,
+    mut on_step: impl FnMut(ScanStep, &ScanProgress) -> bool,
+) -> Result<ScanProgress> {
+    let label = source.label();
+    input::with_reader(
+        source,
+        |reader, total, counter| match input::sniff_format(reader)? {
+            Format::Csv => scan_reader(reader, total, counter, &label, &mut on_step),
+            Format::Json => json_parser::scan_reader(reader, total, counter, &label, &mut on_step),
+        },
+    )
+}
+
+/// what a scan picking up partway through a source needs to know of its start
+#[derive(Debug, Clone)]
+pub enum ScanKind {
+    Csv {
+        delimiter: u8,
+        headers: HeaderMap,
+    },
+    /// one json record per line
+    JsonLines,
+    /// one json array or api envelope
+    JsonDocument,
+}
+
+/// read how `source` is laid out, for `scan_timeline_from`
+pub fn scan_kind(source: &Source) -> Result<ScanKind> {
+    input::with_reader(source, |reader, _, _| {
+        if input::sniff_format(reader)? == Format::Json {
+            return Ok(if json_parser::is_json_lines(reader)? {
+                ScanKind::JsonLines
+            } else {
+                ScanKind::JsonDocument
+            });
+        }
+        let delimiter = sniff_delimiter(reader.fill_buf()?);
+        let mut rdr = reader_builder(delimiter).from_reader(reader);
+        let raw = rdr.byte_headers()?.clone();
+        Ok(ScanKind::Csv {
+            delimiter,
+            headers: HeaderMap::new(&csv::StringRecord::from_byte_record_lossy(raw)),
+        })
+    })
+}
+
+/// like `scan_timeline`, starting at `offset` in the decoded stream: the offset of a row seen
+/// by an earlier scan. the bytes before it are decompressed but not parsed; lines (and the
+/// progress counters) count from there.
+pub fn scan_timeline_from(
+    source: &Source,
+    kind: &ScanKind,
+    offset: u64,
+    mut on_step: impl FnMut(ScanStep, &ScanProgress) -> bool,
+) -> Result<ScanProgress> {
+    let label = source.label();
+    input::with_reader(source, |reader, total, counter| {
+        io::copy(&mut reader.take(offset), &mut io::sink())?;
+        match kind {
+            ScanKind::Csv { delimiter, headers } => {
+                let mut progress = ScanProgress {
+                    total_bytes: total,
+                    ..ScanProgress::default()
+                };
+                let mut rdr = reader_builder(*delimiter)
+                    .has_headers(false)
+                    .from_reader(reader);
+                scan_records(
+                    &mut rdr,
+                    headers,
+                    offset,
+                    &mut progress,
+                    counter,
+                    &label,
+                    &mut on_step,
+                )?;
+                Ok(progress)
+            }
+            ScanKind::JsonLines | ScanKind::JsonDocument => json_parser::resume_reader(
+                reader,
+                matches!(kind, ScanKind::JsonLines),
+                offset,
+                total,
+                counter,
+                &label,
+                &mut on_step,
+            ),
+        }
+    })
+}
+
+fn scan_reader(
+    reader: &mut dyn BufRead,
+    total_bytes: u64,
+    counter: &ByteCounter,
+    label: &str,
+    on_step: &mut dyn FnMut(ScanStep, &ScanProgress) -> bool,
+) -> Result<ScanProgress> {
+    let mut progress = ScanProgress {
+        total_bytes,
+        ..ScanProgress::default()
+    };
+    let delimiter = sniff_delimiter(reader.fill_buf()?);
+    let mut rdr = reader_builder(delimiter).from_reader(reader);
+    let raw = rdr.byte_headers()?.clone();
+    let headers = HeaderMap::new(&csv::StringRecord::from_byte_record_lossy(raw));
+    scan_records(
+        &mut rdr,
+        &headers,
+        0,
+        &mut progress,
+        counter,
+        label,
+        on_step,
+    )?;
+    Ok(progress)
+}
+
+/// the records of `rdr` (past the header row) through `on_step`; byte offsets are shifted by
+/// `base`, where `rdr` started reading
+fn scan_records(
+    rdr: &mut csv::Reader<&mut dyn BufRead>,
+    headers: &HeaderMap,
+    base: u64,
+    progress: &mut ScanProgress,
+    counter: &ByteCounter,
+    label: &str,
+    on_step: &mut dyn FnMut(ScanStep, &ScanProgress) -> bool,
+) -> Result<()> {
+    let total_bytes = progress.total_bytes;
+    let mut record = csv::ByteRecord::new();
+    loop {
+        let mut step = match rdr.read_byte_record(&mut record) {
+            Ok(true) => check_record(&record, headers, label),
+            Ok(false) => break,
+            Err(e) if e.is_io_error() => return Err(e.into()),
+            Err(e) => {
+                let (line, byte) = e
+                    .position()
+                    .map(|p| (p.line(), p.byte()))
+                    .unwrap_or((0, rdr.position().byte()));
+                ScanStep::Skipped(RejectedRow {
+                    source: label.to_string(),
+                    line,
+                    byte_offset: byte,
+                    error: e.to_string(),
+                    snippet: snippet(&record),
+                })
+            }
+        };
+        match &mut step {
+            ScanStep::Row { offset, .. } => {
+                *offset += base;
+                progress.rows_parsed += 1;
+            }
+            ScanStep::Skipped(row) => {
+                row.byte_offset += base;
+                progress.rows_skipped += 1;
+            }
+        }
+        progress.bytes_read = counter.get().min(total_bytes);
+        if !on_step(step, progress) {
+            break;
+        }
+    }
+    progress.bytes_read = total_bytes;
+    Ok(())
+}
+
+/// field-count check plus deserialize; either failure becomes a rejected row
+fn check_record(record: &csv::ByteRecord, headers: &HeaderMap, label: &str) -> ScanStep {
+    let (line, offset) = record
+        .position()
+        .map(|p| (p.line(), p.byte()))
+        .unwrap_or_default();
+    let reject = |error: String| {
+        ScanStep::Skipped(RejectedRow {
+            source: label.to_string(),
+            line,
+            byte_offset: offset,
+            error,
+            snippet: snippet(record),
+        })
+    };
+    if record.len() != headers.columns() {
+        return reject(format!(
+            "expected {} fields, found {}",
+            headers.columns(),
+            record.len()
+        ));
+    }
+    match headers.event(record) {
+        Ok(event) => ScanStep::Row { offset, event },
+        Err(e) => reject(e.to_string()),
+    }
+}
+
+/// raw record text for the report, truncated to `SNIPPET_CHARS`
+fn snippet(record: &csv::ByteRecord) -> String {
+    let joined = record
+        .iter()
+        .map(String::from_utf8_lossy)
+        .collect::<Vec<_>>()
+        .join(",");
+    clip(joined)
+}
+
+/// truncate report text to `SNIPPET_CHARS`
+pub(crate) fn clip(text: String) -> String {
+    match text.char_indices().nth(SNIPPET_CHARS) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text,
+    }
+}
+
+/// load timeline events from a csv or json path (plain, .gz, or a zip with a single timeline)
+/// into memory, with a report of the rows that were rejected
+pub fn load_timeline(
+    path: &Path,
+    max_rows: Option<usize>,
+) -> Result<(Vec<TimelineEvent>, LoadReport)> {
+    load_source(&Source::new(path), max_rows)
+}
+
+/// like `load_timeline`, for an explicit source (e.g. a named zip member)
+pub fn load_source(
+    source: &Source,
+    max_rows: Option<usize>,
+) -> Result<(Vec<TimelineEvent>, LoadReport)> {
     let mut out = Vec::new();
-    for row in rdr.deserialize() {
+    let mut report = LoadReport::default();
+    scan_timeline(source, |step, _| {
         if let Some(cap) = max_rows {
             if out.len() >= cap {
-                break;
+                return false;
             }
         }
-        if let Ok(ev) = row {
-            out.push(ev);
+        match step {
+            ScanStep::Row { event, .. } => out.push(event),
+            ScanStep::Skipped(row) => report.add(row),
         }
-    }
-    Ok(out)
+        true
+    })?;
+    Ok((out, report))
 }
 
 #[cfg(test)]
@@ -32,9 +609,114 @@ mod tests {
     #[test]
     fn parse_fixture() {
         let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixture_sample.csv");
-        let events = load_timeline(&path, None).unwrap();
+        let (events, report) = load_timeline(&path, None).unwrap();
         assert_eq!(events.len(), 2);
+        assert_eq!(report.rejected_total, 0);
         assert_eq!(events[0].action_type.as_deref(), Some("ProcessCreated"));
         assert_eq!(events[1].action_type.as_deref(), Some("ConnectionSuccess"));
     }
+
+    #[test]
+    fn malformed_rows_are_reported() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixture_malformed.csv");
+        let (events, report) = load_timeline(&path, None).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(report.rejected_total, 1);
+        let row = &report.rejected[0];
+        assert_eq!(row.line, 3);
+        assert!(row.error.contains("expected 66 fields, found 5"));
+        assert!(row.snippet.contains("short.exe"));
+    }
+
+    #[test]
+    fn gzip_and_zip_inputs() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let (events, _) = load_timeline(&dir.join("fixture_sample.csv.gz"), None).unwrap();
+        assert_eq!(events.len(), 2);
+
+        let bundle = dir.join("fixture_bundle.zip");
+        // two csv members: must name one
+        assert!(load_timeline(&bundle, None).is_err());
+        let source = Source::with_member(&bundle, Some("host2/timeline.csv".to_string()));
+        let (events, _) = load_source(&source, None).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].computer_name.as_deref(), Some("HOST2"));
+    }
+
+    #[test]
+    fn advanced_hunting_export_is_mapped() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixture_hunting_process.csv");
+        let source = Source::new(&path);
+        let layout = read_layout(&source).unwrap();
+        assert_eq!(
+            layout.schema,
+            Schema::AdvancedHunting(Some("DeviceProcessEvents"))
+        );
+        assert!(layout.unmapped.contains(&"AccountUpn".to_string()));
+        let (events, report) = load_timeline(&path, None).unwrap();
+        assert_eq!(report.rejected_total, 0);
+        assert_eq!(events.len(), 2);
+        let ev = &events[0];
+        assert_eq!(ev.computer_name.as_deref(), Some("host3.contoso.local"));
+        assert_eq!(ev.action_type.as_deref(), Some("ProcessCreated"));
+        assert_eq!(ev.process_id.as_deref(), Some("4242"));
+        let upn = ev.extra.iter().find(|(k, _)| &**k == "AccountUpn");
+        assert_eq!(upn.map(|(_, v)| v.as_str()), Some("alice@contoso.local"));
+        assert!(ev.matches_search("alice@contoso"));
+        assert!(ev.matches_search("account_upn:ALICE@contoso.local"));
+        assert!(ev
+            .detail_lines()
+            .contains(&("FileSize".to_string(), "1000".to_string())));
+        assert_eq!(ev.initiating_process_file_name.as_deref(), Some("cmd.exe"));
+        assert_eq!(ev.initiating_process_parent_id.as_deref(), Some("900"));
+        assert!(ev.event_time_parsed().is_some());
+
+        // columns every table shares name none of them
+        assert_eq!(
+            detect_schema([
+                "Timestamp",
+                "DeviceName",
+                "ActionType",
+                "FileName",
+                "InitiatingProcessFileName",
+            ]),
+            Schema::AdvancedHunting(None)
+        );
+
+        let portal = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixture_sample.csv");
+        let layout = read_layout(&Source::new(portal)).unwrap();
+        assert_eq!(layout.schema, Schema::Timeline);
+        assert!(layout.unmapped.is_empty());
+        assert_eq!(
+            timeline_column("RemoteDeviceName"),
+            Some("Remote Computer Name")
+        );
+        assert_eq!(timeline_column("AccountUpn"), None);
+    }
+
+    #[test]
+    fn excel_resaved_exports() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        // utf-8 bom, `;` separated, crlf
+        let (events, report) = load_timeline(&dir.join("fixture_semicolon.csv"), None).unwrap();
+        assert_eq!(report.rejected_total, 0);
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0].event_time.as_deref(),
+            Some("2026-01-01T12:00:00.000")
+        );
+        assert_eq!(
+            events[0].process_command_line.as_deref(),
+            Some("cmd.exe /c echo a,b")
+        );
+        // utf-16 le with bom, tab separated
+        let (events, report) = load_timeline(&dir.join("fixture_utf16.csv"), None).unwrap();
+        assert_eq!(report.rejected_total, 0);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].computer_name.as_deref(), Some("HÖST1-𝔘"));
+        assert_eq!(events[1].action_type.as_deref(), Some("ConnectionSuccess"));
+
+        assert_eq!(sniff_delimiter(b"a;\"b,c\";d\n1,2,3,4,5"), b';');
+        assert_eq!(sniff_delimiter(b"single"), b',');
+    }
 }
@@ -1,42 +1,125 @@
-// filter timeline events by action type and text search
+// filter timeline events by action type and search query
 
+use crate::index::{Bits, SearchIndex};
+use crate::query::Query;
 use crate::timeline::TimelineEvent;
-use chrono::{NaiveDate, Timelike};
-use std::collections::HashSet;
+use crate::zone::DisplayZone;
+use chrono::{Duration, NaiveDate, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
-/// filter events: optional action type exact match, optional substring search (case-insensitive)
+/// filter events: action type include/exclude sets, optional search query (see `Query`;
+/// one that does not parse matches nothing)
 pub fn filter_events<'a>(
     events: &'a [TimelineEvent],
-    action_type_filter: Option<&str>,
+    action_types: &ActionTypeFilter,
     search: &str,
 ) -> Vec<&'a TimelineEvent> {
-    let search = search.trim();
-    let action_match = action_type_filter
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty());
+    let Ok(query) = Query::parse(search, &[]) else {
+        return Vec::new();
+    };
     events
         .iter()
-        .filter(|ev| {
-            if let Some(at) = action_match {
-                if ev.action_type.as_deref() != Some(at) {
-                    return false;
-                }
-            }
-            ev.matches_search(search)
-        })
+        .filter(|ev| action_types.matches(ev.action_type.as_deref()) && query.matches(ev))
         .collect()
 }
 
-/// collect unique action type strings from events (for filter dropdown/typeahead)
-pub fn unique_action_types(events: &[TimelineEvent]) -> Vec<String> {
-    let set: std::collections::HashSet<String> = events
+/// unique action types with their event counts, sorted by name (for the filter picker)
+pub fn unique_action_types(events: &[TimelineEvent]) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for at in events
         .iter()
-        .filter_map(|e| e.action_type.clone())
+        .filter_map(|e| e.action_type.as_deref())
         .filter(|s| !s.is_empty())
-        .collect();
-    let mut v: Vec<String> = set.into_iter().collect();
-    v.sort();
-    v
+    {
+        *counts.entry(at.to_string()).or_default() += 1;
+    }
+    counts.into_iter().collect()
+}
+
+/// state of one action type in the picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    Off,
+    Include,
+    Exclude,
+}
+
+/// action types to show (include) and to hide (exclude); with nothing included every type not
+/// excluded is shown
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActionTypeFilter {
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub include: BTreeSet<String>,
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub exclude: BTreeSet<String>,
+}
+
+impl ActionTypeFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// true if an event with `action_type` passes
+    pub fn matches(&self, action_type: Option<&str>) -> bool {
+        let at = action_type.unwrap_or("");
+        (self.include.is_empty() || self.include.contains(at)) && !self.exclude.contains(at)
+    }
+
+    /// rows of `index` that pass
+    pub fn rows(&self, index: &SearchIndex) -> Bits {
+        let mut rows = if self.include.is_empty() {
+            Bits::full(index.len())
+        } else {
+            let mut rows = Bits::new(index.len());
+            for at in &self.include {
+                rows.or(&index.action_type(at));
+            }
+            rows
+        };
+        for at in &self.exclude {
+            rows.and_not(&index.action_type(at));
+        }
+        rows
+    }
+
+    pub fn mark(&self, action_type: &str) -> Mark {
+        if self.include.contains(action_type) {
+            Mark::Include
+        } else if self.exclude.contains(action_type) {
+            Mark::Exclude
+        } else {
+            Mark::Off
+        }
+    }
+
+    /// off -> include -> exclude -> off
+    pub fn cycle(&mut self, action_type: &str) {
+        match self.mark(action_type) {
+            Mark::Off => {
+                self.include.insert(action_type.to_string());
+            }
+            Mark::Include => {
+                self.include.remove(action_type);
+                self.exclude.insert(action_type.to_string());
+            }
+            Mark::Exclude => {
+                self.exclude.remove(action_type);
+            }
+        }
+    }
+
+    /// e.g. "ProcessCreated + ImageLoaded, not FileCreated"
+    pub fn describe(&self) -> String {
+        let join = |set: &BTreeSet<String>| set.iter().cloned().collect::<Vec<_>>().join(" + ");
+        match (self.include.is_empty(), self.exclude.is_empty()) {
+            (true, true) => "all action types".to_string(),
+            (false, true) => join(&self.include),
+            (true, false) => format!("not {}", join(&self.exclude)),
+            (false, false) => format!("{}, not {}", join(&self.include), join(&self.exclude)),
+        }
+    }
 }
 
 /// collect unique dates that appear in event times (for time range picker)
@@ -62,3 +145,106 @@ pub fn unique_hours_for_date(events: &[TimelineEvent], date: NaiveDate) -> Vec<u
     v.sort();
     v
 }
+
+/// facets collected while events stream in (action types with counts, utc hours with events)
+#[derive(Debug, Clone, Default)]
+pub struct TimelineSummary {
+    action_types: BTreeMap<String, usize>,
+    /// start of every utc hour holding at least one event
+    hours: BTreeSet<NaiveDateTime>,
+}
+
+impl TimelineSummary {
+    pub fn add(&mut self, ev: &TimelineEvent) {
+        if let Some(at) = ev.action_type.as_deref().filter(|s| !s.is_empty()) {
+            match self.action_types.get_mut(at) {
+                Some(n) => *n += 1,
+                None => {
+                    self.action_types.insert(at.to_string(), 1);
+                }
+            }
+        }
+        if let Some(hour) = ev
+            .event_time_parsed()
+            .and_then(|dt| dt.date().and_hms_opt(dt.hour(), 0, 0))
+        {
+            self.hours.insert(hour);
+        }
+    }
+
+    /// action types with event counts, sorted by name (same as `unique_action_types`)
+    pub fn action_types(&self) -> Vec<(String, usize)> {
+        self.action_types
+            .iter()
+            .map(|(at, &n)| (at.clone(), n))
+            .collect()
+    }
+
+    /// sorted unique dates in `zone`
+    pub fn dates(&self, zone: &DisplayZone) -> Vec<NaiveDate> {
+        let set: BTreeSet<NaiveDate> = self.local_hours(zone).map(|t| t.date()).collect();
+        set.into_iter().collect()
+    }
+
+    /// sorted hours seen on `date` in `zone`
+    pub fn hours_for_date(&self, zone: &DisplayZone, date: NaiveDate) -> Vec<u32> {
+        let set: BTreeSet<u32> = self
+            .local_hours(zone)
+            .filter(|t| t.date() == date)
+            .map(|t| t.hour())
+            .collect();
+        set.into_iter().collect()
+    }
+
+    /// wall-clock hours in `zone` touched by the utc hours (a half-hour offset spans two)
+    fn local_hours<'a>(
+        &'a self,
+        zone: &'a DisplayZone,
+    ) -> impl Iterator<Item = NaiveDateTime> + 'a {
+        self.hours.iter().flat_map(move |&h| {
+            [h, h + Duration::minutes(59)].map(|t| {
+                let local = zone.to_local(t);
+                local
+                    .date()
+                    .and_hms_opt(local.hour(), 0, 0)
+                    .unwrap_or(local)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::event;
+
+    #[test]
+    fn action_type_include_exclude() {
+        let ev = |at: &str| event(&[("Action Type", at)]);
+        let events = [
+            ev("ProcessCreated"),
+            ev("ImageLoaded"),
+            ev("ProcessCreated"),
+            ev("FileCreated"),
+        ];
+        assert_eq!(
+            unique_action_types(&events),
+            [
+                ("FileCreated".to_string(), 1),
+                ("ImageLoaded".to_string(), 1),
+                ("ProcessCreated".to_string(), 2)
+            ]
+        );
+        let mut f = ActionTypeFilter::default();
+        f.cycle("ProcessCreated");
+        f.cycle("ImageLoaded");
+        assert_eq!(filter_events(&events, &f, "").len(), 3);
+        f.cycle("ImageLoaded");
+        assert_eq!(f.mark("ImageLoaded"), Mark::Exclude);
+        assert_eq!(f.describe(), "ProcessCreated, not ImageLoaded");
+        assert_eq!(filter_events(&events, &f, "").len(), 2);
+        f.cycle("ProcessCreated");
+        f.cycle("ProcessCreated");
+        assert_eq!(filter_events(&events, &f, "").len(), 3);
+    }
+}
@@ -0,0 +1,188 @@
+// event density over time: counts per time bucket, stacked by action type, with a bucket size
+// picked to fit the columns available
+
+use chrono::{DateTime, NaiveDateTime, TimeDelta};
+use std::collections::HashMap;
+
+/// action types stacked on their own; the rest are summed as "other"
+pub const STACKED: usize = 5;
+
+/// bucket sizes to choose from, in seconds (1s up to 30 days; longer spans use whole days)
+const BUCKET_SECS: [i64; 17] = [
+    1, 5, 10, 30, 60, 300, 600, 900, 1800, 3600, 7200, 10800, 21600, 43200, 86400, 604800, 2592000,
+];
+
+/// event counts per bucket; buckets are aligned to whole multiples of their size
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    /// start of the first bucket
+    pub start: NaiveDateTime,
+    pub bucket: TimeDelta,
+    /// stacked series, most events first ("other" last when there is one)
+    pub series: Vec<String>,
+    /// per bucket: count per series
+    pub counts: Vec<Vec<u32>>,
+}
+
+impl Histogram {
+    /// `events` as (time, action type), in any order, in at most `max_buckets` buckets
+    pub fn build(events: &[(NaiveDateTime, &str)], max_buckets: usize) -> Self {
+        let max_buckets = max_buckets.max(1) as i64;
+        let secs = |t: &NaiveDateTime| t.and_utc().timestamp();
+        let (Some(first), Some(last)) = (
+            events.iter().map(|(t, _)| secs(t)).min(),
+            events.iter().map(|(t, _)| secs(t)).max(),
+        ) else {
+            return Self::default();
+        };
+        let buckets_of = |size: i64| last.div_euclid(size) - first.div_euclid(size) + 1;
+        let size = BUCKET_SECS
+            .iter()
+            .copied()
+            .find(|&s| buckets_of(s) <= max_buckets)
+            .unwrap_or_else(|| {
+                let days = (last - first) / 86400 / max_buckets + 1;
+                (days..)
+                    .map(|d| d * 86400)
+                    .find(|&s| buckets_of(s) <= max_buckets)
+                    .unwrap_or(86400)
+            });
+        let start = first.div_euclid(size) * size;
+
+        let mut totals: HashMap<&str, usize> = HashMap::new();
+        for (_, action_type) in events {
+            *totals.entry(action_type).or_default() += 1;
+        }
+        let mut ranked: Vec<(&str, usize)> = totals.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        // one type left over is shown as itself rather than as "other"
+        let stacked = if ranked.len() > STACKED + 1 {
+            STACKED
+        } else {
+            ranked.len()
+        };
+        let mut series: Vec<String> = ranked[..stacked]
+            .iter()
+            .map(|(name, _)| display_name(name).to_string())
+            .collect();
+        if ranked.len() > stacked {
+            series.push("other".to_string());
+        }
+        let slot: HashMap<&str, usize> = ranked
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| (*name, i.min(stacked)))
+            .collect();
+
+        let mut counts = vec![vec![0; series.len()]; buckets_of(size) as usize];
+        for (t, action_type) in events {
+            let i = (secs(t) - start) / size;
+            counts[i as usize][slot[action_type]] += 1;
+        }
+        Self {
+            start: DateTime::from_timestamp(start, 0)
+                .unwrap_or_default()
+                .naive_utc(),
+            bucket: TimeDelta::seconds(size),
+            series,
+            counts,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// events in bucket `i`
+    pub fn total(&self, i: usize) -> u32 {
+        self.counts.get(i).map_or(0, |c| c.iter().sum())
+    }
+
+    /// events in the fullest bucket
+    pub fn max(&self) -> u32 {
+        (0..self.len()).map(|i| self.total(i)).max().unwrap_or(0)
+    }
+
+    /// bucket `i` as [start, end)
+    pub fn range(&self, i: usize) -> (NaiveDateTime, NaiveDateTime) {
+        let start = self.start + self.bucket * i as i32;
+        (start, start + self.bucket)
+    }
+
+    /// the bucket `t` falls in
+    pub fn bucket_of(&self, t: NaiveDateTime) -> Option<usize> {
+        if self.is_empty() || t < self.start {
+            return None;
+        }
+        let i = ((t - self.start).num_seconds() / self.bucket.num_seconds()) as usize;
+        (i < self.len()).then_some(i)
+    }
+
+    /// bucket size for display: `30s`, `5m`, `3h`, `7d`
+    pub fn bucket_label(&self) -> String {
+        let s = self.bucket.num_seconds();
+        match s {
+            _ if s % 86400 == 0 => format!("{}d", s / 86400),
+            _ if s % 3600 == 0 => format!("{}h", s / 3600),
+            _ if s % 60 == 0 => format!("{}m", s / 60),
+            _ => format!("{}s", s),
+        }
+    }
+}
+
+fn display_name(action_type: &str) -> &str {
+    if action_type.is_empty() {
+        "(no action type)"
+    } else {
+        action_type
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::parse_time;
+
+    #[test]
+    fn buckets_fit_and_stack_by_action_type() {
+        let at = |s: &str| parse_time(s).unwrap();
+        let mut events = vec![
+            (at("2024-05-01T03:12:05Z"), "ProcessCreated"),
+            (at("2024-05-01T03:12:40Z"), "ProcessCreated"),
+            (at("2024-05-01T03:14:00Z"), "FileCreated"),
+            (at("2024-05-01T03:40:00Z"), ""),
+        ];
+        let h = Histogram::build(&events, 40);
+        assert_eq!(h.bucket_label(), "1m");
+        assert_eq!(h.start, at("2024-05-01T03:12:00Z"));
+        assert_eq!(h.len(), 29);
+        assert_eq!(
+            h.series,
+            ["ProcessCreated", "(no action type)", "FileCreated"]
+        );
+        assert_eq!(h.counts[0], [2, 0, 0]);
+        assert_eq!(h.max(), 2);
+        assert_eq!(h.bucket_of(at("2024-05-01T03:14:59Z")), Some(2));
+        assert_eq!(
+            h.range(2),
+            (at("2024-05-01T03:14:00Z"), at("2024-05-01T03:15:00Z"))
+        );
+
+        for (i, name) in ["A", "B", "C", "D", "E", "F"].iter().enumerate() {
+            for _ in 0..=i {
+                events.push((at("2024-05-03T00:00:00Z"), name));
+            }
+        }
+        let h = Histogram::build(&events, 10);
+        assert_eq!(h.bucket_label(), "6h");
+        assert_eq!(h.series.len(), STACKED + 1);
+        assert_eq!(h.series[0], "F");
+        assert_eq!(h.series[STACKED], "other");
+        assert_eq!(h.total(h.len() - 1), 21);
+        assert!(Histogram::build(&[], 10).is_empty());
+    }
+}
@@ -0,0 +1,597 @@
+// inverted index built while rows load: lowercased tokens of every field -> rows holding them,
+// byte trigrams -> tokens holding them, and action type -> rows. search and filters are answered
+// from these postings; only rows the index cannot decide are read back and checked against the
+// query.
+
+use crate::timeline::TimelineEvent;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// tokens longer than this are not kept in the dictionary (base64 blobs, long command-line
+/// arguments); their rows are listed in `SearchIndex::unlisted` and always checked
+const MAX_TOKEN_LEN: usize = 64;
+
+/// distinct tokens kept in the dictionary (up to about 150 MiB with their trigrams);
+/// rows with a token first seen past it are listed in `SearchIndex::unlisted` and always checked
+const MAX_TOKENS: usize = 1 << 20;
+
+/// hashes (32 hex digits or more) and GUIDs: nearly every one is distinct and they are looked
+/// up whole, so they are not listed under their trigrams
+fn is_opaque(tok: &str) -> bool {
+    let b = tok.as_bytes();
+    let guid = b.len() == 36
+        && b.iter().enumerate().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => *c == b'-',
+            _ => c.is_ascii_hexdigit(),
+        });
+    guid || (b.len() >= 32 && b.iter().all(u8::is_ascii_hexdigit))
+}
+
+/// characters that belong to a token; everything else (spaces, path separators, quotes,
+/// brackets, `:` `,` `=` …) splits. keeps `cmd.exe`, `alice@contoso.com` and hashes whole.
+pub fn is_token_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '.' | '_' | '-' | '@' | '$' | '#' | '~' | '+' | '%')
+}
+
+/// tokens of `text` (not lowercased here)
+pub fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !is_token_char(c))
+        .filter(|t| !t.is_empty())
+}
+
+/// row numbers held by one token; most tokens (hashes, ids) occur in a single row
+#[derive(Debug, Clone)]
+enum Postings {
+    One(u32),
+    Many(Vec<u32>),
+}
+
+impl Postings {
+    fn push(&mut self, row: u32) {
+        match self {
+            Postings::One(r) if *r == row => {}
+            Postings::One(r) => *self = Postings::Many(vec![*r, row]),
+            Postings::Many(v) if v.last() == Some(&row) => {}
+            Postings::Many(v) => v.push(row),
+        }
+    }
+
+    fn rows(&self) -> &[u32] {
+        match self {
+            Postings::One(r) => std::slice::from_ref(r),
+            Postings::Many(v) => v,
+        }
+    }
+
+    fn remap(&mut self, new_of_old: &[u32]) {
+        match self {
+            Postings::One(r) => *r = new_of_old[*r as usize],
+            Postings::Many(v) => {
+                for r in v.iter_mut() {
+                    *r = new_of_old[*r as usize];
+                }
+                v.sort_unstable();
+            }
+        }
+    }
+}
+
+/// postings for all rows added so far (row = global index in the event store). the dictionary
+/// grows with every distinct token, and each token is listed once under every trigram it holds,
+/// so it costs about five times its length in bytes on top of its rows (see `heap_bytes`).
+/// hashes and GUIDs, the bulk of the distinct tokens in a timeline, skip the trigrams and cost
+/// about their length; a search for part of one scans them instead. past `MAX_TOKENS` new
+/// tokens are not kept, so the dictionary stops growing and only the row lists do.
+#[derive(Debug)]
+pub struct SearchIndex {
+    /// token -> id (position in `tokens`)
+    ids: HashMap<Arc<str>, u32>,
+    tokens: Vec<(Arc<str>, Postings)>,
+    /// byte trigram -> ids of the tokens holding it, ascending
+    grams: HashMap<[u8; 3], Vec<u32>>,
+    /// ids of tokens shorter than a trigram
+    short: Vec<u32>,
+    /// ids of hashes and GUIDs, not listed under their trigrams (see `is_opaque`)
+    opaque: Vec<u32>,
+    action_types: HashMap<Box<str>, Vec<u32>>,
+    /// rows with a token not in the dictionary (over `MAX_TOKEN_LEN`, or past `max_tokens`)
+    unlisted: Vec<u32>,
+    len: usize,
+    max_tokens: usize,
+}
+
+impl Default for SearchIndex {
+    fn default() -> Self {
+        Self {
+            ids: HashMap::new(),
+            tokens: Vec::new(),
+            grams: HashMap::new(),
+            short: Vec::new(),
+            opaque: Vec::new(),
+            action_types: HashMap::new(),
+            unlisted: Vec::new(),
+            len: 0,
+            max_tokens: MAX_TOKENS,
+        }
+    }
+}
+
+impl SearchIndex {
+    /// index row `row` (rows arrive in increasing order)
+    pub fn add(&mut self, row: usize, ev: &TimelineEvent) {
+        let r = row as u32;
+        for tok in tokens(&ev.searchable_text()) {
+            match self.ids.get(tok) {
+                Some(&id) => self.tokens[id as usize].1.push(r),
+                None if tok.len() <= MAX_TOKEN_LEN && self.tokens.len() < self.max_tokens => {
+                    self.insert_token(tok, r)
+                }
+                None => {
+                    if self.unlisted.last() != Some(&r) {
+                        self.unlisted.push(r);
+                    }
+                }
+            }
+        }
+        let at = ev.action_type.as_deref().unwrap_or("");
+        match self.action_types.get_mut(at) {
+            Some(v) => v.push(r),
+            None => {
+                self.action_types.insert(at.into(), vec![r]);
+            }
+        }
+        self.len = self.len.max(row + 1);
+    }
+
+    /// new dictionary token, first seen in row `row`
+    fn insert_token(&mut self, tok: &str, row: u32) {
+        let id = self.tokens.len() as u32;
+        let tok: Arc<str> = tok.into();
+        self.ids.insert(tok.clone(), id);
+        if tok.len() < 3 {
+            self.short.push(id);
+        }
+        if is_opaque(&tok) {
+            self.opaque.push(id);
+        } else {
+            for w in tok.as_bytes().windows(3) {
+                let ids = self.grams.entry([w[0], w[1], w[2]]).or_default();
+                if ids.last() != Some(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+        self.tokens.push((tok, Postings::One(row)));
+    }
+
+    /// rows renumbered (see `EventStore::sort_by_time`): old row `i` is now `new_of_old[i]`
+    pub fn remap(&mut self, new_of_old: &[u32]) {
+        for (_, p) in self.tokens.iter_mut() {
+            p.remap(new_of_old);
+        }
+        for v in self.action_types.values_mut() {
+            for r in v.iter_mut() {
+                *r = new_of_old[*r as usize];
+            }
+            v.sort_unstable();
+        }
+        for r in self.unlisted.iter_mut() {
+            *r = new_of_old[*r as usize];
+        }
+        self.unlisted.sort_unstable();
+    }
+
+    /// rows indexed
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// distinct tokens in the dictionary
+    pub fn token_count(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// rough heap size of the dictionary, trigrams and postings in bytes
+    pub fn heap_bytes(&self) -> usize {
+        const WORD: usize = std::mem::size_of::<usize>();
+        let tokens: usize = self
+            .tokens
+            .iter()
+            .map(|(tok, p)| {
+                // the string, its map entry and slot, and the rows past the first
+                let rows = match p {
+                    Postings::One(_) => 0,
+                    Postings::Many(v) => v.capacity() * 4,
+                };
+                tok.len() + 2 * WORD + 4 * WORD + rows
+            })
+            .sum();
+        let grams: usize = self
+            .grams
+            .values()
+            .map(|ids| ids.capacity() * 4 + 4 * WORD)
+            .sum();
+        let rows: usize = self
+            .action_types
+            .values()
+            .map(|v| v.capacity() * 4)
+            .sum::<usize>()
+            + self.unlisted.capacity() * 4;
+        tokens + grams + rows + (self.short.capacity() + self.opaque.capacity()) * 4
+    }
+
+    /// rows with exactly this action type ("" for none)
+    pub fn action_type(&self, action_type: &str) -> Bits {
+        let mut bits = Bits::new(self.len);
+        for &r in self.action_types.get(action_type).into_iter().flatten() {
+            bits.insert(r as usize);
+        }
+        bits
+    }
+
+    /// every action type ("" for none) with its rows
+    pub fn action_types(&self) -> impl Iterator<Item = (&str, &[u32])> {
+        self.action_types
+            .iter()
+            .map(|(at, rows)| (at.as_ref(), rows.as_slice()))
+    }
+
+    /// candidates for "some field contains `needle`" (lowercase): rows with a token containing
+    /// it are sure when `needle` is a single token, otherwise only possible
+    pub fn containing(&self, needle: &str) -> Candidates {
+        let parts: Vec<&str> = tokens(needle).collect();
+        if parts.is_empty() {
+            return Candidates::unknown(self.len);
+        }
+        let unlisted = self.unlisted_rows();
+        if parts.len() == 1 && parts[0] == needle {
+            let sure = self.token_rows(needle);
+            let mut maybe = unlisted;
+            maybe.and_not(&sure);
+            return Candidates { sure, maybe };
+        }
+        let mut maybe = Bits::full(self.len);
+        for part in &parts {
+            maybe.and(&self.token_rows(part));
+        }
+        maybe.or(&unlisted);
+        Candidates {
+            sure: Bits::new(self.len),
+            maybe,
+        }
+    }
+
+    /// rows with a dictionary token containing `part`
+    fn token_rows(&self, part: &str) -> Bits {
+        let mut bits = Bits::new(self.len);
+        for id in self.tokens_containing(part) {
+            for &r in self.tokens[id as usize].1.rows() {
+                bits.insert(r as usize);
+            }
+        }
+        bits
+    }
+
+    /// ids of the dictionary tokens containing `part`, found through the trigrams: only the
+    /// tokens under the rarest trigram of `part` are checked, and a `part` shorter than a
+    /// trigram takes every trigram holding it (their number is bounded by the alphabet, not
+    /// the data) plus the tokens too short for one. hashes and GUIDs are scanned, and only
+    /// when `part` could be inside one.
+    fn tokens_containing(&self, part: &str) -> Vec<u32> {
+        let holding = |id: &u32| self.tokens[*id as usize].0.contains(part);
+        let bytes = part.as_bytes();
+        let opaque: &[u32] = if bytes.iter().all(|c| c.is_ascii_hexdigit() || *c == b'-') {
+            &self.opaque
+        } else {
+            &[]
+        };
+        let mut ids: Vec<u32> = if bytes.len() >= 3 {
+            let rarest = bytes
+                .windows(3)
+                .map(|w| self.grams.get(w).map_or(&[][..], Vec::as_slice))
+                .min_by_key(|ids| ids.len())
+                .unwrap_or_default();
+            rarest.iter().copied().filter(holding).collect()
+        } else {
+            self.grams
+                .iter()
+                .filter(|(gram, _)| gram.windows(bytes.len()).any(|w| w == bytes))
+                .flat_map(|(_, ids)| ids.iter().copied())
+                .chain(self.short.iter().copied().filter(holding))
+                .collect()
+        };
+        ids.extend(opaque.iter().copied().filter(holding));
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    fn unlisted_rows(&self) -> Bits {
+        let mut bits = Bits::new(self.len);
+        for &r in &self.unlisted {
+            bits.insert(r as usize);
+        }
+        bits
+    }
+}
+
+/// rows that surely match and rows that may match (must be read and checked); anything in
+/// neither cannot match
+#[derive(Debug, Clone)]
+pub struct Candidates {
+    pub sure: Bits,
+    pub maybe: Bits,
+}
+
+impl Candidates {
+    /// every row sure
+    pub fn all(len: usize) -> Self {
+        Self {
+            sure: Bits::full(len),
+            maybe: Bits::new(len),
+        }
+    }
+
+    /// the index cannot tell: every row has to be checked
+    pub fn unknown(len: usize) -> Self {
+        Self {
+            sure: Bits::new(len),
+            maybe: Bits::full(len),
+        }
+    }
+
+    /// nothing sure any more: every candidate has to be checked
+    pub fn possible(mut self) -> Self {
+        self.maybe.or(&self.sure);
+        self.sure = Bits::new(self.sure.len);
+        self
+    }
+
+    pub fn and(mut self, other: &Self) -> Self {
+        let mut possible = self.sure.clone();
+        possible.or(&self.maybe);
+        let mut other_possible = other.sure.clone();
+        other_possible.or(&other.maybe);
+        possible.and(&other_possible);
+        self.sure.and(&other.sure);
+        possible.and_not(&self.sure);
+        self.maybe = possible;
+        self
+    }
+
+    pub fn or(mut self, other: &Self) -> Self {
+        self.sure.or(&other.sure);
+        self.maybe.or(&other.maybe);
+        self.maybe.and_not(&self.sure);
+        self
+    }
+
+    pub fn negate(self) -> Self {
+        let mut sure = self.sure;
+        sure.or(&self.maybe);
+        sure.negate();
+        Self {
+            sure,
+            maybe: self.maybe,
+        }
+    }
+}
+
+/// fixed-size bit set over rows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bits {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bits {
+    /// empty set over `len` rows
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    pub fn full(len: usize) -> Self {
+        let mut bits = Self::new(len);
+        bits.negate();
+        bits
+    }
+
+    /// rows covered (set or not)
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn insert(&mut self, i: usize) {
+        if i < self.len {
+            self.words[i / 64] |= 1 << (i % 64);
+        }
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        i < self.len && self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    pub fn and(&mut self, other: &Self) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a &= b;
+        }
+    }
+
+    pub fn or(&mut self, other: &Self) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a |= b;
+        }
+    }
+
+    pub fn and_not(&mut self, other: &Self) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a &= !b;
+        }
+    }
+
+    pub fn negate(&mut self) {
+        for w in &mut self.words {
+            *w = !*w;
+        }
+        if !self.len.is_multiple_of(64) {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1 << (self.len % 64)) - 1;
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// set rows in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(wi, &w)| {
+            let mut w = w;
+            std::iter::from_fn(move || {
+                if w == 0 {
+                    return None;
+                }
+                let b = w.trailing_zeros() as usize;
+                w &= w - 1;
+                Some(wi * 64 + b)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::Query;
+    use crate::test_util::event;
+
+    #[test]
+    fn candidates_agree_with_linear_scan() {
+        let ev = |at: &str, file: &str, cmd: &str| {
+            event(&[
+                ("Action Type", at),
+                ("File Name", file),
+                ("Process Command Line", cmd),
+            ])
+        };
+        let blob = "A".repeat(MAX_TOKEN_LEN + 10);
+        let events = [
+            ev("ProcessCreated", "cmd.exe", "cmd /c whoami"),
+            ev(
+                "ProcessCreated",
+                "powershell.exe",
+                &format!("powershell -enc {blob}"),
+            ),
+            ev(
+                "FileCreated",
+                "notes.txt",
+                "\"c:\\program files\\app\\app.exe\"",
+            ),
+            ev("ConnectionSuccess", "svchost.exe", ""),
+            ev(
+                "FileCreated",
+                "d41d8cd98f00b204e9800998ecf8427e",
+                "{3f2504e0-4f89-11d3-9a0c-0305e82c3301}",
+            ),
+        ];
+        let mut index = SearchIndex::default();
+        for (i, e) in events.iter().enumerate() {
+            index.add(i, e);
+        }
+        for q in [
+            "cmd",
+            "md.ex",
+            "cmd.exe",
+            "program files",
+            "\"c:\\program files\"",
+            "power*enc",
+            "aaaa",
+            "file_name:cmd.exe",
+            "action_type:Process*",
+            "NOT cmd",
+            "exe AND NOT (whoami OR svchost)",
+            "re:who.mi",
+            "d41d8cd98f00b204e9800998ecf8427e",
+            "8f00b2",
+            "9a0c-0305",
+            "md",
+            "e",
+            "",
+        ] {
+            let query = Query::parse(q, &[]).unwrap();
+            let found = query.candidates(&index);
+            for (i, e) in events.iter().enumerate() {
+                let hit = query.matches(e);
+                assert!(hit || !found.sure.contains(i), "{q}: row {i} wrongly sure");
+                assert!(
+                    !hit || found.sure.contains(i) || found.maybe.contains(i),
+                    "{q}: row {i} missed"
+                );
+            }
+        }
+        let exact = Query::parse("whoami", &[]).unwrap().candidates(&index);
+        assert_eq!(exact.sure.iter().collect::<Vec<_>>(), vec![0]);
+        assert_eq!(exact.maybe.iter().collect::<Vec<_>>(), vec![1]);
+        assert_eq!(
+            index.action_type("FileCreated").iter().collect::<Vec<_>>(),
+            vec![2, 4]
+        );
+
+        // the trigram lookup finds what a scan of the whole dictionary finds
+        for part in [
+            "c", "md", "/c", "cmd", "d.e", "exe", "whoami", "zzz", "é", "d4", "8f00b", "11d3-9a",
+        ] {
+            let mut scanned: Vec<u32> = (0..index.tokens.len() as u32)
+                .filter(|&id| index.tokens[id as usize].0.contains(part))
+                .collect();
+            scanned.sort_unstable();
+            assert_eq!(index.tokens_containing(part), scanned, "{part}");
+        }
+
+        assert!(index
+            .grams
+            .values()
+            .flatten()
+            .all(|&id| !index.opaque.contains(&id)));
+
+        index.remap(&[4, 3, 2, 1, 0]);
+        let moved = Query::parse("whoami", &[]).unwrap().candidates(&index);
+        assert_eq!(moved.sure.iter().collect::<Vec<_>>(), vec![4]);
+        assert_eq!(
+            index.action_type("FileCreated").iter().collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+    }
+
+    #[test]
+    fn capped_dictionary_checks_rows_past_it() {
+        let ev = |cmd: &str| event(&[("Process Command Line", cmd)]);
+        let events = [ev("cmd /c whoami"), ev("cmd /c hostname"), ev("net user")];
+        let mut index = SearchIndex {
+            max_tokens: 3,
+            ..SearchIndex::default()
+        };
+        for (i, e) in events.iter().enumerate() {
+            index.add(i, e);
+        }
+        assert_eq!(index.token_count(), 3);
+        let whoami = Query::parse("whoami", &[]).unwrap().candidates(&index);
+        assert_eq!(whoami.sure.iter().collect::<Vec<_>>(), vec![0]);
+        assert_eq!(whoami.maybe.iter().collect::<Vec<_>>(), vec![1, 2]);
+        let user = Query::parse("user", &[]).unwrap().candidates(&index);
+        assert!(user.sure.is_empty());
+        assert_eq!(user.maybe.iter().collect::<Vec<_>>(), vec![1, 2]);
+    }
+}
@@ -0,0 +1,565 @@
+// timeline inputs: plain file, gzip stream, or a member of a zip archive (sniffed by magic bytes),
+// holding csv or json (sniffed from the first decompressed byte)
+
+use crate::error::Result;
+use anyhow::{anyhow, bail};
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const ZIP_EMPTY_MAGIC: &[u8] = b"PK\x05\x06";
+
+/// how the bytes of an input file are packed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zip,
+}
+
+/// what the (decompressed) bytes of an input hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    /// json array, api envelope object, or json lines
+    Json,
+}
+
+/// a timeline file plus, for zip archives, which member to read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    pub path: PathBuf,
+    /// zip member name; None picks the archive's only timeline
+    pub member: Option<String>,
+}
+
+impl Source {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            member: None,
+        }
+    }
+
+    pub fn with_member(path: impl Into<PathBuf>, member: Option<String>) -> Self {
+        Self {
+            path: path.into(),
+            member,
+        }
+    }
+
+    /// short name for list columns and filters: the zip member, else the file name
+    pub fn label(&self) -> String {
+        match &self.member {
+            Some(m) => m.clone(),
+            None => self
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| self.path.display().to_string()),
+        }
+    }
+
+    /// the path as given, plus the zip member
+    fn full_name(&self) -> String {
+        match &self.member {
+            Some(m) => format!("{}/{}", self.path.display(), m),
+            None => self.path.display().to_string(),
+        }
+    }
+}
+
+/// labels that tell `sources` apart: `Source::label`, with the parent directory (or, for a
+/// zip member, the archive) name in front where two would read the same, and the full path
+/// where even that clashes
+pub fn unique_labels(sources: &[Source]) -> Vec<String> {
+    let clashes = |names: &[String], i: usize| names.iter().filter(|&n| *n == names[i]).count() > 1;
+    let short: Vec<String> = sources.iter().map(Source::label).collect();
+    let longer: Vec<String> = sources
+        .iter()
+        .zip(&short)
+        .map(|(s, label)| {
+            let outer = match s.member {
+                Some(_) => s.path.file_name(),
+                None => s.path.parent().and_then(Path::file_name),
+            };
+            match outer {
+                Some(outer) => format!("{}/{}", outer.to_string_lossy(), label),
+                None => label.clone(),
+            }
+        })
+        .collect();
+    let picked: Vec<String> = (0..sources.len())
+        .map(|i| {
+            if !clashes(&short, i) {
+                short[i].clone()
+            } else if !clashes(&longer, i) {
+                longer[i].clone()
+            } else {
+                sources[i].full_name()
+            }
+        })
+        .collect();
+    // a longer label can still read like another source's short one
+    (0..sources.len())
+        .map(|i| {
+            if clashes(&picked, i) {
+                sources[i].full_name()
+            } else {
+                picked[i].clone()
+            }
+        })
+        .collect()
+}
+
+/// expand command-line inputs into sources: directories contribute their timeline files,
+/// glob patterns are matched, and a zip contributes `member`, or without it every csv/json
+/// member (`member` applies to zip inputs only, so one of them must be a zip)
+pub fn expand_inputs(inputs: &[PathBuf], member: Option<&str>) -> Result<Vec<Source>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let text = input.to_string_lossy();
+        if !input.exists() && text.contains(['*', '?', '[']) {
+            let before = files.len();
+            for entry in glob::glob(&text)? {
+                let p = entry?;
+                if p.is_file() {
+                    files.push(p);
+                }
+            }
+            if files.len() == before {
+                bail!("{}: pattern matched no files", text);
+            }
+        } else if input.is_dir() {
+            let mut found: Vec<PathBuf> = std::fs::read_dir(input)?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && is_timeline_file_name(p))
+                .collect();
+            found.sort();
+            if found.is_empty() {
+                bail!(
+                    "{}: no .csv, .json, .jsonl, .gz or .zip files in directory",
+                    input.display()
+                );
+            }
+            files.extend(found);
+        } else {
+            files.push(input.clone());
+        }
+    }
+    let mut out: Vec<Source> = Vec::new();
+    let mut zips = 0;
+    for path in files {
+        if detect(&path)? != Compression::Zip {
+            out.push(Source::new(path));
+            continue;
+        }
+        zips += 1;
+        if member.is_none() {
+            let members = zip_timeline_members(&path)?;
+            if members.is_empty() {
+                bail!(
+                    "{}: zip archive contains no .csv or .json member",
+                    path.display()
+                );
+            }
+            for m in members {
+                out.push(Source::with_member(&path, Some(m)));
+            }
+        } else {
+            out.push(Source::with_member(path, member.map(str::to_string)));
+        }
+    }
+    if let (Some(m), 0) = (member, zips) {
+        bail!(
+            "--member {:?} given, but none of the inputs is a zip archive",
+            m
+        );
+    }
+    out.dedup();
+    Ok(out)
+}
+
+fn is_timeline_file_name(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    [".gz", ".zip"].iter().any(|ext| name.ends_with(ext)) || is_timeline_name(&name)
+}
+
+/// raw size of a source: file length, or compressed size of the zip member
+pub fn raw_size(source: &Source) -> Result<u64> {
+    with_reader(source, |_, total, _| Ok(total))
+}
+
+/// detect compression from the first bytes of the file (extension is ignored)
+pub fn detect(path: &Path) -> Result<Compression> {
+    let mut head = [0u8; 4];
+    let mut f = File::open(path)?;
+    let mut n = 0;
+    while n < head.len() {
+        match f.read(&mut head[n..])? {
+            0 => break,
+            k => n += k,
+        }
+    }
+    let head = &head[..n];
+    Ok(if head.starts_with(GZIP_MAGIC) {
+        Compression::Gzip
+    } else if head.starts_with(ZIP_MAGIC) || head.starts_with(ZIP_EMPTY_MAGIC) {
+        Compression::Zip
+    } else {
+        Compression::None
+    })
+}
+
+/// csv/json members of a zip archive, in archive order
+pub fn zip_timeline_members(path: &Path) -> Result<Vec<String>> {
+    let archive = zip::ZipArchive::new(File::open(path)?)?;
+    Ok(archive
+        .file_names()
+        .filter(|n| is_timeline_name(n))
+        .map(str::to_string)
+        .collect())
+}
+
+fn is_timeline_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    !name.ends_with('/')
+        && [".csv", ".json", ".jsonl", ".ndjson"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+}
+
+/// csv or json, from the first non-blank byte of the decompressed stream (a utf-8 bom is skipped);
+/// nothing is consumed
+pub fn sniff_format(reader: &mut dyn BufRead) -> Result<Format> {
+    let head = reader.fill_buf()?;
+    let head = head.strip_prefix(b"\xef\xbb\xbf").unwrap_or(head);
+    Ok(match head.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'[') | Some(b'{') => Format::Json,
+        _ => Format::Csv,
+    })
+}
+
+/// format of `source` (opens it and peeks at the first bytes)
+pub fn detect_format(source: &Source) -> Result<Format> {
+    with_reader(source, |reader, _, _| {
+        sniff_format(&mut BufReader::new(reader))
+    })
+}
+
+/// count of raw (possibly compressed) bytes consumed from the input file, for progress
+#[derive(Debug, Clone, Default)]
+pub struct ByteCounter(Rc<Cell<u64>>);
+
+impl ByteCounter {
+    pub fn get(&self) -> u64 {
+        self.0.get()
+    }
+
+    fn reset(&self) {
+        self.0.set(0);
+    }
+}
+
+/// counts the bytes read through it into a `ByteCounter`
+pub(crate) struct CountingReader<R> {
+    inner: R,
+    counter: ByteCounter,
+}
+
+impl<R> CountingReader<R> {
+    pub(crate) fn new(inner: R, counter: &ByteCounter) -> Self {
+        Self {
+            inner,
+            counter: counter.clone(),
+        }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.counter.0.set(self.counter.0.get() + n as u64);
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// open `source` as a decompressed, utf-8 byte stream and hand it to `f` together with the
+/// total raw size and a counter of raw bytes consumed so far. utf-16 input (by bom) is
+/// transcoded on the fly; nothing is written to disk.
+pub fn with_reader<T>(
+    source: &Source,
+    f: impl FnOnce(&mut dyn BufRead, u64, &ByteCounter) -> Result<T>,
+) -> Result<T> {
+    let path = source.path.as_path();
+    let counter = ByteCounter::default();
+    let file = File::open(path)?;
+    let total = file.metadata()?.len();
+    let mut raw = CountingReader::new(file, &counter);
+    match detect(path)? {
+        Compression::None => decoded(&mut raw, total, &counter, f),
+        Compression::Gzip => {
+            let mut gz = flate2::read::MultiGzDecoder::new(raw);
+            decoded(&mut gz, total, &counter, f)
+        }
+        Compression::Zip => {
+            let mut archive = zip::ZipArchive::new(raw)?;
+            let name = pick_member(&archive, path, source.member.as_deref())?;
+            let mut member = archive.by_name(&name)?;
+            // directory lookups above consumed bytes too; count only member data from here
+            counter.reset();
+            let size = member.compressed_size();
+            decoded(&mut member, size, &counter, f)
+        }
+    }
+}
+
+/// text encoding of a (decompressed) stream, from its byte order mark
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// utf-8, with or without bom (the bom is left for the readers to skip)
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+pub fn sniff_encoding(head: &[u8]) -> Encoding {
+    if head.starts_with(&[0xff, 0xfe]) {
+        Encoding::Utf16Le
+    } else if head.starts_with(&[0xfe, 0xff]) {
+        Encoding::Utf16Be
+    } else {
+        Encoding::Utf8
+    }
+}
+
+/// encoding of an uncompressed file, from its first bytes
+pub fn detect_encoding(path: &Path) -> Result<Encoding> {
+    let mut head = Vec::with_capacity(2);
+    File::open(path)?.take(2).read_to_end(&mut head)?;
+    Ok(sniff_encoding(&head))
+}
+
+/// buffer `reader` and, for utf-16, transcode it to utf-8 before handing it to `f`
+fn decoded<T>(
+    reader: &mut dyn Read,
+    total: u64,
+    counter: &ByteCounter,
+    f: impl FnOnce(&mut dyn BufRead, u64, &ByteCounter) -> Result<T>,
+) -> Result<T> {
+    let mut buffered = BufReader::new(reader);
+    match sniff_encoding(buffered.fill_buf()?) {
+        Encoding::Utf8 => f(&mut buffered, total, counter),
+        enc => {
+            buffered.consume(2);
+            let utf16 = Utf16Reader {
+                inner: buffered,
+                big_endian: enc == Encoding::Utf16Be,
+                raw: vec![0; 8192],
+                odd: None,
+                high: None,
+                out: Vec::new(),
+                pos: 0,
+            };
+            f(&mut BufReader::new(utf16), total, counter)
+        }
+    }
+}
+
+/// utf-16 (bom already consumed) transcoded to utf-8 as it is read; unpaired surrogates
+/// become U+FFFD
+struct Utf16Reader<R> {
+    inner: R,
+    big_endian: bool,
+    raw: Vec<u8>,
+    /// odd byte left over from the last raw read
+    odd: Option<u8>,
+    /// high surrogate waiting for its pair from the next raw read
+    high: Option<u16>,
+    out: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> Utf16Reader<R> {
+    /// decode the next raw chunk into `out`; false at end of input
+    fn refill(&mut self) -> io::Result<bool> {
+        let n = self.inner.read(&mut self.raw)?;
+        self.out.clear();
+        self.pos = 0;
+        if n == 0 {
+            if self.high.take().is_some() || self.odd.take().is_some() {
+                self.out.extend_from_slice("\u{fffd}".as_bytes());
+                return Ok(true);
+            }
+            return Ok(false);
+        }
+        let mut bytes: Vec<u8> = self.odd.take().into_iter().collect();
+        bytes.extend_from_slice(&self.raw[..n]);
+        if bytes.len() % 2 == 1 {
+            self.odd = bytes.pop();
+        }
+        let mut units: Vec<u16> = self.high.take().into_iter().collect();
+        units.extend(bytes.chunks_exact(2).map(|b| {
+            if self.big_endian {
+                u16::from_be_bytes([b[0], b[1]])
+            } else {
+                u16::from_le_bytes([b[0], b[1]])
+            }
+        }));
+        if let Some(&last) = units.last() {
+            if (0xd800..0xdc00).contains(&last) {
+                self.high = units.pop();
+            }
+        }
+        let mut buf = [0u8; 4];
+        for c in char::decode_utf16(units) {
+            let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+            self.out
+                .extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for Utf16Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.out.len() {
+            if !self.refill()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.out.len() - self.pos);
+        buf[..n].copy_from_slice(&self.out[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// choose the zip member to read: the requested one, else the only timeline in the archive
+fn pick_member<R: Read + Seek>(
+    archive: &zip::ZipArchive<R>,
+    path: &Path,
+    wanted: Option<&str>,
+) -> Result<String> {
+    let names: Vec<&str> = archive
+        .file_names()
+        .filter(|n| is_timeline_name(n))
+        .collect();
+    if let Some(w) = wanted {
+        if archive.file_names().any(|n| n == w) {
+            return Ok(w.to_string());
+        }
+        // allow the bare file name for members inside folders
+        let by_base: Vec<&str> = names
+            .iter()
+            .copied()
+            .filter(|n| n.rsplit('/').next() == Some(w))
+            .collect();
+        return match by_base.as_slice() {
+            [only] => Ok(only.to_string()),
+            [] => Err(anyhow!(
+                "{}: no member named {:?} (timeline members: {})",
+                path.display(),
+                w,
+                names.join(", ")
+            )),
+            many => Err(anyhow!(
+                "{}: member name {:?} is ambiguous ({}); give the full path",
+                path.display(),
+                w,
+                many.join(", ")
+            )),
+        };
+    }
+    match names.as_slice() {
+        [] => bail!(
+            "{}: zip archive contains no .csv or .json member",
+            path.display()
+        ),
+        [only] => Ok(only.to_string()),
+        many => bail!(
+            "{}: zip archive contains several timelines ({}); pick one with --member",
+            path.display(),
+            many.join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_member_name_must_be_unique() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixture_bundle.zip");
+        let archive = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(
+            pick_member(&archive, &path, Some("host2/timeline.csv")).unwrap(),
+            "host2/timeline.csv"
+        );
+        let err = pick_member(&archive, &path, Some("timeline.csv"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("ambiguous"), "{err}");
+        assert!(
+            err.contains("host1/timeline.csv, host2/timeline.csv"),
+            "{err}"
+        );
+        let err = pick_member(&archive, &path, Some("other.csv"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("no member named"), "{err}");
+    }
+
+    #[test]
+    fn labels_tell_same_named_sources_apart() {
+        let sources = [
+            Source::new("cases/host1/DeviceTimeline.csv"),
+            Source::new("cases/host2/DeviceTimeline.csv"),
+            Source::with_member("a.zip", Some("DeviceTimeline.csv".into())),
+            Source::with_member("b.zip", Some("DeviceTimeline.csv".into())),
+            Source::new("one/host/other.csv"),
+            Source::new("two/host/other.csv"),
+            Source::new("single.csv"),
+        ];
+        assert_eq!(
+            unique_labels(&sources),
+            [
+                "host1/DeviceTimeline.csv",
+                "host2/DeviceTimeline.csv",
+                "a.zip/DeviceTimeline.csv",
+                "b.zip/DeviceTimeline.csv",
+                "one/host/other.csv",
+                "two/host/other.csv",
+                "single.csv",
+            ]
+        );
+    }
+
+    #[test]
+    fn member_applies_to_zip_inputs_only() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let inputs = [
+            dir.join("fixture_bundle.zip"),
+            dir.join("fixture_sample.csv.gz"),
+        ];
+        let sources = expand_inputs(&inputs, Some("host2/timeline.csv")).unwrap();
+        assert_eq!(sources[0].member.as_deref(), Some("host2/timeline.csv"));
+        assert_eq!(sources[1].member, None);
+        assert_eq!(sources[1].label(), "fixture_sample.csv.gz");
+        assert!(expand_inputs(&inputs[1..], Some("host2/timeline.csv")).is_err());
+    }
+}
@@ -0,0 +1,395 @@
+// indicator (ioc) lists from threat intel: hashes, ip addresses, domains, urls and file names,
+// loaded from a plain list, a csv or stix-like json, and matched against timeline events
+
+use crate::error::Result;
+use crate::timeline::TimelineEvent;
+use anyhow::{bail, Context};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// what an indicator is, which decides the fields it is matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IocKind {
+    /// md5 / sha1 / sha256: file and initiating process hashes
+    Hash,
+    /// remote ip and file origin ip
+    Ip,
+    /// host of remote url and file origin url (subdomains included)
+    Domain,
+    /// part of remote url or file origin url
+    Url,
+    /// file name
+    FileName,
+}
+
+impl IocKind {
+    /// kind named in a csv `type` column or a stix object / pattern path
+    fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        let kind = match name.as_str() {
+            "md5" | "sha1" | "sha-1" | "sha256" | "sha-256" | "hash" | "filehash" | "file_hash" => {
+                Self::Hash
+            }
+            "ip" | "ipv4" | "ipv6" | "ip-dst" | "ip-src" | "ipv4-addr" | "ipv6-addr"
+            | "ipaddress" | "ip_address" => Self::Ip,
+            "domain" | "domain-name" | "hostname" | "host" | "fqdn" => Self::Domain,
+            "url" | "uri" | "link" => Self::Url,
+            "filename" | "file_name" | "file-name" | "file" | "name" => Self::FileName,
+            _ if name.starts_with("file:hashes") => Self::Hash,
+            _ if name.starts_with("file:name") => Self::FileName,
+            _ if name.starts_with("ipv4-addr") || name.starts_with("ipv6-addr") => Self::Ip,
+            _ if name.starts_with("domain-name") => Self::Domain,
+            _ if name.starts_with("url") => Self::Url,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// guess the kind of a bare value: hex of hash length, an ip, something with a scheme or
+    /// path, a name with a file extension, else a domain
+    fn guess(value: &str) -> Option<Self> {
+        if matches!(value.len(), 32 | 40 | 64) && value.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Some(Self::Hash);
+        }
+        if value.parse::<IpAddr>().is_ok() {
+            return Some(Self::Ip);
+        }
+        if value.contains("://") || value.contains('/') {
+            return Some(Self::Url);
+        }
+        if value.contains(char::is_whitespace) || !value.contains('.') {
+            return None;
+        }
+        let ext = value.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+        if FILE_EXTENSIONS.contains(&ext.as_str()) {
+            Some(Self::FileName)
+        } else {
+            Some(Self::Domain)
+        }
+    }
+}
+
+impl fmt::Display for IocKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Hash => "hash",
+            Self::Ip => "ip",
+            Self::Domain => "domain",
+            Self::Url => "url",
+            Self::FileName => "file name",
+        })
+    }
+}
+
+/// extensions that make a bare `name.ext` a file name rather than a domain
+const FILE_EXTENSIONS: &[&str] = &[
+    "exe", "dll", "sys", "scr", "cpl", "ocx", "msi", "bat", "cmd", "ps1", "psm1", "vbs", "vbe",
+    "js", "jse", "wsf", "hta", "lnk", "jar", "iso", "img", "vhd", "zip", "rar", "7z", "doc",
+    "docm", "docx", "xls", "xlsm", "xlsx", "ppt", "pptm", "pdf", "rtf", "one", "tmp", "dat", "bin",
+];
+
+/// one indicator, normalised (lowercase, refanged)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Indicator {
+    pub value: String,
+    pub kind: IocKind,
+}
+
+impl fmt::Display for Indicator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.value, self.kind)
+    }
+}
+
+/// indicators by kind, ready to match events
+#[derive(Debug, Default)]
+pub struct IocList {
+    pub indicators: Vec<Indicator>,
+    /// (kind, value) -> indicator
+    by_value: HashMap<(IocKind, String), usize>,
+    urls: Vec<usize>,
+    /// values that were not recognised as any kind
+    pub skipped: usize,
+}
+
+impl IocList {
+    /// read an indicator file: json (`[`/`{`, stix bundle or plain values), csv (`.csv`, a
+    /// column named indicator/ioc/value/…, optional `type`) or one indicator per line
+    pub fn load(path: &Path) -> Result<Self> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("ioc list {}", path.display()))?;
+        let text = text.trim_start_matches('\u{feff}');
+        let mut list = Self::default();
+        let first = text.trim_start().chars().next();
+        let is_csv = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+        if matches!(first, Some('[' | '{')) {
+            let json: Value = serde_json::from_str(text)
+                .with_context(|| format!("ioc list {}", path.display()))?;
+            list.add_json(&json, None);
+        } else if is_csv {
+            list.add_csv(text)
+                .with_context(|| format!("ioc list {}", path.display()))?;
+        } else {
+            for line in text.lines() {
+                let line = line.trim();
+                if !line.is_empty() && !line.starts_with('#') {
+                    list.add(line, None);
+                }
+            }
+        }
+        if list.indicators.is_empty() {
+            bail!("no indicators found in {}", path.display());
+        }
+        Ok(list)
+    }
+
+    /// add `value` as `kind` (guessed when None); duplicates are ignored
+    pub fn add(&mut self, value: &str, kind: Option<IocKind>) {
+        let value = refang(value);
+        let Some(kind) = kind.or_else(|| IocKind::guess(&value)) else {
+            self.skipped += 1;
+            return;
+        };
+        let value = match kind {
+            IocKind::Url => strip_scheme(&value).trim_end_matches('/').to_string(),
+            _ => value,
+        };
+        if value.is_empty() || self.by_value.contains_key(&(kind, value.clone())) {
+            return;
+        }
+        let i = self.indicators.len();
+        if kind == IocKind::Url {
+            self.urls.push(i);
+        }
+        self.by_value.insert((kind, value.clone()), i);
+        self.indicators.push(Indicator { value, kind });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indicators.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.indicators.len()
+    }
+
+    /// indicators `ev` touches, in list order
+    pub fn hits(&self, ev: &TimelineEvent) -> Vec<usize> {
+        let mut out = Vec::new();
+        let mut exact = |kind: IocKind, value: Option<&String>| {
+            let Some(v) = value else { return };
+            let v = v.trim_matches('"').trim().to_lowercase();
+            if let Some(&i) = self.by_value.get(&(kind, v)) {
+                out.push(i);
+            }
+        };
+        for hash in [
+            &ev.sha1,
+            &ev.sha256,
+            &ev.md5,
+            &ev.initiating_process_sha1,
+            &ev.initiating_process_sha256,
+            &ev.initiating_process_md5,
+        ] {
+            exact(IocKind::Hash, hash.as_ref());
+        }
+        exact(IocKind::Ip, ev.remote_ip.as_ref());
+        exact(IocKind::Ip, ev.file_origin_ip.as_ref());
+        exact(IocKind::FileName, ev.file_name.as_ref());
+        for url in [&ev.remote_url, &ev.file_origin_url].into_iter().flatten() {
+            let url = url.trim_matches('"').trim().to_lowercase();
+            let url = strip_scheme(&url);
+            let host = url
+                .split(['/', '?', '#'])
+                .next()
+                .unwrap_or("")
+                .rsplit('@')
+                .next()
+                .unwrap_or("");
+            // the host and every parent domain
+            let mut host = host.split(':').next().unwrap_or("");
+            while !host.is_empty() {
+                if let Some(&i) = self.by_value.get(&(IocKind::Domain, host.to_string())) {
+                    out.push(i);
+                }
+                host = host.split_once('.').map_or("", |(_, parent)| parent);
+            }
+            for &i in &self.urls {
+                if url.contains(self.indicators[i].value.as_str()) {
+                    out.push(i);
+                }
+            }
+        }
+        out.sort_unstable();
+        out.dedup();
+        out
+    }
+
+    pub fn is_hit(&self, ev: &TimelineEvent) -> bool {
+        !self.is_empty() && !self.hits(ev).is_empty()
+    }
+
+    /// rows: first row is the header; the value column is the first one named like an
+    /// indicator (else the first column), an optional type column gives the kind
+    fn add_csv(&mut self, text: &str) -> Result<()> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(text.as_bytes());
+        let headers: Vec<String> = rdr
+            .headers()?
+            .iter()
+            .map(|h| h.trim().to_ascii_lowercase())
+            .collect();
+        let find = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+        let value_col = find(&[
+            "indicator",
+            "ioc",
+            "value",
+            "observable",
+            "indicator_value",
+            "ioc_value",
+        ])
+        .unwrap_or(0);
+        let type_col = find(&["type", "kind", "indicator_type", "ioc_type"]);
+        for record in rdr.records() {
+            let record = record?;
+            let Some(value) = record
+                .get(value_col)
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+            else {
+                continue;
+            };
+            let kind = type_col
+                .and_then(|c| record.get(c))
+                .and_then(|t| IocKind::from_name(t.trim()));
+            self.add(value, kind);
+        }
+        Ok(())
+    }
+
+    /// stix 2 bundles (indicator patterns such as `[file:hashes.'SHA-256' = '…']`, and
+    /// observable objects with a `value`), arrays of values, or objects with a value / type
+    fn add_json(&mut self, json: &Value, kind: Option<IocKind>) {
+        match json {
+            Value::String(s) => self.add(s, kind),
+            Value::Array(items) => {
+                for item in items {
+                    self.add_json(item, kind);
+                }
+            }
+            Value::Object(map) => {
+                if let Some(pattern) = map.get("pattern").and_then(Value::as_str) {
+                    self.add_stix_pattern(pattern);
+                    return;
+                }
+                let kind = ["type", "kind", "indicator_type"]
+                    .iter()
+                    .filter_map(|k| map.get(*k).and_then(Value::as_str))
+                    .find_map(IocKind::from_name)
+                    .or(kind);
+                let value = ["value", "indicator", "ioc", "observable"]
+                    .iter()
+                    .find_map(|k| map.get(*k));
+                match value {
+                    Some(v) => self.add_json(v, kind),
+                    None => {
+                        for key in ["objects", "indicators", "iocs", "data", "value"] {
+                            if let Some(v) = map.get(key) {
+                                self.add_json(v, kind);
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// every `path = 'value'` comparison in a stix pattern
+    fn add_stix_pattern(&mut self, pattern: &str) {
+        let re = Regex::new(r"([A-Za-z0-9_.:'-]+)\s*=\s*'((?:[^'\\]|\\.)*)'").expect("valid");
+        for c in re.captures_iter(pattern) {
+            let kind = IocKind::from_name(&c[1].replace('\'', ""));
+            self.add(&c[2].replace("\\'", "'"), kind);
+        }
+    }
+}
+
+/// lowercase and undo common defanging: hxxp, [.], (.), [dot], [:]
+fn refang(value: &str) -> String {
+    let v = value.trim().trim_matches('"').to_lowercase();
+    let v = v
+        .replace("[.]", ".")
+        .replace("(.)", ".")
+        .replace("[dot]", ".")
+        .replace("[:]", ":")
+        .replace("[://]", "://");
+    match v.strip_prefix("hxxp") {
+        Some(rest) => format!("http{}", rest),
+        None => v,
+    }
+}
+
+fn strip_scheme(url: &str) -> &str {
+    url.split_once("://").map_or(url, |(_, rest)| rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{event, TempPath};
+
+    #[test]
+    fn formats_kinds_and_matching() {
+        let write = |name: &str, text: &str| {
+            let p = TempPath::new(name);
+            fs::write(&p, text).unwrap();
+            p
+        };
+        let sha = "A".repeat(64);
+        let plain = write(
+            "plain.txt",
+            &format!("# intel\n{sha}\n10.0.0[.]5\nevil[.]com\nhxxps://bad.example/payload\nmal.exe\n???\n"),
+        );
+        let list = IocList::load(&plain).unwrap();
+        let kinds: Vec<IocKind> = list.indicators.iter().map(|i| i.kind).collect();
+        use IocKind::*;
+        assert_eq!(kinds, [Hash, Ip, Domain, Url, FileName]);
+        assert_eq!(list.skipped, 1);
+
+        let ev = event(&[
+            ("Initiating Process SHA256", &sha),
+            ("Remote Url", "https://cdn.evil.com/x"),
+            ("File Origin Url", "http://bad.example/payload.bin"),
+            ("File Name", "MAL.EXE"),
+            ("Remote IP", "10.0.0.6"),
+        ]);
+        assert_eq!(list.hits(&ev), [0, 2, 3, 4]);
+        assert!(!list.is_hit(&event(&[("Remote Url", "notevil.com")])));
+
+        let csv = write(
+            "list.csv",
+            "Type,Indicator,Note\nip,10.0.0.5,c2\ndomain,evil.exe,odd\n",
+        );
+        let list = IocList::load(&csv).unwrap();
+        assert_eq!(list.indicators[1].kind, Domain);
+
+        let stix = write(
+            "bundle.json",
+            r#"{"type":"bundle","objects":[
+                {"type":"indicator","pattern":"[file:hashes.'SHA-256' = 'abc'] OR [ipv4-addr:value = '1.2.3.4']"},
+                {"type":"domain-name","value":"evil.com"}]}"#,
+        );
+        let list = IocList::load(&stix).unwrap();
+        let got: Vec<String> = list.indicators.iter().map(|i| i.to_string()).collect();
+        assert_eq!(got, ["abc (hash)", "1.2.3.4 (ip)", "evil.com (domain)"]);
+    }
+}
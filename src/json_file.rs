@@ -0,0 +1,30 @@
+// small json settings files (noise profile, filter library): read whole, written back pretty
+
+use crate::error::Result;
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// read `path`; a missing file gives the default (it is created on the first save). `what`
+/// names the file in errors, e.g. "noise profile"
+pub(crate) fn load<T: DeserializeOwned + Default>(path: &Path, what: &str) -> Result<T> {
+    match fs::read_to_string(path) {
+        Ok(text) => {
+            serde_json::from_str(&text).with_context(|| format!("{} {}", what, path.display()))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e).with_context(|| format!("{} {}", what, path.display())),
+    }
+}
+
+/// write `value` to `path`, creating its directory
+pub(crate) fn save<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut text = serde_json::to_string_pretty(value)?;
+    text.push('\n');
+    fs::write(path, text).with_context(|| format!("saving {}", path.display()))
+}
@@ -0,0 +1,382 @@
+// json timeline reader: an array of events, an api envelope ({"value": [...]}), or json lines.
+// keys are matched like csv headers, so portal, advanced hunting and api (camelCase) names all work.
+
+use crate::csv_parser::{clip, timeline_column, RejectedRow, ScanProgress, ScanStep};
+use crate::error::Result;
+use crate::input::{ByteCounter, CountingReader};
+use crate::timeline::TimelineEvent;
+use serde::de::{self, Deserialize, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::io::{BufRead, Cursor, Read};
+use std::sync::Arc;
+
+/// keys that hold the event array in api responses (defender api, advanced hunting api)
+const ENVELOPE_KEYS: &[&str] = &["value", "Results", "results", "events"];
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// build an event from one json object: null values are skipped, scalars become strings and
+/// nested objects/arrays (e.g. AdditionalFields) are kept as compact json text; keys with no
+/// timeline field go to `extra` in object order
+pub fn event_from_object(obj: Map<String, Value>) -> std::result::Result<TimelineEvent, String> {
+    let mut fields = Map::new();
+    let mut extra = Vec::new();
+    for (key, value) in obj {
+        let text = match value {
+            Value::Null => continue,
+            Value::String(s) => s,
+            other => other.to_string(),
+        };
+        match timeline_column(&key) {
+            Some(col) if !fields.contains_key(col) => {
+                fields.insert(col.to_string(), Value::String(text));
+            }
+            _ if !text.trim().is_empty() => extra.push((Arc::from(key), text)),
+            _ => {}
+        }
+    }
+    let mut event: TimelineEvent =
+        serde_json::from_value(Value::Object(fields)).map_err(|e| e.to_string())?;
+    event.extra = extra;
+    Ok(event)
+}
+
+/// one record of a json input, or the raw text of a json-lines line that did not parse
+type Record = std::result::Result<Value, (String, String)>;
+
+/// takes each record with its line and byte offset; returns false to stop
+type Emit<'a> = dyn FnMut(Record, u64, u64) -> bool + 'a;
+
+/// keys of the first event in a json input (for schema detection); empty if there is none
+pub fn first_keys(reader: &mut dyn BufRead) -> Result<Vec<String>> {
+    let mut keys = Vec::new();
+    scan_records(reader, &mut |record, _, _| {
+        if let Ok(Value::Object(obj)) = record {
+            keys = obj.into_iter().map(|(k, _)| k).collect();
+        }
+        false
+    })?;
+    Ok(keys)
+}
+
+/// true if a json input holds one record per line rather than one document (reads the
+/// first line)
+pub fn is_json_lines(reader: &mut dyn BufRead) -> Result<bool> {
+    let mut first = Vec::new();
+    reader.read_until(b'\n', &mut first)?;
+    Ok(first_line_is_record(&first))
+}
+
+/// stream every event of a json input through `on_step` (see `csv_parser::scan_timeline`)
+pub(crate) fn scan_reader(
+    reader: &mut dyn BufRead,
+    total_bytes: u64,
+    counter: &ByteCounter,
+    label: &str,
+    on_step: &mut dyn FnMut(ScanStep, &ScanProgress) -> bool,
+) -> Result<ScanProgress> {
+    scan_with(total_bytes, counter, label, on_step, &mut |emit| {
+        scan_records(reader, emit)
+    })
+}
+
+/// like `scan_reader`, with `reader` already at `offset`, where a record of an earlier scan
+/// began (json lines) or where the one before it ended (one document)
+pub(crate) fn resume_reader(
+    reader: &mut dyn BufRead,
+    lines: bool,
+    offset: u64,
+    total_bytes: u64,
+    counter: &ByteCounter,
+    label: &str,
+    on_step: &mut dyn FnMut(ScanStep, &ScanProgress) -> bool,
+) -> Result<ScanProgress> {
+    scan_with(total_bytes, counter, label, on_step, &mut |emit| {
+        if lines {
+            scan_lines(reader, offset, emit)
+        } else {
+            scan_elements(reader, offset, emit)
+        }
+    })
+}
+
+/// turn the records `records` emits into rows and rejected rows for `on_step`
+fn scan_with(
+    total_bytes: u64,
+    counter: &ByteCounter,
+    label: &str,
+    on_step: &mut dyn FnMut(ScanStep, &ScanProgress) -> bool,
+    records: &mut dyn FnMut(&mut Emit) -> Result<()>,
+) -> Result<ScanProgress> {
+    let mut progress = ScanProgress {
+        total_bytes,
+        ..ScanProgress::default()
+    };
+    let mut index = 0u64;
+    records(&mut |record, line, offset| {
+        index += 1;
+        let reject = |error: String, text: String| {
+            ScanStep::Skipped(RejectedRow {
+                source: label.to_string(),
+                line,
+                byte_offset: offset,
+                error,
+                snippet: clip(text),
+            })
+        };
+        let step = match record {
+            Ok(Value::Object(obj)) => {
+                let text = Value::Object(obj.clone()).to_string();
+                match event_from_object(obj) {
+                    Ok(event) => ScanStep::Row { offset, event },
+                    Err(e) => reject(e, text),
+                }
+            }
+            Ok(other) => reject(
+                format!("record {}: expected a json object", index),
+                other.to_string(),
+            ),
+            Err((error, text)) => reject(error, text),
+        };
+        match step {
+            ScanStep::Row { .. } => progress.rows_parsed += 1,
+            ScanStep::Skipped(_) => progress.rows_skipped += 1,
+        }
+        progress.bytes_read = counter.get().min(total_bytes);
+        on_step(step, &progress)
+    })?;
+    progress.bytes_read = total_bytes;
+    Ok(progress)
+}
+
+/// a first line holding a complete object (or something shaped like one) means json lines;
+/// otherwise the input is one, possibly pretty-printed, document
+fn first_line_is_record(first: &[u8]) -> bool {
+    let first = first.strip_prefix(UTF8_BOM).unwrap_or(first);
+    let text = String::from_utf8_lossy(first);
+    let text = text.trim();
+    serde_json::from_slice::<Value>(first).is_ok() || (text.starts_with('{') && text.ends_with('}'))
+}
+
+/// every record with its 1-based line and byte offset (json lines), or with line 0 and the
+/// offset just past the record before it (0 for the first) inside one json document; `emit`
+/// returns false to stop
+fn scan_records(
+    reader: &mut dyn BufRead,
+    emit: &mut dyn FnMut(Record, u64, u64) -> bool,
+) -> Result<()> {
+    let mut first = Vec::new();
+    reader.read_until(b'\n', &mut first)?;
+    let bom = if first.starts_with(UTF8_BOM) {
+        UTF8_BOM.len()
+    } else {
+        0
+    };
+    if first_line_is_record(&first) {
+        let text = String::from_utf8_lossy(&first[bom..]).trim().to_string();
+        let record = serde_json::from_slice::<Value>(&first[bom..])
+            .map_err(|e| (format!("invalid json: {}", e), text));
+        if !emit_record(record, 1, bom as u64, emit) {
+            return Ok(());
+        }
+        return scan_lines(reader, first.len() as u64, emit);
+    }
+    let mut stopped = false;
+    let consumed = ByteCounter::default();
+    let mut offset = 0;
+    let mut emit_doc = |value: Value| {
+        stopped = !emit_record(Ok(value), 0, offset, emit);
+        // serde_json reads byte by byte, so this is just past the record's closing brace
+        offset = bom as u64 + consumed.get();
+        !stopped
+    };
+    let input = CountingReader::new(Cursor::new(&first[bom..]).chain(reader), &consumed);
+    let mut de = serde_json::Deserializer::from_reader(input);
+    match Document(&mut emit_doc).deserialize(&mut de) {
+        Ok(()) => Ok(de.end()?),
+        Err(_) if stopped => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// json lines after the first: one record per non-blank line; a line that does not parse is
+/// reported as a rejected record rather than ending the scan
+fn scan_lines(
+    reader: &mut dyn BufRead,
+    mut offset: u64,
+    emit: &mut dyn FnMut(Record, u64, u64) -> bool,
+) -> Result<()> {
+    let mut buf = Vec::new();
+    let mut line = 1u64;
+    loop {
+        buf.clear();
+        let n = reader.read_until(b'\n', &mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        line += 1;
+        let start = offset;
+        offset += n as u64;
+        if buf.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let record = serde_json::from_slice::<Value>(&buf).map_err(|e| {
+            let text = String::from_utf8_lossy(&buf).trim_end().to_string();
+            (format!("invalid json: {}", e), text)
+        });
+        if !emit_record(record, line, start, emit) {
+            return Ok(());
+        }
+    }
+}
+
+/// the records of one json document from `offset`, just past an earlier record, up to the
+/// end of the array holding them; each record is parsed on its own
+fn scan_elements(
+    reader: &mut dyn BufRead,
+    mut offset: u64,
+    emit: &mut dyn FnMut(Record, u64, u64) -> bool,
+) -> Result<()> {
+    loop {
+        // separators up to the next record, or the bracket closing the array
+        let next = loop {
+            let buf = reader.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(());
+            }
+            match buf
+                .iter()
+                .position(|&b| b != b',' && !b.is_ascii_whitespace())
+            {
+                Some(n) => {
+                    let next = buf[n];
+                    reader.consume(n);
+                    offset += n as u64;
+                    break next;
+                }
+                None => {
+                    let n = buf.len();
+                    reader.consume(n);
+                    offset += n as u64;
+                }
+            }
+        };
+        if next == b']' || next == b'}' {
+            return Ok(());
+        }
+        let consumed = ByteCounter::default();
+        let mut de =
+            serde_json::Deserializer::from_reader(CountingReader::new(&mut *reader, &consumed));
+        let value = Value::deserialize(&mut de)?;
+        if !emit(Ok(value), 0, offset) {
+            return Ok(());
+        }
+        offset += consumed.get();
+    }
+}
+
+/// one top-level value: an event, an array of events, or an envelope holding them
+fn emit_record(
+    record: Record,
+    line: u64,
+    offset: u64,
+    emit: &mut dyn FnMut(Record, u64, u64) -> bool,
+) -> bool {
+    let items = match record {
+        Ok(Value::Array(items)) => items,
+        Ok(Value::Object(mut obj)) => match ENVELOPE_KEYS.iter().find_map(|k| obj.remove(*k)) {
+            Some(Value::Array(items)) => items,
+            Some(other) => vec![other],
+            None => vec![Value::Object(obj)],
+        },
+        other => return emit(other, line, offset),
+    };
+    items.into_iter().all(|v| emit(Ok(v), line, offset))
+}
+
+/// streams the records of one json document (array or envelope) to the callback without
+/// building the whole tree first
+struct Document<'a>(&'a mut dyn FnMut(Value) -> bool);
+
+impl<'de> DeserializeSeed<'de> for Document<'_> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, d: D) -> std::result::Result<(), D::Error> {
+        d.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Document<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a json array or object of timeline events")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+        while let Some(v) = seq.next_element::<Value>()? {
+            if !(self.0)(v) {
+                return Err(de::Error::custom("stopped"));
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<(), A::Error> {
+        let mut fields = Map::new();
+        let mut found = false;
+        while let Some(key) = map.next_key::<String>()? {
+            if found {
+                map.next_value::<IgnoredAny>()?;
+            } else if ENVELOPE_KEYS.contains(&key.as_str()) {
+                map.next_value_seed(Document(&mut *self.0))?;
+                found = true;
+            } else {
+                fields.insert(key, map.next_value()?);
+            }
+        }
+        // a lone event object rather than an envelope
+        if !found && !(self.0)(Value::Object(fields)) {
+            return Err(de::Error::custom("stopped"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::csv_parser::{load_timeline, read_layout, Schema};
+    use crate::input::Source;
+    use std::path::Path;
+
+    #[test]
+    fn json_envelope_and_json_lines() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let (events, report) = load_timeline(&dir.join("fixture_sample.json"), None).unwrap();
+        assert_eq!(report.rejected_total, 0);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].process_id.as_deref(), Some("1234"));
+        assert_eq!(
+            events[0].additional_fields.as_deref(),
+            Some(r#"{"ProcessStartKey":42}"#)
+        );
+        assert_eq!(events[0].remote_ip, None);
+        assert_eq!(events[1].remote_port.as_deref(), Some("443"));
+
+        let path = dir.join("fixture_hunting.jsonl");
+        assert!(matches!(
+            read_layout(&Source::new(&path)).unwrap().schema,
+            Schema::AdvancedHunting(_)
+        ));
+        let (events, report) = load_timeline(&path, None).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].computer_name.as_deref(), Some("host3"));
+        assert_eq!(
+            events[1].initiating_process_file_name.as_deref(),
+            Some("curl.exe")
+        );
+        assert_eq!(report.rejected_total, 1);
+        assert_eq!(report.rejected[0].line, 2);
+        assert!(report.rejected[0].error.starts_with("invalid json"));
+    }
+}
@@ -1,7 +1,24 @@
-// library: parser + types for defender timeline csv
+// library: parser + types for defender timeline csv / json
 
 pub mod csv_parser;
 pub mod error;
 pub mod filters;
+pub mod histogram;
+pub mod index;
+pub mod input;
+pub mod ioc;
+mod json_file;
+pub mod json_parser;
+pub mod library;
+pub mod loader;
+pub mod noise;
+pub mod proctree;
+pub mod query;
+pub mod sigma;
+pub mod stacking;
+pub mod store;
+#[cfg(test)]
+mod test_util;
 pub mod timeline;
 pub mod tui;
+pub mod zone;
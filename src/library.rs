@@ -0,0 +1,169 @@
+// saved filters: the search, action types, time range and exclusion rules of a view, stored
+// under a name in a json library file so standard hunts can be re-applied (and the file shared)
+
+use crate::error::Result;
+use crate::filters::ActionTypeFilter;
+use crate::json_file;
+use crate::noise::{self, Rule};
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// one named filter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedFilter {
+    pub name: String,
+    /// search as applied (see `Query`; `re:` prefix for a regex search)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub search: String,
+    #[serde(default, skip_serializing_if = "ActionTypeFilter::is_empty")]
+    pub action_types: ActionTypeFilter,
+    /// time range in the time prompt's syntax: `last 7 days`, `after <t>`, `<t> to <t>`, …
+    /// (times without an offset are in the display zone)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+    /// exclusion rules switched on while the filter is applied (all others are switched off)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclusions: Vec<Rule>,
+}
+
+impl SavedFilter {
+    /// one-line summary for the picker
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.search.is_empty() {
+            parts.push(format!("search: {}", self.search));
+        }
+        if !self.action_types.is_empty() {
+            parts.push(format!("types: {}", self.action_types.describe()));
+        }
+        if let Some(time) = &self.time {
+            parts.push(format!("time: {}", time));
+        }
+        if !self.exclusions.is_empty() {
+            parts.push(format!("{} exclusions", self.exclusions.len()));
+        }
+        if parts.is_empty() {
+            return "no filters".to_string();
+        }
+        parts.join("  |  ")
+    }
+}
+
+/// saved filters sorted by name, and the file they are kept in (if any)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FilterLibrary {
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    pub filters: Vec<SavedFilter>,
+}
+
+impl FilterLibrary {
+    /// read the library at `path`; a missing file is an empty library that will be created on
+    /// the first save
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut library: Self = json_file::load(path, "filter library")?;
+        library.filters.sort_by_key(|f| f.name.to_lowercase());
+        library.path = Some(path.to_path_buf());
+        Ok(library)
+    }
+
+    /// write the filters back to `path` (no-op without one)
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        json_file::save(path, self)
+    }
+
+    /// add `filter`, replacing one with the same name (case-insensitive); returns its position
+    pub fn put(&mut self, filter: SavedFilter) -> Result<usize> {
+        if filter.name.trim().is_empty() {
+            bail!("a saved filter needs a name");
+        }
+        let key = filter.name.to_lowercase();
+        self.filters.retain(|f| f.name.to_lowercase() != key);
+        let i = self
+            .filters
+            .partition_point(|f| f.name.to_lowercase() < key);
+        self.filters.insert(i, filter);
+        Ok(i)
+    }
+
+    pub fn remove(&mut self, i: usize) -> Option<SavedFilter> {
+        (i < self.filters.len()).then(|| self.filters.remove(i))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.filters.len()
+    }
+}
+
+/// `rusty-lens/filters.json` next to the default noise profile
+pub fn default_path() -> Option<PathBuf> {
+    noise::default_path().map(|p| p.with_file_name("filters.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempPath;
+    use std::fs;
+
+    #[test]
+    fn filters_replace_by_name_and_round_trip() {
+        let path = TempPath::new("filters.json");
+        let mut library = FilterLibrary::load(&path).unwrap();
+        assert!(library.is_empty());
+        let lolbins = SavedFilter {
+            name: "LOLBin execution".into(),
+            search: "certutil.exe OR mshta.exe".into(),
+            action_types: ActionTypeFilter {
+                include: ["ProcessCreated".to_string()].into(),
+                ..ActionTypeFilter::default()
+            },
+            time: Some("last 7 days".into()),
+            exclusions: vec![Rule::Field {
+                field: "Initiating Process File Name".into(),
+                value: "MsSense.exe".into(),
+            }],
+        };
+        let services = SavedFilter {
+            name: "new services".into(),
+            search: "action_type:ServiceInstalled".into(),
+            action_types: ActionTypeFilter::default(),
+            time: None,
+            exclusions: Vec::new(),
+        };
+        assert_eq!(library.put(services.clone()).unwrap(), 0);
+        assert_eq!(library.put(lolbins.clone()).unwrap(), 0);
+        let renamed = SavedFilter {
+            name: "New Services".into(),
+            ..services
+        };
+        assert_eq!(library.put(renamed.clone()).unwrap(), 1);
+        assert_eq!(library.len(), 2);
+        assert!(library
+            .put(SavedFilter {
+                name: " ".into(),
+                ..lolbins.clone()
+            })
+            .is_err());
+        assert_eq!(
+            library.filters[1].describe(),
+            "search: action_type:ServiceInstalled"
+        );
+        library.save().unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("\"time\": null"));
+        let mut again = FilterLibrary::load(&path).unwrap();
+        assert_eq!(again.filters, [lolbins, renamed]);
+        assert_eq!(again.remove(0).unwrap().name, "LOLBin execution");
+        assert!(again.remove(5).is_none());
+    }
+}
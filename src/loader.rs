@@ -0,0 +1,132 @@
+// background loading: scan the csv sources on a worker thread and stream batches of rows to the tui
+
+use crate::csv_parser::{scan_timeline, RejectedRow, ScanProgress, ScanStep};
+use crate::input::{self, Source};
+use crate::timeline::TimelineEvent;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread;
+
+/// rows per batch sent to the ui thread
+const BATCH_ROWS: usize = 2_000;
+/// batches buffered in the channel before the worker blocks (bounds memory)
+const CHANNEL_BATCHES: usize = 16;
+
+/// message from the loader thread
+#[derive(Debug)]
+pub enum LoadMsg {
+    /// well-formed rows (byte offset, event) of source `source` in file order, rows rejected
+    /// since the last batch, and counters (summed over all sources) after the batch
+    Batch {
+        source: usize,
+        rows: Vec<(u64, TimelineEvent)>,
+        rejected: Vec<RejectedRow>,
+        progress: ScanProgress,
+    },
+    /// all sources scanned (or given up on); final counters
+    Done(ScanProgress),
+    /// a source could not be read, at all or past some row; loading goes on with the next
+    Failed(String),
+}
+
+/// start scanning `sources` one after another on a worker thread; the worker stops when the
+/// receiver is dropped
+pub fn spawn(sources: Vec<Source>) -> Receiver<LoadMsg> {
+    let (tx, rx) = sync_channel(CHANNEL_BATCHES);
+    thread::spawn(move || run(sources, tx));
+    rx
+}
+
+fn run(sources: Vec<Source>, tx: SyncSender<LoadMsg>) {
+    let sizes: Vec<u64> = sources
+        .iter()
+        .map(|s| input::raw_size(s).unwrap_or(0))
+        .collect();
+    let labels = input::unique_labels(&sources);
+    let mut total = ScanProgress {
+        total_bytes: sizes.iter().sum(),
+        ..ScanProgress::default()
+    };
+    for (idx, source) in sources.iter().enumerate() {
+        let base = total;
+        match run_source(idx, source, &labels[idx], base, &tx) {
+            Some((done, failed)) => {
+                total.bytes_read = base.bytes_read + sizes[idx];
+                total.rows_parsed = base.rows_parsed + done.rows_parsed;
+                total.rows_skipped = base.rows_skipped + done.rows_skipped;
+                if let Some(e) = failed {
+                    let msg = LoadMsg::Failed(format!("{}: {}", labels[idx], e));
+                    if tx.send(msg).is_err() {
+                        return;
+                    }
+                }
+            }
+            None => return,
+        }
+    }
+    let _ = tx.send(LoadMsg::Done(total));
+}
+
+/// scan one source, offsetting its counters by `base` and reporting rejected rows under
+/// `label`; returns its own counters (up to the error, if reading failed) and the error; None
+/// if the receiver went away
+fn run_source(
+    idx: usize,
+    source: &Source,
+    label: &str,
+    base: ScanProgress,
+    tx: &SyncSender<LoadMsg>,
+) -> Option<(ScanProgress, Option<anyhow::Error>)> {
+    let add = |p: &ScanProgress| ScanProgress {
+        bytes_read: base.bytes_read + p.bytes_read,
+        total_bytes: base.total_bytes,
+        rows_parsed: base.rows_parsed + p.rows_parsed,
+        rows_skipped: base.rows_skipped + p.rows_skipped,
+    };
+    let mut rows = Vec::with_capacity(BATCH_ROWS);
+    let mut rejected = Vec::new();
+    let mut seen = ScanProgress::default();
+    let mut last = base;
+    let mut hung_up = false;
+    let result = scan_timeline(source, |step, progress| {
+        seen = *progress;
+        last = add(progress);
+        match step {
+            ScanStep::Row { offset, event } => rows.push((offset, event)),
+            ScanStep::Skipped(row) => rejected.push(RejectedRow {
+                source: label.to_string(),
+                ..row
+            }),
+        }
+        if rows.len() + rejected.len() < BATCH_ROWS {
+            return true;
+        }
+        let batch = LoadMsg::Batch {
+            source: idx,
+            rows: std::mem::replace(&mut rows, Vec::with_capacity(BATCH_ROWS)),
+            rejected: std::mem::take(&mut rejected),
+            progress: last,
+        };
+        hung_up = tx.send(batch).is_err();
+        !hung_up
+    });
+    if hung_up {
+        return None;
+    }
+    let (done, failed) = match result {
+        Ok(done) => (done, None),
+        Err(e) => (seen, Some(e)),
+    };
+    last = add(&done);
+    if !rows.is_empty() || !rejected.is_empty() {
+        let batch = LoadMsg::Batch {
+            source: idx,
+            rows,
+            rejected,
+            progress: last,
+        };
+        if tx.send(batch).is_err() {
+            return None;
+        }
+    }
+    Some((done, failed))
+}
@@ -1,29 +1,84 @@
-// tui entrypoint: load csv, run event loop, draw
+// tui entrypoint: open csv files (indexed in the background), run event loop, draw
 
 use anyhow::Result;
 use clap::Parser;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
 use std::io;
 use std::path::PathBuf;
 
+use rusty_lens::input;
+use rusty_lens::ioc::IocList;
+use rusty_lens::library::{self, FilterLibrary};
+use rusty_lens::noise::{self, NoiseProfile};
+use rusty_lens::sigma::SigmaRules;
 use rusty_lens::tui::{
     self,
     app::{App, Mode},
 };
+use rusty_lens::zone::DisplayZone;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Defender device timeline TUI (DFIR)")]
 struct Args {
-    /// path to defender timeline csv
-    #[arg(value_name = "FILE")]
-    path: PathBuf,
+    /// defender timeline csv files (plain, .gz, or .zip), directories, or glob patterns;
+    /// several inputs are merged into one chronological view
+    #[arg(value_name = "FILE", required = true, num_args = 1..)]
+    paths: Vec<PathBuf>,
+
+    /// csv member to read from zip inputs (default: every csv in the archive); like .gz, a
+    /// member is decoded again from the nearest resume point when an earlier row is needed
+    #[arg(long, value_name = "NAME")]
+    member: Option<String>,
+
+    /// noise-profile file holding the exclusion rules (default: rusty-lens/noise.json in the
+    /// user config directory); created on the first change
+    #[arg(long, value_name = "FILE")]
+    noise: Option<PathBuf>,
+
+    /// zone to show and enter times in: utc, local, an offset (+02:00) or an iana name
+    /// (Europe/Berlin); [ z ] toggles between it and utc
+    #[arg(long, value_name = "ZONE", default_value = "utc")]
+    tz: DisplayZone,
+
+    /// indicator list to flag events with: one per line, a csv with an indicator column, or
+    /// stix-like json (hashes, ips, domains, urls, file names)
+    #[arg(long, value_name = "FILE")]
+    ioc: Option<PathBuf>,
+
+    /// sigma rule file or directory of rules (repeatable); matching events are tagged and
+    /// [ d ] lists the rules with their hits
+    #[arg(long, value_name = "PATH")]
+    sigma: Vec<PathBuf>,
+
+    /// saved filters library (default: rusty-lens/filters.json in the user config directory);
+    /// point it at a shared file to use a team's hunts
+    #[arg(long, value_name = "FILE")]
+    filters: Option<PathBuf>,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    let mut app = App::new(args.path)?;
+    let sources = input::expand_inputs(&args.paths, args.member.as_deref())?;
+    let noise = match args.noise.or_else(noise::default_path) {
+        Some(path) => NoiseProfile::load(&path)?,
+        None => NoiseProfile::default(),
+    };
+    let ioc = match &args.ioc {
+        Some(path) => IocList::load(path)?,
+        None => IocList::default(),
+    };
+    let sigma = if args.sigma.is_empty() {
+        SigmaRules::default()
+    } else {
+        SigmaRules::load(&args.sigma)?
+    };
+    let library = match args.filters.or_else(library::default_path) {
+        Some(path) => FilterLibrary::load(&path)?,
+        None => FilterLibrary::default(),
+    };
+    let mut app = App::new(sources, args.tz, noise, ioc, sigma, library)?;
 
     crossterm::terminal::enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -32,14 +87,23 @@ fn main() -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
 
     loop {
+        app.poll_loader();
+        app.poll_search();
         terminal.draw(|f| {
             let chunks = tui::views::layout_chunks(f.area());
             tui::views::draw_list(f, chunks[0], &mut app);
-            tui::views::draw_detail(f, chunks[1], &app);
+            tui::views::draw_detail(f, chunks[1], &mut app);
             tui::views::draw_command_bar(f, chunks[2], &app);
         })?;
 
-        if event::poll(std::time::Duration::from_millis(100))? {
+        // tick faster while the loader is streaming or a search is running so work is drained
+        // promptly
+        let tick = if app.loading.is_some() || app.search_busy() {
+            20
+        } else {
+            100
+        };
+        if event::poll(std::time::Duration::from_millis(tick))? {
             if let Event::Key(key) = event::read()? {
                 if key.kind != KeyEventKind::Press {
                     continue;
@@ -48,15 +112,124 @@ fn main() -> Result<()> {
                     Mode::SearchInput => match key.code {
                         KeyCode::Enter => app.commit_search(),
                         KeyCode::Esc => app.cancel_search(),
+                        KeyCode::Tab => app.toggle_search_regex(),
                         KeyCode::Backspace => app.pop_search_char(),
                         KeyCode::Char(c) => app.push_search_char(c),
                         _ => {}
                     },
-                    Mode::ActionTypeFilter => match key.code {
-                        KeyCode::Enter => app.commit_action_type_filter(),
-                        KeyCode::Esc => app.clear_action_type_filter(),
-                        KeyCode::Char('j') | KeyCode::Down => app.action_type_next(),
-                        KeyCode::Char('k') | KeyCode::Up => app.action_type_previous(),
+                    Mode::ActionTypeFilter => match (key.code, key.modifiers) {
+                        (KeyCode::Enter, _) => app.commit_action_type_filter(),
+                        (KeyCode::Esc, _) => app.cancel_action_type_filter(),
+                        (KeyCode::Down, _) => app.action_type_next(),
+                        (KeyCode::Up, _) => app.action_type_previous(),
+                        (KeyCode::Char('n'), KeyModifiers::CONTROL) => app.action_type_next(),
+                        (KeyCode::Char('p'), KeyModifiers::CONTROL) => app.action_type_previous(),
+                        (KeyCode::Char(' '), _) => app.cycle_action_type_mark(),
+                        (KeyCode::Delete, _) => app.clear_action_type_marks(),
+                        (KeyCode::Backspace, _) => app.pop_action_type_char(),
+                        (KeyCode::Char(c), _) => app.push_action_type_char(c),
+                        _ => {}
+                    },
+                    Mode::SourceFilter => match key.code {
+                        KeyCode::Enter => app.commit_source_filter(),
+                        KeyCode::Esc => app.clear_source_filter(),
+                        KeyCode::Char('j') | KeyCode::Down => app.source_next(),
+                        KeyCode::Char('k') | KeyCode::Up => app.source_previous(),
+                        _ => {}
+                    },
+                    Mode::Exclusions if app.noise_input.is_some() => match key.code {
+                        KeyCode::Enter => app.commit_exclusion_input(),
+                        KeyCode::Esc => app.cancel_exclusion_input(),
+                        KeyCode::Backspace => app.pop_exclusion_char(),
+                        KeyCode::Char(c) => app.push_exclusion_char(c),
+                        _ => {}
+                    },
+                    Mode::Exclusions => match key.code {
+                        KeyCode::Esc | KeyCode::Char('e') => app.close_exclusions(),
+                        KeyCode::Char('j') | KeyCode::Down => app.exclusion_next(),
+                        KeyCode::Char('k') | KeyCode::Up => app.exclusion_previous(),
+                        KeyCode::Char(' ') => app.toggle_exclusion(),
+                        KeyCode::Char('d') | KeyCode::Delete => app.remove_exclusion(),
+                        KeyCode::Char('a') => app.exclude_selected_action_type(),
+                        KeyCode::Char('p') => {
+                            app.exclude_selected_field("Initiating Process File Name")
+                        }
+                        KeyCode::Char('f') => app.exclude_selected_field("File Name"),
+                        KeyCode::Char('n') => app.start_exclusion_input(),
+                        _ => {}
+                    },
+                    Mode::Library if app.library_input.is_some() => match key.code {
+                        KeyCode::Enter => app.commit_library_input(),
+                        KeyCode::Esc => app.cancel_library_input(),
+                        KeyCode::Backspace => app.pop_library_char(),
+                        KeyCode::Char(c) => app.push_library_char(c),
+                        _ => {}
+                    },
+                    Mode::Library => match key.code {
+                        KeyCode::Esc | KeyCode::Char('l') => app.close_library(),
+                        KeyCode::Char('j') | KeyCode::Down => app.library_next(),
+                        KeyCode::Char('k') | KeyCode::Up => app.library_previous(),
+                        KeyCode::Enter => app.apply_saved_filter(),
+                        KeyCode::Char('n') => app.start_library_input(),
+                        KeyCode::Char('d') | KeyCode::Delete => app.remove_saved_filter(),
+                        _ => {}
+                    },
+                    Mode::Stacking if app.stack_input.is_some() => match key.code {
+                        KeyCode::Esc => app.cancel_stack_input(),
+                        KeyCode::Enter => app.commit_stack_input(),
+                        KeyCode::Backspace => app.pop_stack_char(),
+                        KeyCode::Char(c) => app.push_stack_char(c),
+                        _ => {}
+                    },
+                    Mode::Stacking => match key.code {
+                        KeyCode::Esc => app.close_stacking(),
+                        KeyCode::Char('j') | KeyCode::Down => app.stack_next(),
+                        KeyCode::Char('k') | KeyCode::Up => app.stack_previous(),
+                        KeyCode::Enter => app.filter_to_stack_row(),
+                        KeyCode::Char('s') => app.toggle_stack_order(),
+                        KeyCode::Char('n') => app.start_stack_input(),
+                        _ => {}
+                    },
+                    Mode::Histogram => match key.code {
+                        KeyCode::Esc => app.close_histogram(),
+                        KeyCode::Char('l') | KeyCode::Right => app.histogram_next(),
+                        KeyCode::Char('h') | KeyCode::Left => app.histogram_previous(),
+                        KeyCode::Enter => app.zoom_histogram(),
+                        KeyCode::Backspace => app.unzoom_histogram(),
+                        _ => {}
+                    },
+                    Mode::Detail => match key.code {
+                        KeyCode::Esc | KeyCode::Tab => app.close_detail_cursor(),
+                        KeyCode::Char('j') | KeyCode::Down => app.detail_cursor_next(),
+                        KeyCode::Char('k') | KeyCode::Up => app.detail_cursor_previous(),
+                        KeyCode::Char('f') => app.filter_to_detail_value(),
+                        KeyCode::Char('e') => app.exclude_detail_value(),
+                        KeyCode::Char('s') => app.search_detail_value(),
+                        _ => {}
+                    },
+                    Mode::ProcessTree => match key.code {
+                        KeyCode::Esc | KeyCode::Char('p') => app.close_process_tree(),
+                        KeyCode::Char('j') | KeyCode::Down => app.tree_next(),
+                        KeyCode::Char('k') | KeyCode::Up => app.tree_previous(),
+                        KeyCode::Enter | KeyCode::Char('l') | KeyCode::Right => app.tree_open(),
+                        KeyCode::Char('h') | KeyCode::Left => app.tree_collapse(),
+                        KeyCode::PageDown => app.scroll_detail_down(5),
+                        KeyCode::PageUp => app.scroll_detail_up(5),
+                        _ => {}
+                    },
+                    Mode::Detections => match key.code {
+                        KeyCode::Esc | KeyCode::Char('d') => app.close_detections(),
+                        KeyCode::Char('j') | KeyCode::Down => app.detection_next(),
+                        KeyCode::Char('k') | KeyCode::Up => app.detection_previous(),
+                        KeyCode::Enter => app.commit_detection_filter(),
+                        _ => {}
+                    },
+                    Mode::Rejected => match key.code {
+                        KeyCode::Esc | KeyCode::Char('r') => app.close_rejected_view(),
+                        KeyCode::Char('j') | KeyCode::Down => app.rejected_next(),
+                        KeyCode::Char('k') | KeyCode::Up => app.rejected_previous(),
+                        KeyCode::PageDown => app.scroll_detail_down(5),
+                        KeyCode::PageUp => app.scroll_detail_up(5),
                         _ => {}
                     },
                     Mode::TimeFilter => match &app.time_filter_sub {
@@ -116,6 +289,21 @@ fn main() -> Result<()> {
                         (KeyCode::Char('/'), _) => app.start_search(),
                         (KeyCode::Char('t'), _) => app.start_time_filter(),
                         (KeyCode::Char('a'), _) => app.start_action_type_filter(),
+                        (KeyCode::Char('r'), _) => app.start_rejected_view(),
+                        (KeyCode::Char('s'), _) => app.start_source_filter(),
+                        (KeyCode::Char('e'), _) => app.start_exclusions(),
+                        (KeyCode::Char('z'), _) => app.toggle_zone(),
+                        (KeyCode::Char('i'), _) => app.toggle_ioc_only(),
+                        (KeyCode::Char('d'), _) => app.start_detections(),
+                        (KeyCode::Char('l'), _) => app.start_library(),
+                        (KeyCode::Char('p'), _) => app.start_process_tree(),
+                        (KeyCode::Char('o'), _) => app.pivot_to_process(),
+                        (KeyCode::Tab, _) => app.start_detail_cursor(),
+                        (KeyCode::Char('h'), _) => app.start_histogram(),
+                        (KeyCode::Char('f'), _) => app.start_stacking(),
+                        (KeyCode::Backspace, _) => app.pivot_back(),
+                        (KeyCode::Char('n'), _) => app.next_detail_match(),
+                        (KeyCode::Char('N'), _) => app.previous_detail_match(),
                         (KeyCode::Char('j'), _) | (KeyCode::Down, _) => app.next(),
                         (KeyCode::Char('k'), _) | (KeyCode::Up, _) => app.previous(),
                         (KeyCode::PageDown, _) => app.scroll_detail_down(5),
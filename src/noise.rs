@@ -0,0 +1,240 @@
+// exclusion rules ("noise"): events matching any enabled rule are hidden from the list.
+// rules stack, can be switched off one by one and are kept in a json noise-profile file
+
+use crate::error::Result;
+use crate::json_file;
+use crate::query::Query;
+use crate::timeline::TimelineEvent;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// what an exclusion matches
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Rule {
+    /// one action type, exactly
+    ActionType { action_type: String },
+    /// one field with exactly this value (case-insensitive), e.g. Initiating Process File Name
+    /// = MsSense.exe
+    Field { field: String, value: String },
+    /// any search query (see `Query`), e.g. `svchost.exe AND action_type:Connection*`
+    Query { query: String },
+}
+
+impl Rule {
+    /// one-line description for the filter panel
+    pub fn label(&self) -> String {
+        match self {
+            Rule::ActionType { action_type } => format!("action type = {}", action_type),
+            Rule::Field { field, value } => format!("{} = {}", field, value),
+            Rule::Query { query } => format!("query: {}", query),
+        }
+    }
+
+    fn compile(&self, extra_columns: &[&str]) -> Result<Query> {
+        match self {
+            Rule::ActionType { action_type } => {
+                Query::field_equals("Action Type", action_type, extra_columns)
+            }
+            Rule::Field { field, value } => Query::field_equals(field, value, extra_columns),
+            Rule::Query { query } => Query::parse(query, extra_columns),
+        }
+    }
+}
+
+/// a rule, whether it is switched on, and its compiled form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exclusion {
+    #[serde(flatten)]
+    pub rule: Rule,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// None until compiled, or when the rule does not compile (see `error`)
+    #[serde(skip)]
+    query: Option<Query>,
+    /// why the rule does not compile against the loaded columns; it then hides nothing
+    #[serde(skip)]
+    pub error: Option<String>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// all exclusion rules, and the file they are saved to (if any)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct NoiseProfile {
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    pub rules: Vec<Exclusion>,
+}
+
+impl NoiseProfile {
+    /// read the profile at `path`; a missing file is an empty profile that will be created on
+    /// the first change
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut profile: Self = json_file::load(path, "noise profile")?;
+        profile.path = Some(path.to_path_buf());
+        Ok(profile)
+    }
+
+    /// write the rules back to `path` (no-op without one)
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        json_file::save(path, self)
+    }
+
+    /// compile every rule against the loaded columns (field names may refer to extra columns)
+    pub fn compile(&mut self, extra_columns: &[&str]) {
+        for ex in &mut self.rules {
+            match ex.rule.compile(extra_columns) {
+                Ok(q) => {
+                    ex.query = Some(q);
+                    ex.error = None;
+                }
+                Err(e) => {
+                    ex.query = None;
+                    ex.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    /// add an enabled rule; a rule that does not compile is refused, a duplicate is switched
+    /// back on. returns the rule's position.
+    pub fn add(&mut self, rule: Rule, extra_columns: &[&str]) -> Result<usize> {
+        let query = rule.compile(extra_columns)?;
+        if let Some(i) = self.rules.iter().position(|ex| ex.rule == rule) {
+            self.rules[i].enabled = true;
+            return Ok(i);
+        }
+        self.rules.push(Exclusion {
+            rule,
+            enabled: true,
+            query: Some(query),
+            error: None,
+        });
+        Ok(self.rules.len() - 1)
+    }
+
+    /// switch on exactly `rules` (adding the ones not in the profile yet) and every other rule
+    /// off; nothing changes if one of them does not compile
+    pub fn enable_only(&mut self, rules: &[Rule], extra_columns: &[&str]) -> Result<()> {
+        for rule in rules {
+            rule.compile(extra_columns).with_context(|| rule.label())?;
+        }
+        for rule in rules {
+            self.add(rule.clone(), extra_columns)?;
+        }
+        for ex in &mut self.rules {
+            ex.enabled = rules.contains(&ex.rule);
+        }
+        Ok(())
+    }
+
+    /// enabled rules, as saved in a filter
+    pub fn enabled_rules(&self) -> Vec<Rule> {
+        self.rules
+            .iter()
+            .filter(|ex| ex.enabled)
+            .map(|ex| ex.rule.clone())
+            .collect()
+    }
+
+    pub fn toggle(&mut self, i: usize) {
+        if let Some(ex) = self.rules.get_mut(i) {
+            ex.enabled = !ex.enabled;
+        }
+    }
+
+    pub fn remove(&mut self, i: usize) -> Option<Rule> {
+        (i < self.rules.len()).then(|| self.rules.remove(i).rule)
+    }
+
+    /// number of enabled rules
+    pub fn active(&self) -> usize {
+        self.rules.iter().filter(|ex| ex.enabled).count()
+    }
+
+    /// enabled rules that compiled, with their positions
+    pub fn in_effect(&self) -> impl Iterator<Item = (usize, &Query)> {
+        self.rules
+            .iter()
+            .enumerate()
+            .filter(|(_, ex)| ex.enabled)
+            .filter_map(|(i, ex)| ex.query.as_ref().map(|q| (i, q)))
+    }
+
+    /// index of the first enabled rule that hides `ev`
+    pub fn hiding_rule(&self, ev: &TimelineEvent) -> Option<usize> {
+        self.rules
+            .iter()
+            .position(|ex| ex.enabled && ex.query.as_ref().is_some_and(|q| q.matches(ev)))
+    }
+}
+
+/// `rusty-lens/noise.json` under the user's config directory ($XDG_CONFIG_HOME, ~/.config or
+/// %APPDATA%)
+pub fn default_path() -> Option<PathBuf> {
+    let env = |k: &str| {
+        std::env::var_os(k)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    let base = env("XDG_CONFIG_HOME")
+        .or_else(|| env("HOME").map(|h| h.join(".config")))
+        .or_else(|| env("APPDATA"))?;
+    Some(base.join("rusty-lens").join("noise.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{event, TempPath};
+
+    #[test]
+    fn rules_hide_stack_and_round_trip() {
+        let ev = |at: &str, parent: &str| {
+            event(&[
+                ("Action Type", at),
+                ("Initiating Process File Name", parent),
+            ])
+        };
+        let path = TempPath::new("noise.json");
+        let mut profile = NoiseProfile::load(&path).unwrap();
+        assert!(profile.rules.is_empty());
+        let field = Rule::Field {
+            field: "Initiating Process File Name".into(),
+            value: "MsSense.exe".into(),
+        };
+        assert_eq!(profile.add(field.clone(), &[]).unwrap(), 0);
+        let query = Rule::Query {
+            query: "action_type:Connection*".into(),
+        };
+        assert_eq!(profile.add(query, &[]).unwrap(), 1);
+        assert!(profile.add(Rule::Query { query: "(".into() }, &[]).is_err());
+        assert_eq!(
+            profile.hiding_rule(&ev("FileCreated", "mssense.exe")),
+            Some(0)
+        );
+        assert_eq!(
+            profile.hiding_rule(&ev("ConnectionSuccess", "x.exe")),
+            Some(1)
+        );
+        assert_eq!(profile.hiding_rule(&ev("FileCreated", "x.exe")), None);
+        profile.toggle(0);
+        assert_eq!(profile.active(), 1);
+        assert_eq!(profile.hiding_rule(&ev("FileCreated", "mssense.exe")), None);
+        profile.save().unwrap();
+
+        let mut again = NoiseProfile::load(&path).unwrap();
+        again.compile(&[]);
+        assert_eq!(again.rules.len(), 2);
+        assert!(!again.rules[0].enabled);
+        assert_eq!(again.rules[0].rule, field);
+        assert_eq!(again.hiding_rule(&ev("ConnectionFailed", "x.exe")), Some(1));
+    }
+}
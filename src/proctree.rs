@@ -0,0 +1,383 @@
+// process genealogy rebuilt from the rows: every row names the process that did it (initiating
+// process id + creation time) and that process's parent; ProcessCreated rows add the created
+// process. processes are keyed on (device, pid, creation time) so a reused pid is a new node.
+
+use crate::timeline::{parse_time, TimelineEvent};
+use chrono::{DateTime, NaiveDateTime};
+use std::collections::HashMap;
+
+/// one process instance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProcessKey {
+    /// index into the device names seen (see `ProcessTree::device`)
+    pub device: u32,
+    pub pid: u64,
+    /// creation time, ms since the epoch (utc); None when the export leaves it out
+    pub created: Option<i64>,
+}
+
+/// what is known about a process, from its creation row and the rows it initiated
+#[derive(Debug, Clone)]
+pub struct Process {
+    pub key: ProcessKey,
+    pub name: Option<String>,
+    pub path: Option<String>,
+    pub command_line: Option<String>,
+    pub parent: Option<ProcessKey>,
+    /// row of the event that created it (a ProcessCreated row of its parent)
+    pub created_row: Option<u32>,
+    /// rows it initiated, ascending
+    pub rows: Vec<u32>,
+}
+
+impl Process {
+    fn new(key: ProcessKey) -> Self {
+        Self {
+            key,
+            name: None,
+            path: None,
+            command_line: None,
+            parent: None,
+            created_row: None,
+            rows: Vec::new(),
+        }
+    }
+
+    /// creation time (utc)
+    pub fn created(&self) -> Option<NaiveDateTime> {
+        self.key
+            .created
+            .and_then(DateTime::from_timestamp_millis)
+            .map(|t| t.naive_utc())
+    }
+
+    /// "name (pid)", or "pid N" when the name is not known
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{} ({})", name, self.key.pid),
+            None => format!("pid {}", self.key.pid),
+        }
+    }
+
+    /// fill fields still unknown from another sighting
+    fn learn(
+        &mut self,
+        name: Option<&str>,
+        path: Option<&str>,
+        command_line: Option<&str>,
+        parent: Option<ProcessKey>,
+    ) {
+        let fill = |slot: &mut Option<String>, v: Option<&str>| {
+            if slot.is_none() {
+                *slot = v.map(str::to_string);
+            }
+        };
+        fill(&mut self.name, name);
+        fill(&mut self.path, path);
+        fill(&mut self.command_line, command_line);
+        if self.parent.is_none() {
+            self.parent = parent;
+        }
+    }
+}
+
+/// every process seen, with parent/child links (call `link` before walking the tree)
+#[derive(Debug, Default)]
+pub struct ProcessTree {
+    nodes: Vec<Process>,
+    by_key: HashMap<ProcessKey, usize>,
+    devices: Vec<String>,
+    device_ids: HashMap<String, u32>,
+    /// per node: parent node and children (by creation time), as of the last `link`
+    parents: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+}
+
+impl ProcessTree {
+    /// record row `row` (rows arrive in increasing order)
+    pub fn add(&mut self, row: usize, ev: &TimelineEvent) {
+        let r = row as u32;
+        let device = self.device_id(ev);
+        let key = |pid, created| process_key(device, pid, created);
+        let parent = key(
+            &ev.initiating_process_parent_id,
+            &ev.initiating_process_parent_creation_time,
+        );
+        let actor = key(
+            &ev.initiating_process_id,
+            &ev.initiating_process_creation_time,
+        );
+        if let Some(parent) = parent {
+            let p = self.node(parent);
+            p.learn(
+                clean(&ev.initiating_process_parent_file_name),
+                None,
+                None,
+                None,
+            );
+        }
+        if let Some(actor) = actor {
+            let a = self.node(actor);
+            a.learn(
+                clean(&ev.initiating_process_file_name),
+                clean(&ev.initiating_process_folder_path),
+                clean(&ev.initiating_process_command_line),
+                parent,
+            );
+            if a.rows.last() != Some(&r) {
+                a.rows.push(r);
+            }
+        }
+        if creates_process(ev) {
+            if let Some(created) = key(&ev.process_id, &ev.process_creation_time) {
+                let c = self.node(created);
+                c.learn(
+                    clean(&ev.file_name),
+                    clean(&ev.folder_path),
+                    clean(&ev.process_command_line),
+                    actor,
+                );
+                c.created_row.get_or_insert(r);
+            }
+        }
+    }
+
+    fn device_id(&mut self, ev: &TimelineEvent) -> u32 {
+        let name = device_name(ev);
+        if let Some(&id) = self.device_ids.get(&name) {
+            return id;
+        }
+        let id = self.devices.len() as u32;
+        self.devices.push(name.clone());
+        self.device_ids.insert(name, id);
+        id
+    }
+
+    fn node(&mut self, key: ProcessKey) -> &mut Process {
+        let i = match self.by_key.get(&key) {
+            Some(&i) => i,
+            None => {
+                self.nodes.push(Process::new(key));
+                self.by_key.insert(key, self.nodes.len() - 1);
+                self.nodes.len() - 1
+            }
+        };
+        &mut self.nodes[i]
+    }
+
+    /// rows renumbered (see `EventStore::sort_by_time`): old row `i` is now `new_of_old[i]`
+    pub fn remap(&mut self, new_of_old: &[u32]) {
+        for p in &mut self.nodes {
+            for r in p.rows.iter_mut() {
+                *r = new_of_old[*r as usize];
+            }
+            p.rows.sort_unstable();
+            if let Some(r) = p.created_row.as_mut() {
+                *r = new_of_old[*r as usize];
+            }
+        }
+    }
+
+    /// resolve parent keys to nodes and collect each node's children, oldest first
+    pub fn link(&mut self) {
+        self.parents = self
+            .nodes
+            .iter()
+            .map(|p| p.parent.and_then(|k| self.by_key.get(&k).copied()))
+            .collect();
+        self.children = vec![Vec::new(); self.nodes.len()];
+        for (i, parent) in self.parents.iter().enumerate() {
+            if let Some(&p) = parent.as_ref() {
+                if p != i {
+                    self.children[p].push(i);
+                }
+            }
+        }
+        for list in &mut self.children {
+            list.sort_by_key(|&c| (self.nodes[c].key.created, self.nodes[c].key.pid));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> &Process {
+        &self.nodes[i]
+    }
+
+    /// device (machine id or computer name, lowercased) of a process
+    pub fn device(&self, key: &ProcessKey) -> &str {
+        &self.devices[key.device as usize]
+    }
+
+    pub fn children(&self, i: usize) -> &[usize] {
+        self.children.get(i).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn parent(&self, i: usize) -> Option<usize> {
+        self.parents.get(i).copied().flatten()
+    }
+
+    /// `i` and its ancestors, root first (stops at a loop)
+    pub fn lineage(&self, i: usize) -> Vec<usize> {
+        let mut out = vec![i];
+        while let Some(p) = self.parent(*out.last().unwrap_or(&i)) {
+            if out.contains(&p) {
+                break;
+            }
+            out.push(p);
+        }
+        out.reverse();
+        out
+    }
+
+    /// rows where process `i` is the actor or the target (the row that created it), ascending
+    pub fn involving(&self, i: usize) -> Vec<u32> {
+        let p = &self.nodes[i];
+        let mut rows = p.rows.clone();
+        if let Some(r) = p.created_row {
+            if let Err(pos) = rows.binary_search(&r) {
+                rows.insert(pos, r);
+            }
+        }
+        rows
+    }
+
+    /// true if process `i` initiated `ev` or is the process `ev` created
+    pub fn involves(&self, i: usize, ev: &TimelineEvent) -> bool {
+        let key = self.nodes[i].key;
+        if self.device_ids.get(&device_name(ev)) != Some(&key.device) {
+            return false;
+        }
+        let is = |pid, created| process_key(key.device, pid, created) == Some(key);
+        is(
+            &ev.initiating_process_id,
+            &ev.initiating_process_creation_time,
+        ) || (creates_process(ev) && is(&ev.process_id, &ev.process_creation_time))
+    }
+
+    /// node of the process an event is about: the created process for a ProcessCreated row,
+    /// else the process that initiated it
+    pub fn process_of(&self, ev: &TimelineEvent) -> Option<usize> {
+        let device = *self.device_ids.get(&device_name(ev))?;
+        let find = |pid, created| {
+            self.by_key
+                .get(&process_key(device, pid, created)?)
+                .copied()
+        };
+        creates_process(ev)
+            .then(|| find(&ev.process_id, &ev.process_creation_time))
+            .flatten()
+            .or_else(|| {
+                find(
+                    &ev.initiating_process_id,
+                    &ev.initiating_process_creation_time,
+                )
+            })
+    }
+}
+
+fn process_key(device: u32, pid: &Option<String>, created: &Option<String>) -> Option<ProcessKey> {
+    Some(ProcessKey {
+        device,
+        pid: clean(pid)?.parse().ok()?,
+        created: clean(created)
+            .and_then(parse_time)
+            .map(|t| t.and_utc().timestamp_millis()),
+    })
+}
+
+fn creates_process(ev: &TimelineEvent) -> bool {
+    ev.action_type
+        .as_deref()
+        .is_some_and(|at| at.starts_with("ProcessCreated"))
+}
+
+/// machine id, else computer name (lowercased)
+fn device_name(ev: &TimelineEvent) -> String {
+    clean(&ev.machine_id)
+        .or(clean(&ev.computer_name))
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// trimmed, unquoted, non-empty value
+fn clean(v: &Option<String>) -> Option<&str> {
+    v.as_deref()
+        .map(|s| s.trim().trim_matches('"').trim())
+        .filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn genealogy_follows_pid_and_creation_time() {
+        let created = |parent: (&str, &str, &str), child: (&str, &str, &str)| TimelineEvent {
+            machine_id: Some("m1".into()),
+            action_type: Some("ProcessCreated".into()),
+            initiating_process_file_name: Some(parent.0.into()),
+            initiating_process_id: Some(parent.1.into()),
+            initiating_process_creation_time: Some(parent.2.into()),
+            file_name: Some(child.0.into()),
+            process_id: Some(child.1.into()),
+            process_creation_time: Some(child.2.into()),
+            ..TimelineEvent::default()
+        };
+        let explorer = ("explorer.exe", "100", "2024-05-01T08:00:00Z");
+        let events = [
+            created(explorer, ("cmd.exe", "200", "2024-05-01T09:00:00Z")),
+            TimelineEvent {
+                machine_id: Some("m1".into()),
+                action_type: Some("FileCreated".into()),
+                file_name: Some("drop.exe".into()),
+                initiating_process_file_name: Some("cmd.exe".into()),
+                initiating_process_id: Some("200".into()),
+                initiating_process_creation_time: Some("2024-05-01T09:00:00Z".into()),
+                initiating_process_parent_id: Some("100".into()),
+                initiating_process_parent_creation_time: Some("2024-05-01T08:00:00Z".into()),
+                ..TimelineEvent::default()
+            },
+            // pid 200 reused later by another process
+            created(explorer, ("notepad.exe", "200", "2024-05-01T10:00:00Z")),
+            created(
+                ("cmd.exe", "200", "2024-05-01T09:00:00Z"),
+                ("whoami.exe", "300", "2024-05-01T09:01:00Z"),
+            ),
+        ];
+        let mut tree = ProcessTree::default();
+        for (i, ev) in events.iter().enumerate() {
+            tree.add(i, ev);
+        }
+        tree.link();
+        assert_eq!(tree.len(), 4);
+        let cmd = tree.process_of(&events[1]).unwrap();
+        assert_eq!(tree.get(cmd).label(), "cmd.exe (200)");
+        assert_eq!(tree.get(cmd).created_row, Some(0));
+        assert_eq!(tree.get(cmd).rows, [1, 3]);
+        let notepad = tree.process_of(&events[2]).unwrap();
+        assert_ne!(notepad, cmd);
+        let root = tree.parent(cmd).unwrap();
+        assert_eq!(tree.get(root).name.as_deref(), Some("explorer.exe"));
+        assert_eq!(tree.children(root), [cmd, notepad]);
+        let whoami = tree.process_of(&events[3]).unwrap();
+        assert_eq!(tree.lineage(whoami), [root, cmd, whoami]);
+        assert_eq!(tree.involving(cmd), [0, 1, 3]);
+        assert!(tree.involves(cmd, &events[0]) && tree.involves(cmd, &events[3]));
+        assert!(!tree.involves(cmd, &events[2]));
+        assert_eq!(
+            tree.get(whoami).created().unwrap().to_string(),
+            "2024-05-01 09:01:00"
+        );
+
+        tree.remap(&[3, 2, 1, 0]);
+        assert_eq!(tree.get(cmd).rows, [0, 2]);
+        assert_eq!(tree.get(cmd).created_row, Some(3));
+    }
+}
@@ -0,0 +1,915 @@
+// search query language for `/`: bare terms, field:value, "quoted phrases", AND / OR / NOT,
+// parentheses and * / ? wildcards, or one regular expression (`re:`); parsed once, then
+// evaluated per event
+
+use crate::csv_parser::{squash, timeline_column, TIMELINE_HEADERS};
+use crate::error::Result;
+use crate::index::{Candidates, SearchIndex};
+use crate::timeline::TimelineEvent;
+use anyhow::{anyhow, bail};
+use regex::{Regex, RegexBuilder};
+use std::cell::OnceCell;
+use std::ops::Range;
+
+/// a parsed search; the empty query matches every event
+///
+/// - `cmd.exe` — substring of any field (case-insensitive)
+/// - `file_name:cmd.exe` — the whole field value (case-insensitive); field names may be written
+///   as in the portal export, advanced hunting or snake_case (`FileName`, `file_name`); extra
+///   columns and `source` work too
+/// - `"program files"` / `folder_path:"c:\program files\app"` — phrase, taken literally (a
+///   quote inside is doubled: `"say ""hi"""`)
+/// - `power*ell`, `file_name:*.ps1` — `*` any run of characters, `?` one character
+/// - terms next to each other are ANDed; `AND`, `OR`, `NOT` (upper case) and parentheses
+///   combine them, NOT binding tightest and OR loosest
+/// - `re:<regex>` / `re:field:<regex>` — the rest of the input is one case-insensitive regular
+///   expression, tried against each field on its own (see `Query::regex`)
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    expr: Option<Expr>,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Term(Term),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    /// regex tried against one field, or against every field in turn
+    Regex(Option<Field>, Regex),
+}
+
+#[derive(Debug, Clone)]
+struct Term {
+    field: Option<Field>,
+    pattern: Pattern,
+}
+
+#[derive(Debug, Clone)]
+enum Field {
+    /// index into `TIMELINE_HEADERS`
+    Column(usize),
+    /// unmapped column, by squashed name
+    Extra(String),
+    Source,
+}
+
+/// lowercased text; with wildcards it is matched as a glob
+#[derive(Debug, Clone)]
+struct Pattern {
+    text: String,
+    glob: Option<Vec<u8>>,
+}
+
+impl Query {
+    /// parse `input`; `extra_columns` are the unmapped column names loaded (valid as fields)
+    pub fn parse(input: &str, extra_columns: &[&str]) -> Result<Self> {
+        let trimmed = input.trim_start();
+        if let Some(pattern) = trimmed.strip_prefix("re:") {
+            let offset = input.len() - pattern.len();
+            return Self::regex_at(pattern, offset, extra_columns);
+        }
+        let tokens = tokenize(input, extra_columns)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            end: input.chars().count() + 1,
+        };
+        if parser.tokens.is_empty() {
+            return Ok(Self::default());
+        }
+        let expr = parser.or()?;
+        if let Some(tok) = parser.peek() {
+            match tok.kind {
+                Kind::Close => bail!("unexpected ')' at column {}", tok.col),
+                _ => bail!("unexpected {} at column {}", tok.kind.describe(), tok.col),
+            }
+        }
+        Ok(Self { expr: Some(expr) })
+    }
+
+    /// `pattern` as one regular expression (regex search mode): case-insensitive unless it
+    /// says otherwise (`(?-i)`), matched against each field value separately; a leading
+    /// `field:` limits it to that field. an invalid regex is an error, never "no results".
+    pub fn regex(pattern: &str, extra_columns: &[&str]) -> Result<Self> {
+        Self::regex_at(pattern, 0, extra_columns)
+    }
+
+    /// `field` equal to `value` (case-insensitive, taken literally); unknown fields are an error
+    pub fn field_equals(field: &str, value: &str, extra_columns: &[&str]) -> Result<Self> {
+        let Some(resolved) = resolve_field(field, extra_columns) else {
+            bail!("unknown field \"{}\"", field);
+        };
+        Ok(Self {
+            expr: Some(Expr::Term(Term {
+                field: Some(resolved),
+                pattern: Pattern::new(value.trim(), true, true),
+            })),
+        })
+    }
+
+    /// `regex` for a pattern found `offset` bytes into the input (for error columns)
+    fn regex_at(pattern: &str, offset: usize, extra_columns: &[&str]) -> Result<Self> {
+        let (field, pattern, offset) = match pattern.split_once(':') {
+            Some((name, rest)) if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
+                match resolve_field(name, extra_columns) {
+                    Some(field) => (Some(field), rest, offset + name.len() + 1),
+                    None => (None, pattern, offset),
+                }
+            }
+            _ => (None, pattern, offset),
+        };
+        if pattern.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let re = RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| anyhow!(regex_error(&e, offset)))?;
+        Ok(Self {
+            expr: Some(Expr::Regex(field, re)),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expr.is_none()
+    }
+
+    pub fn matches(&self, ev: &TimelineEvent) -> bool {
+        let Some(expr) = &self.expr else {
+            return true;
+        };
+        let text = OnceCell::new();
+        expr.eval(ev, &text)
+    }
+
+    /// what to mark in matching events (terms under NOT are not marked)
+    pub fn highlighter(&self) -> Highlighter {
+        let mut out = Highlighter::default();
+        if let Some(expr) = &self.expr {
+            expr.collect_highlights(&mut out.rules);
+        }
+        out
+    }
+
+    /// rows of `index` that surely / possibly match, so only the possible ones need `matches`
+    pub fn candidates(&self, index: &SearchIndex) -> Candidates {
+        match &self.expr {
+            None => Candidates::all(index.len()),
+            Some(expr) => expr.candidates(index),
+        }
+    }
+}
+
+impl Expr {
+    /// `text` holds the event's searchable text, built on first use by a bare term
+    fn eval(&self, ev: &TimelineEvent, text: &OnceCell<String>) -> bool {
+        match self {
+            Expr::Term(t) => t.eval(ev, text),
+            Expr::And(all) => all.iter().all(|e| e.eval(ev, text)),
+            Expr::Or(any) => any.iter().any(|e| e.eval(ev, text)),
+            Expr::Not(e) => !e.eval(ev, text),
+            Expr::Regex(Some(field), re) => field.value(ev).is_some_and(|v| re.is_match(v)),
+            Expr::Regex(None, re) => ev
+                .fields()
+                .filter_map(|(_, v)| v)
+                .chain(ev.extra.iter().map(|(_, v)| v.as_str()))
+                .any(|v| re.is_match(v.trim_matches('"').trim())),
+        }
+    }
+}
+
+/// marks the parts of field values a query matched (see `Query::highlighter`)
+#[derive(Debug, Default)]
+pub struct Highlighter {
+    /// per positive term: the field it is tied to, a regex for that field's trimmed value and
+    /// one for free text
+    rules: Vec<(Option<Field>, Regex, Regex)>,
+}
+
+impl Highlighter {
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// sorted, non-overlapping byte ranges of `text` to mark; `label` is the column the value
+    /// belongs to (as in `TimelineEvent::detail_lines`), or None for text mixing several fields
+    /// such as the list line
+    pub fn ranges(&self, label: Option<&str>, text: &str) -> Vec<Range<usize>> {
+        let trimmed = text.trim_matches('"').trim();
+        let offset = trimmed.as_ptr() as usize - text.as_ptr() as usize;
+        let mut out: Vec<Range<usize>> = Vec::new();
+        for (field, value_re, loose_re) in &self.rules {
+            match (field, label) {
+                (Some(field), Some(label)) if field.is_labelled(label) => out.extend(
+                    value_re
+                        .find_iter(trimmed)
+                        .map(|m| m.start() + offset..m.end() + offset),
+                ),
+                (Some(_), Some(_)) => {}
+                _ => out.extend(loose_re.find_iter(text).map(|m| m.range())),
+            }
+        }
+        out.retain(|r| !r.is_empty());
+        out.sort_by_key(|r| r.start);
+        let mut merged: Vec<Range<usize>> = Vec::new();
+        for r in out {
+            match merged.last_mut() {
+                Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+                _ => merged.push(r),
+            }
+        }
+        merged
+    }
+}
+
+impl Expr {
+    fn collect_highlights(&self, out: &mut Vec<(Option<Field>, Regex, Regex)>) {
+        match self {
+            Expr::Term(t) => {
+                let loose = t.pattern.regex(false);
+                let value = match t.field {
+                    Some(_) => t.pattern.regex(true),
+                    None => loose.clone(),
+                };
+                if let (Some(value), Some(loose)) = (value, loose) {
+                    out.push((t.field.clone(), value, loose));
+                }
+            }
+            Expr::And(all) | Expr::Or(all) => {
+                for e in all {
+                    e.collect_highlights(out);
+                }
+            }
+            Expr::Not(_) => {}
+            Expr::Regex(field, re) => out.push((field.clone(), re.clone(), re.clone())),
+        }
+    }
+
+    fn candidates(&self, index: &SearchIndex) -> Candidates {
+        match self {
+            Expr::Term(t) => t.candidates(index),
+            Expr::And(all) => all
+                .iter()
+                .map(|e| e.candidates(index))
+                .reduce(|a, b| a.and(&b))
+                .unwrap_or_else(|| Candidates::all(index.len())),
+            Expr::Or(any) => any
+                .iter()
+                .map(|e| e.candidates(index))
+                .reduce(|a, b| a.or(&b))
+                .unwrap_or_else(|| Candidates::all(index.len())),
+            Expr::Not(e) => e.candidates(index).negate(),
+            Expr::Regex(..) => Candidates::unknown(index.len()),
+        }
+    }
+}
+
+impl Term {
+    /// a bare literal is answered by the index; field values and globs only narrow the rows,
+    /// since the index knows tokens, not which field they came from
+    fn candidates(&self, index: &SearchIndex) -> Candidates {
+        if matches!(self.field, Some(Field::Source)) {
+            return Candidates::unknown(index.len());
+        }
+        let text = &self.pattern.text;
+        let found = match &self.pattern.glob {
+            None => index.containing(text),
+            Some(_) => text
+                .split(['*', '?'])
+                .filter(|f| !f.is_empty())
+                .map(|f| index.containing(f))
+                .reduce(|a, b| a.and(&b))
+                .unwrap_or_else(|| Candidates::unknown(index.len())),
+        };
+        if self.field.is_none() && self.pattern.glob.is_none() {
+            found
+        } else {
+            found.possible()
+        }
+    }
+
+    fn eval(&self, ev: &TimelineEvent, text: &OnceCell<String>) -> bool {
+        let value = match &self.field {
+            None => {
+                let text = text.get_or_init(|| ev.searchable_text());
+                return match &self.pattern.glob {
+                    Some(glob) => text.split('\n').any(|line| glob_match(glob, line)),
+                    None => text.contains(self.pattern.text.as_str()),
+                };
+            }
+            Some(field) => field.value(ev),
+        };
+        let Some(value) = value else {
+            return false;
+        };
+        let value = value.to_lowercase();
+        match &self.pattern.glob {
+            Some(glob) => glob_match(glob, &value),
+            None => value == self.pattern.text,
+        }
+    }
+}
+
+/// a field named as in a search (`file_name`, `FileName`, an extra column or `source`), to
+/// read its value from events
+#[derive(Debug, Clone)]
+pub struct FieldRef {
+    field: Field,
+    /// column name as shown in the detail view
+    pub label: String,
+}
+
+impl FieldRef {
+    /// `name` as written before `:` in a search; unknown names are an error
+    pub fn parse(name: &str, extra_columns: &[&str]) -> Result<Self> {
+        let Some(field) = resolve_field(name, extra_columns) else {
+            bail!("unknown field \"{}\"", name);
+        };
+        let label = match &field {
+            Field::Column(i) => TIMELINE_HEADERS[*i].to_string(),
+            Field::Extra(key) => extra_columns
+                .iter()
+                .find(|c| squashes_to(c, key))
+                .map_or_else(|| key.clone(), |c| c.to_string()),
+            Field::Source => "Source".to_string(),
+        };
+        Ok(Self { field, label })
+    }
+
+    /// the value in `ev`, without surrounding quotes and spaces
+    pub fn value<'a>(&self, ev: &'a TimelineEvent) -> Option<&'a str> {
+        self.field.value(ev)
+    }
+}
+
+impl Field {
+    /// true if `label` (a column name as shown in the detail view) is this field
+    fn is_labelled(&self, label: &str) -> bool {
+        match self {
+            Field::Column(i) => TIMELINE_HEADERS[*i] == label,
+            Field::Extra(key) => squashes_to(label, key),
+            Field::Source => label == "Source",
+        }
+    }
+
+    /// the field's value in `ev`, without surrounding quotes and spaces
+    fn value<'a>(&self, ev: &'a TimelineEvent) -> Option<&'a str> {
+        let value = match self {
+            Field::Column(i) => ev.field(*i),
+            Field::Extra(key) => ev
+                .extra
+                .iter()
+                .find(|(name, _)| squashes_to(name, key))
+                .map(|(_, v)| v.as_str()),
+            Field::Source => ev.source.as_deref(),
+        };
+        value.map(|v| v.trim_matches('"').trim())
+    }
+}
+
+impl Pattern {
+    /// case-insensitive regex finding the pattern: the whole value when `whole`, else any
+    /// occurrence (wildcards at either end dropped so they do not mark the rest of the text)
+    fn regex(&self, whole: bool) -> Option<Regex> {
+        let mut re = String::new();
+        match &self.glob {
+            None => re.push_str(&regex::escape(&self.text)),
+            Some(_) => {
+                let text = if whole {
+                    self.text.as_str()
+                } else {
+                    self.text.trim_matches('*')
+                };
+                for c in text.chars() {
+                    match c {
+                        '*' if whole => re.push_str(".*"),
+                        '*' => re.push_str(".*?"),
+                        '?' => re.push('.'),
+                        c => re.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+                    }
+                }
+            }
+        }
+        if re.is_empty() {
+            return None;
+        }
+        if whole {
+            re = format!("^(?:{})$", re);
+        }
+        RegexBuilder::new(&re).case_insensitive(true).build().ok()
+    }
+
+    /// quoted text is literal; a bare term is a substring, so its glob gets `*` at both ends
+    fn new(text: &str, quoted: bool, whole_value: bool) -> Self {
+        let text = text.to_lowercase();
+        let glob = (!quoted && text.contains(['*', '?'])).then(|| {
+            let mut glob = text.as_bytes().to_vec();
+            if !whole_value {
+                glob.insert(0, b'*');
+                glob.push(b'*');
+            }
+            glob
+        });
+        Self { text, glob }
+    }
+}
+
+/// regex syntax errors span several lines (pattern, caret, message); keep the message and
+/// turn the caret into a column of the whole input
+fn regex_error(e: &regex::Error, offset: usize) -> String {
+    let text = e.to_string();
+    let message = text.lines().find_map(|l| l.strip_prefix("error: "));
+    // the pattern is echoed indented by four spaces, the caret line under it
+    let caret = text
+        .lines()
+        .find(|l| l.trim_start().starts_with('^'))
+        .and_then(|l| l.find('^'))
+        .map(|c| c.saturating_sub(4));
+    match (message, caret) {
+        (Some(m), Some(c)) => format!("invalid regex at column {}: {}", offset + c + 1, m),
+        (Some(m), None) => format!("invalid regex: {}", m),
+        _ => format!("invalid regex: {}", text.trim()),
+    }
+}
+
+/// true if `name` squashes to `key` (see `csv_parser::squash`), without allocating
+fn squashes_to(name: &str, key: &str) -> bool {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .eq(key.chars())
+}
+
+/// whole-string glob match on utf-8 bytes: `*` any run of characters, `?` one character
+fn glob_match(pattern: &[u8], text: &str) -> bool {
+    let text = text.as_bytes();
+    let (mut p, mut t) = (0, 0);
+    // last `*` seen and the text position it currently stands in for
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(b'?') if !is_continuation(text[t]) => {
+                p += 1;
+                t += 1;
+                while t < text.len() && is_continuation(text[t]) {
+                    t += 1;
+                }
+            }
+            Some(&c) if c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((sp, st)) => {
+                    star = Some((sp, st + 1));
+                    p = sp + 1;
+                    t = st + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+fn is_continuation(b: u8) -> bool {
+    b & 0xc0 == 0x80
+}
+
+#[derive(Debug)]
+enum Kind {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Term(Term),
+}
+
+impl Kind {
+    fn describe(&self) -> &'static str {
+        match self {
+            Kind::Open => "'('",
+            Kind::Close => "')'",
+            Kind::And => "AND",
+            Kind::Or => "OR",
+            Kind::Not => "NOT",
+            Kind::Term(_) => "term",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: Kind,
+    /// 1-based character column, for error messages
+    col: usize,
+}
+
+fn tokenize(input: &str, extra_columns: &[&str]) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let col = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let kind = match c {
+            '(' => {
+                i += 1;
+                Kind::Open
+            }
+            ')' => {
+                i += 1;
+                Kind::Close
+            }
+            '"' => {
+                let phrase = quoted(&chars, &mut i)?;
+                Kind::Term(Term {
+                    field: None,
+                    pattern: Pattern::new(&phrase, true, false),
+                })
+            }
+            _ => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !"()\"".contains(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                match word.as_str() {
+                    "AND" => Kind::And,
+                    "OR" => Kind::Or,
+                    "NOT" => Kind::Not,
+                    _ => Kind::Term(word_term(&word, &chars, &mut i, col, extra_columns)?),
+                }
+            }
+        };
+        tokens.push(Token { kind, col });
+    }
+    Ok(tokens)
+}
+
+/// the phrase starting at the quote `chars[*i]`; leaves `i` past the closing quote
+fn quoted(chars: &[char], i: &mut usize) -> Result<String> {
+    let open = *i;
+    let mut phrase = String::new();
+    let mut j = open + 1;
+    loop {
+        match chars.get(j).copied() {
+            None => bail!("unclosed quote at column {}", open + 1),
+            Some('"') if chars.get(j + 1) == Some(&'"') => {
+                phrase.push('"');
+                j += 2;
+            }
+            Some('"') => break,
+            Some(c) => {
+                phrase.push(c);
+                j += 1;
+            }
+        }
+    }
+    *i = j + 1;
+    Ok(phrase)
+}
+
+/// `value` as a phrase term, quotes doubled
+pub fn phrase(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+/// a term matching `field` (a column label as shown, e.g. "Remote IP") equal to `value`,
+/// written `remote_ip:"10.0.0.1"` (an empty value also matches a missing one); None if the
+/// label has no letters or digits to name it by
+pub fn field_term(field: &str, value: &str) -> Option<String> {
+    let name: Vec<String> = field
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_ascii_lowercase())
+        .collect();
+    if name.is_empty() {
+        return None;
+    }
+    let name = name.join("_");
+    Some(if value.trim().is_empty() {
+        format!("({}:\"\" OR NOT {}:*)", name, name)
+    } else {
+        format!("{}:{}", name, phrase(value))
+    })
+}
+
+/// `source`, a timeline column (any naming scheme) or one of `extra_columns`
+fn resolve_field(name: &str, extra_columns: &[&str]) -> Option<Field> {
+    if name.eq_ignore_ascii_case("source") {
+        return Some(Field::Source);
+    }
+    if let Some(col) = timeline_column(name) {
+        return TIMELINE_HEADERS
+            .iter()
+            .position(|h| *h == col)
+            .map(Field::Column);
+    }
+    let key = squash(name);
+    extra_columns
+        .iter()
+        .any(|c| squash(c) == key)
+        .then_some(Field::Extra(key))
+}
+
+/// a bare word, or `field:value` / `field:"phrase"` (the phrase is read from `chars[*i]`)
+fn word_term(
+    word: &str,
+    chars: &[char],
+    i: &mut usize,
+    col: usize,
+    extra_columns: &[&str],
+) -> Result<Term> {
+    let bare = |word: &str| Term {
+        field: None,
+        pattern: Pattern::new(word, false, false),
+    };
+    let Some((name, value)) = word.split_once(':').filter(|(name, _)| !name.is_empty()) else {
+        return Ok(bare(word));
+    };
+    let field = resolve_field(name, extra_columns);
+    let Some(field) = field else {
+        // `c:\temp`, `http://…`, ipv6 addresses (`fe80::1`), times (`12:00`), mac addresses
+        // (`00:1a:2b:…`) and the like are text, not a field
+        let looks_like_field = name.len() > 1
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !name.chars().all(|c| c.is_ascii_hexdigit())
+            && !value.starts_with(['/', '\\', ':'])
+            && !value.starts_with(|c: char| c.is_ascii_digit());
+        if looks_like_field {
+            bail!(
+                "unknown field \"{}\" at column {} (quote the term to search for it as text)",
+                name,
+                col
+            );
+        }
+        return Ok(bare(word));
+    };
+    let (value, quoted_value) = if value.is_empty() && chars.get(*i) == Some(&'"') {
+        (quoted(chars, i)?, true)
+    } else {
+        (value.to_string(), false)
+    };
+    if value.is_empty() && !quoted_value {
+        bail!("missing value after \"{}:\" at column {}", name, col);
+    }
+    Ok(Term {
+        field: Some(field),
+        pattern: Pattern::new(&value, quoted_value, true),
+    })
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// column just past the input
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// column of the next token, or just past the input
+    fn col(&self) -> usize {
+        self.peek().map_or(self.end, |t| t.col)
+    }
+
+    /// or := and (OR and)*
+    fn or(&mut self) -> Result<Expr> {
+        let mut any = vec![self.and()?];
+        while matches!(self.peek(), Some(Token { kind: Kind::Or, .. })) {
+            self.pos += 1;
+            any.push(self.and()?);
+        }
+        Ok(if any.len() == 1 {
+            any.remove(0)
+        } else {
+            Expr::Or(any)
+        })
+    }
+
+    /// and := unary ([AND] unary)*
+    fn and(&mut self) -> Result<Expr> {
+        let mut all = vec![self.unary()?];
+        loop {
+            match self.peek().map(|t| &t.kind) {
+                Some(Kind::And) => self.pos += 1,
+                Some(Kind::Not | Kind::Open | Kind::Term(_)) => {}
+                _ => break,
+            }
+            all.push(self.unary()?);
+        }
+        Ok(if all.len() == 1 {
+            all.remove(0)
+        } else {
+            Expr::And(all)
+        })
+    }
+
+    /// unary := NOT unary | ( or ) | term
+    fn unary(&mut self) -> Result<Expr> {
+        let col = self.col();
+        let Some(tok) = self.peek() else {
+            bail!("expected a search term at column {}", col);
+        };
+        match &tok.kind {
+            Kind::Not => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Kind::Open => {
+                self.pos += 1;
+                let inner = self.or()?;
+                match self.peek() {
+                    Some(Token {
+                        kind: Kind::Close, ..
+                    }) => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    _ => bail!("missing ')' for the '(' at column {}", col),
+                }
+            }
+            Kind::Term(term) => {
+                let term = term.clone();
+                self.pos += 1;
+                Ok(Expr::Term(term))
+            }
+            other => bail!(
+                "expected a search term at column {}, found {}",
+                col,
+                other.describe()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event() -> TimelineEvent {
+        TimelineEvent {
+            action_type: Some("ProcessCreated".into()),
+            file_name: Some("powershell.exe".into()),
+            process_command_line: Some("powershell.exe -enc AAAA".into()),
+            initiating_process_file_name: Some("cmd.exe".into()),
+            account_name: Some("SYSTEM".into()),
+            folder_path: Some(r"C:\Program Files\App".into()),
+            extra: vec![("AccountUpn".into(), "alice@contoso.com".into())],
+            ..TimelineEvent::default()
+        }
+    }
+
+    fn hit(q: &str) -> bool {
+        Query::parse(q, &["AccountUpn"]).unwrap().matches(&event())
+    }
+
+    #[test]
+    fn fields_operators_and_wildcards() {
+        assert!(hit(""));
+        assert!(hit("cmd.exe"));
+        assert!(hit("initiating_process_file_name:cmd.exe"));
+        assert!(!hit("file_name:cmd.exe"));
+        assert!(hit(
+            "InitiatingProcessFileName:CMD.EXE AND NOT account_name:bob"
+        ));
+        assert!(!hit(
+            "initiating_process_file_name:powershell.exe AND NOT account_name:system"
+        ));
+        assert!(hit(
+            "file_name:cmd.exe OR (action_type:processcreated -enc)"
+        ));
+        assert!(hit("file_name:power*.exe"));
+        assert!(!hit("file_name:power"));
+        assert!(hit("power?hell -ENC"));
+        assert!(hit(r#"folder_path:"c:\program files\app""#));
+        assert!(hit(r#""program files""#));
+        assert!(hit(
+            &field_term("Folder Path", r"C:\Program Files\App").unwrap()
+        ));
+        assert!(hit(&field_term("AccountUpn", "ALICE@contoso.com").unwrap()));
+        assert!(!hit(&field_term("File Name", "power*.exe").unwrap()));
+        assert!(hit(&field_term("Remote IP", "").unwrap()));
+        assert!(!hit(&field_term("Account Name", " ").unwrap()));
+        assert_eq!(phrase(r#"say "hi""#), r#""say ""hi""""#);
+        assert!(Query::parse(r#"file_name:"a""b""#, &[]).is_ok());
+        assert!(!hit(r#""files\app powershell""#));
+        assert!(hit("accountupn:*@contoso.com"));
+        assert!(hit(r"c:\program"));
+        assert!(!hit("NOT NOT source:x"));
+    }
+
+    #[test]
+    fn regex_queries() {
+        assert!(hit(r"re:^power\w+\.exe$"));
+        assert!(hit(r"re:file_name:^POWERSHELL"));
+        assert!(!hit(r"re:initiating_process_file_name:^power"));
+        assert!(!hit(r"re:(?-i)^POWERSHELL"));
+        // each field on its own: no match across the boundary of two fields
+        assert!(!hit(r"re:exe.*cmd"));
+        assert!(Query::regex("", &[]).unwrap().is_empty());
+        assert!(Query::regex(r"-enc\s+[a-z0-9+/=]{4,}", &[])
+            .unwrap()
+            .matches(&event()));
+        let err = |q: &str| Query::parse(q, &[]).unwrap_err().to_string();
+        assert_eq!(err("re:a("), "invalid regex at column 5: unclosed group");
+        assert_eq!(
+            err("re:file_name:[z-a]"),
+            "invalid regex at column 15: invalid character class range, the start must be <= the end"
+        );
+    }
+
+    #[test]
+    fn parse_errors_name_the_column() {
+        let err = |q: &str| Query::parse(q, &[]).unwrap_err().to_string();
+        assert_eq!(err("a AND"), "expected a search term at column 6");
+        assert_eq!(err("(a OR b"), "missing ')' for the '(' at column 1");
+        assert_eq!(err("a)"), "unexpected ')' at column 2");
+        assert_eq!(err("\"open"), "unclosed quote at column 1");
+        assert_eq!(
+            err("a OR OR b"),
+            "expected a search term at column 6, found OR"
+        );
+        assert!(
+            err("filename:x file_nmae:y").starts_with("unknown field \"file_nmae\" at column 12")
+        );
+        assert_eq!(
+            err("file_name:"),
+            "missing value after \"file_name:\" at column 1"
+        );
+    }
+
+    #[test]
+    fn addresses_and_times_are_text() {
+        let ev = TimelineEvent {
+            event_time: Some("2024-05-01T12:00:07Z".into()),
+            local_ip: Some("fe80::1".into()),
+            remote_ip: Some("2001:db8::1".into()),
+            additional_fields: Some(r#"{"MacAddress":"00:1a:2b:3c:4d:5e"}"#.into()),
+            ..TimelineEvent::default()
+        };
+        for q in [
+            "fe80::1",
+            "2001:db8::1",
+            "12:00",
+            "00:1a:2b:3c:4d:5e",
+            "db8:",
+        ] {
+            let query = Query::parse(q, &[]).unwrap_or_else(|e| panic!("{q}: {e}"));
+            assert!(query.matches(&ev), "{q}");
+        }
+        assert!(!Query::parse("12:34", &[]).unwrap().matches(&ev));
+        assert!(Query::parse("pid:1234", &[]).is_ok());
+        assert!(Query::parse("file_nmae:1234", &[]).is_ok());
+        assert!(Query::parse("file_nmae:x", &[]).is_err());
+    }
+
+    #[test]
+    fn highlights_positive_terms() {
+        let hl = |q: &str, label: Option<&str>, text: &str| {
+            let ranges = Query::parse(q, &["AccountUpn"])
+                .unwrap()
+                .highlighter()
+                .ranges(label, text);
+            ranges
+                .into_iter()
+                .map(|r| text[r].to_string())
+                .collect::<Vec<_>>()
+        };
+        let cmd = "PowerShell.exe -enc AAAA";
+        assert_eq!(
+            hl("powershell", Some("Process Command Line"), cmd),
+            ["PowerShell"]
+        );
+        assert_eq!(hl("power*exe -ENC", None, cmd), ["PowerShell.exe", "-enc"]);
+        assert_eq!(hl("NOT powershell enc", None, cmd), ["enc"]);
+        assert_eq!(
+            hl("file_name:cmd.exe", Some("File Name"), "\"CMD.exe\""),
+            ["CMD.exe"]
+        );
+        assert!(hl("file_name:cmd.exe", Some("Folder Path"), "cmd.exe").is_empty());
+        assert_eq!(
+            hl("file_name:cmd.exe", None, "12:00 | cmd.exe"),
+            ["cmd.exe"]
+        );
+        assert_eq!(
+            hl("accountupn:alice*", Some("AccountUpn"), "alice@x"),
+            ["alice@x"]
+        );
+        assert_eq!(hl("re:a{2,}", Some("File Name"), cmd), ["AAAA"]);
+    }
+}
@@ -0,0 +1,1098 @@
+// sigma rules evaluated offline against timeline events. the logsource category picks the
+// action types a rule looks at (process_creation -> ProcessCreated, …) and sigma field names
+// are mapped onto defender columns; conditions, selections and the common value modifiers
+// (contains, startswith, endswith, all, re, cidr, windash, exists) are evaluated locally.
+
+use crate::csv_parser::{squash, timeline_column, TIMELINE_HEADERS};
+use crate::error::Result;
+use crate::timeline::TimelineEvent;
+use anyhow::{anyhow, bail, Context};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::cell::OnceCell;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// sigma field -> timeline column, per logsource category (None = any category); the first
+/// entry for a field that applies wins, so category-specific entries come first
+const FIELD_MAP: &[(Option<&str>, &str, &str)] = &[
+    (Some("process_creation"), "Image", "Folder Path"),
+    (
+        Some("process_creation"),
+        "CommandLine",
+        "Process Command Line",
+    ),
+    (Some("process_creation"), "User", "Account Name"),
+    (
+        Some("process_creation"),
+        "IntegrityLevel",
+        "Process Integrity Level",
+    ),
+    (Some("process_creation"), "ProcessId", "Process Id"),
+    (Some("process_creation"), "md5", "MD5"),
+    (Some("process_creation"), "sha1", "Sha1"),
+    (Some("process_creation"), "sha256", "Sha256"),
+    (
+        Some("process_creation"),
+        "ParentImage",
+        "Initiating Process Folder Path",
+    ),
+    (
+        Some("process_creation"),
+        "ParentCommandLine",
+        "Initiating Process Command Line",
+    ),
+    (
+        Some("process_creation"),
+        "ParentUser",
+        "Initiating Process Account Name",
+    ),
+    (
+        Some("process_creation"),
+        "ParentProcessId",
+        "Initiating Process Id",
+    ),
+    (Some("image_load"), "ImageLoaded", "Folder Path"),
+    (None, "Image", "Initiating Process Folder Path"),
+    (None, "CommandLine", "Initiating Process Command Line"),
+    (None, "User", "Initiating Process Account Name"),
+    (None, "IntegrityLevel", "Initiating Process Integrity Level"),
+    (None, "ProcessId", "Initiating Process Id"),
+    (None, "md5", "Initiating Process MD5"),
+    (None, "sha1", "Initiating Process SHA1"),
+    (None, "sha256", "Initiating Process SHA256"),
+    (None, "ParentProcessId", "Initiating Process Parent Id"),
+    (None, "Computer", "Computer Name"),
+    (None, "ComputerName", "Computer Name"),
+    (None, "TargetFilename", "Folder Path"),
+    (None, "DestinationIp", "Remote IP"),
+    (None, "DestinationPort", "Remote Port"),
+    (None, "DestinationHostname", "Remote Url"),
+    (None, "SourceIp", "Local IP"),
+    (None, "SourcePort", "Local Port"),
+    (None, "QueryName", "Remote Url"),
+    (None, "Details", "Registry Value Data"),
+    (None, "EventType", "Action Type"),
+    (
+        None,
+        "OriginalFileName",
+        "Process Version Info Original File Name",
+    ),
+    (None, "Product", "Process Version Info Product Name"),
+    (None, "Company", "Process Version Info Company Name"),
+    (None, "Description", "Process Version Info File Description"),
+];
+
+/// logsource categories that exist in a defender timeline, with the action types they cover
+fn category_applies(category: &str, action_type: &str) -> Option<bool> {
+    let applies = match category {
+        "process_creation" => action_type.starts_with("ProcessCreated"),
+        "network_connection" => action_type.contains("Connection"),
+        "registry_event" | "registry_add" | "registry_set" | "registry_delete"
+        | "registry_rename" => action_type.starts_with("Registry"),
+        "file_event" | "file_change" | "file_delete" | "file_rename" | "file_access" => {
+            action_type.starts_with("File")
+        }
+        "image_load" => action_type == "ImageLoaded",
+        "dns_query" => action_type.starts_with("Dns"),
+        _ => return None,
+    };
+    Some(applies)
+}
+
+/// the parts of a rule file that matter here
+#[derive(Debug, Deserialize)]
+struct RawRule {
+    title: Option<String>,
+    id: Option<String>,
+    level: Option<String>,
+    #[serde(default)]
+    logsource: RawLogsource,
+    detection: Option<Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawLogsource {
+    category: Option<String>,
+    product: Option<String>,
+    service: Option<String>,
+}
+
+/// one rule as loaded
+#[derive(Debug)]
+pub struct SigmaRule {
+    pub title: String,
+    pub id: Option<String>,
+    /// informational, low, medium, high or critical
+    pub level: String,
+    pub path: PathBuf,
+    /// why the rule cannot run against this timeline; it then matches nothing
+    pub error: Option<String>,
+    /// sigma fields the timeline has no column for (they never match a value)
+    pub unmapped: Vec<String>,
+    category: Option<String>,
+    raw: Option<Value>,
+    detection: Option<Detection>,
+}
+
+impl SigmaRule {
+    /// sort key: critical first
+    pub fn severity(&self) -> u8 {
+        match self.level.as_str() {
+            "critical" => 0,
+            "high" => 1,
+            "medium" => 2,
+            "low" => 3,
+            _ => 4,
+        }
+    }
+}
+
+/// every rule loaded from `--sigma` paths, and the files that did not parse
+#[derive(Debug, Default)]
+pub struct SigmaRules {
+    pub rules: Vec<SigmaRule>,
+    /// (file, error) for rule files that are not valid yaml
+    pub failed: Vec<(PathBuf, String)>,
+    /// field slots shared by all rules, so each value is read once per event
+    slots: Vec<Slot>,
+}
+
+impl SigmaRules {
+    /// read rule files; directories are searched for `.yml` / `.yaml` files
+    pub fn load(paths: &[PathBuf]) -> Result<Self> {
+        let mut files = Vec::new();
+        for path in paths {
+            collect_files(path, &mut files)
+                .with_context(|| format!("sigma rules {}", path.display()))?;
+        }
+        if files.is_empty() {
+            bail!("no sigma rule files (.yml / .yaml) found");
+        }
+        let mut out = Self::default();
+        for file in files {
+            if let Err(e) = out.load_file(&file) {
+                out.failed.push((file, format!("{:#}", e)));
+            }
+        }
+        out.rules.sort_by_key(|r| r.severity());
+        Ok(out)
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<()> {
+        let text = fs::read_to_string(path)?;
+        for doc in serde_yaml::Deserializer::from_str(&text) {
+            let raw = RawRule::deserialize(doc)?;
+            // rule collections carry shared parts in documents without a detection
+            let Some(detection) = raw.detection else {
+                continue;
+            };
+            let mut rule = SigmaRule {
+                title: raw.title.unwrap_or_else(|| path.display().to_string()),
+                id: raw.id,
+                level: raw
+                    .level
+                    .unwrap_or_else(|| "informational".into())
+                    .to_lowercase(),
+                path: path.to_path_buf(),
+                error: None,
+                unmapped: Vec::new(),
+                category: raw.logsource.category.map(|c| c.to_lowercase()),
+                raw: Some(detection),
+                detection: None,
+            };
+            let product = raw.logsource.product.map(|p| p.to_lowercase());
+            if product.as_deref().is_some_and(|p| p != "windows") {
+                rule.error = Some(format!(
+                    "logsource product {} is not in a Defender timeline",
+                    product.unwrap_or_default()
+                ));
+            } else if let Some(c) = rule.category.as_deref() {
+                if category_applies(c, "").is_none() {
+                    rule.error = Some(format!("logsource category {} is not supported", c));
+                }
+            } else if let Some(s) = raw.logsource.service {
+                rule.error = Some(format!("logsource service {} is not supported", s));
+            }
+            self.rules.push(rule);
+        }
+        Ok(())
+    }
+
+    /// resolve every rule against the loaded columns (extra columns count as fields)
+    pub fn compile(&mut self, extra_columns: &[&str]) {
+        self.slots.clear();
+        for i in 0..self.rules.len() {
+            let rule = &self.rules[i];
+            if rule.error.is_some() || rule.detection.is_some() {
+                continue;
+            }
+            let Some(raw) = rule.raw.clone() else {
+                continue;
+            };
+            let category = rule.category.clone();
+            let mut unmapped = Vec::new();
+            let mut ctx = Compiler {
+                category: category.as_deref(),
+                extra_columns,
+                slots: &mut self.slots,
+                unmapped: &mut unmapped,
+            };
+            let rule = &mut self.rules[i];
+            match ctx.detection(&raw) {
+                Ok(d) => rule.detection = Some(d),
+                Err(e) => rule.error = Some(e.to_string()),
+            }
+            rule.unmapped = unmapped;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// rules that match `ev`, in rule order
+    pub fn matching(&self, ev: &TimelineEvent) -> Vec<usize> {
+        let values = Values::new(ev, &self.slots);
+        (0..self.rules.len())
+            .filter(|&i| self.eval(i, &values))
+            .collect()
+    }
+
+    /// true if rule `i` matches `ev`
+    pub fn rule_matches(&self, i: usize, ev: &TimelineEvent) -> bool {
+        self.eval(i, &Values::new(ev, &self.slots))
+    }
+
+    fn eval(&self, i: usize, values: &Values) -> bool {
+        let rule = &self.rules[i];
+        let Some(d) = &rule.detection else {
+            return false;
+        };
+        if let Some(c) = rule.category.as_deref() {
+            let at = values.ev.action_type.as_deref().unwrap_or("");
+            if category_applies(c, at) != Some(true) {
+                return false;
+            }
+        }
+        d.condition.eval(&d.selections, values)
+    }
+}
+
+fn collect_files(path: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    if path.is_dir() {
+        let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .collect();
+        entries.sort();
+        for p in entries {
+            let is_rule = p
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("yml") || e.eq_ignore_ascii_case("yaml"));
+            if p.is_dir() || is_rule {
+                collect_files(&p, out)?;
+            }
+        }
+    } else if path.exists() {
+        out.push(path.to_path_buf());
+    } else {
+        bail!("not found");
+    }
+    Ok(())
+}
+
+/// where a field value comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Slot {
+    Column(usize),
+    /// unmapped column, by squashed name
+    Extra(String),
+    /// registry key (HKLM\… / HKU\… short forms) and value name joined, as sigma writes it
+    TargetObject,
+    /// `SHA1=…,MD5=…,SHA256=…` as in sysmon
+    Hashes,
+    /// no column in this timeline
+    Missing,
+}
+
+/// field values of one event, read on first use (indexed like `SigmaRules::slots`)
+struct Values<'a> {
+    ev: &'a TimelineEvent,
+    slots: &'a [Slot],
+    /// values as written, for `|cased`
+    cased: Vec<OnceCell<Option<String>>>,
+    cache: Vec<OnceCell<Option<String>>>,
+    /// every value lowercased, for keyword selections
+    text: OnceCell<String>,
+}
+
+impl<'a> Values<'a> {
+    fn new(ev: &'a TimelineEvent, slots: &'a [Slot]) -> Self {
+        Self {
+            ev,
+            slots,
+            cased: (0..slots.len()).map(|_| OnceCell::new()).collect(),
+            cache: (0..slots.len()).map(|_| OnceCell::new()).collect(),
+            text: OnceCell::new(),
+        }
+    }
+
+    /// lowercased value of slot `i` (None when empty or missing)
+    fn get(&self, i: usize) -> Option<&str> {
+        self.cache[i]
+            .get_or_init(|| self.get_cased(i).map(str::to_lowercase))
+            .as_deref()
+    }
+
+    /// value of slot `i` in its original case (None when empty or missing)
+    fn get_cased(&self, i: usize) -> Option<&str> {
+        self.cased[i]
+            .get_or_init(|| {
+                let ev = self.ev;
+                let trim = |v: &str| v.trim_matches('"').trim().to_string();
+                let v = match &self.slots[i] {
+                    Slot::Column(c) => ev.field(*c).map(trim),
+                    Slot::Extra(key) => ev
+                        .extra
+                        .iter()
+                        .find(|(name, _)| squash(name) == *key)
+                        .map(|(_, v)| trim(v)),
+                    Slot::TargetObject => ev.registry_key.as_deref().map(|key| {
+                        let mut key = short_hive(&trim(key));
+                        let name = ev.registry_value_name.as_deref().map(trim);
+                        if let Some(name) = name.filter(|n| !n.is_empty()) {
+                            key.push('\\');
+                            key.push_str(&name);
+                        }
+                        key
+                    }),
+                    Slot::Hashes => {
+                        let parts: Vec<String> =
+                            [("SHA1", &ev.sha1), ("MD5", &ev.md5), ("SHA256", &ev.sha256)]
+                                .into_iter()
+                                .filter_map(|(k, v)| {
+                                    v.as_deref().map(|v| format!("{}={}", k, trim(v)))
+                                })
+                                .collect();
+                        (!parts.is_empty()).then(|| parts.join(","))
+                    }
+                    Slot::Missing => None,
+                };
+                v.filter(|v| !v.is_empty())
+            })
+            .as_deref()
+    }
+
+    fn text(&self) -> &str {
+        self.text.get_or_init(|| self.ev.searchable_text())
+    }
+}
+
+/// defender writes full hive names, sigma the short ones
+fn short_hive(key: &str) -> String {
+    for (long, short) in [
+        ("HKEY_LOCAL_MACHINE", "HKLM"),
+        ("HKEY_CURRENT_USER", "HKCU"),
+        ("HKEY_USERS", "HKU"),
+        ("HKEY_CLASSES_ROOT", "HKCR"),
+    ] {
+        if key
+            .get(..long.len())
+            .is_some_and(|k| k.eq_ignore_ascii_case(long))
+        {
+            return format!("{}{}", short, &key[long.len()..]);
+        }
+    }
+    key.to_string()
+}
+
+#[derive(Debug)]
+struct Detection {
+    selections: Vec<(String, Selection)>,
+    condition: Cond,
+}
+
+#[derive(Debug)]
+enum Selection {
+    /// any of these groups; every field match in a group must hold
+    Groups(Vec<Vec<FieldMatch>>),
+    /// any field contains any of these (lowercased)
+    Keywords(Vec<String>),
+}
+
+impl Selection {
+    fn eval(&self, values: &Values) -> bool {
+        match self {
+            Selection::Groups(groups) => groups.iter().any(|g| g.iter().all(|m| m.eval(values))),
+            Selection::Keywords(words) => {
+                let text = values.text();
+                words.iter().any(|w| text.contains(w.as_str()))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct FieldMatch {
+    slot: usize,
+    /// compared against the value as written (`|cased`), else lowercased
+    cased: bool,
+    /// every matcher must hold (`|all`), else any
+    all: bool,
+    matchers: Vec<Matcher>,
+}
+
+impl FieldMatch {
+    fn eval(&self, values: &Values) -> bool {
+        let value = if self.cased {
+            values.get_cased(self.slot)
+        } else {
+            values.get(self.slot)
+        };
+        let hit = |m: &Matcher| m.eval(value);
+        if self.all {
+            self.matchers.iter().all(hit)
+        } else {
+            self.matchers.iter().any(hit)
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Equals(String),
+    Contains(String),
+    StartsWith(String),
+    EndsWith(String),
+    Regex(Regex),
+    Cidr(IpAddr, u8),
+    /// field is empty / missing
+    Null,
+    /// `|exists: true/false`
+    Exists(bool),
+}
+
+impl Matcher {
+    fn eval(&self, value: Option<&str>) -> bool {
+        match (self, value) {
+            (Matcher::Null, v) => v.is_none(),
+            (Matcher::Exists(want), v) => v.is_some() == *want,
+            (_, None) => false,
+            (Matcher::Equals(s), Some(v)) => v == s,
+            (Matcher::Contains(s), Some(v)) => v.contains(s.as_str()),
+            (Matcher::StartsWith(s), Some(v)) => v.starts_with(s.as_str()),
+            (Matcher::EndsWith(s), Some(v)) => v.ends_with(s.as_str()),
+            (Matcher::Regex(re), Some(v)) => re.is_match(v),
+            (Matcher::Cidr(net, bits), Some(v)) => v
+                .parse::<IpAddr>()
+                .is_ok_and(|ip| in_network(ip, *net, *bits)),
+        }
+    }
+}
+
+fn in_network(ip: IpAddr, net: IpAddr, bits: u8) -> bool {
+    match (ip, net) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            let mask = u32::MAX.checked_shl(32 - bits.min(32) as u32).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(net)) => {
+            let mask = u128::MAX
+                .checked_shl(128 - bits.min(128) as u32)
+                .unwrap_or(0);
+            u128::from(ip) & mask == u128::from(net) & mask
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug)]
+enum Cond {
+    Selection(usize),
+    Not(Box<Cond>),
+    And(Vec<Cond>),
+    Or(Vec<Cond>),
+    /// at least n of these selections
+    AtLeast(usize, Vec<usize>),
+    AllOf(Vec<usize>),
+}
+
+impl Cond {
+    fn eval(&self, selections: &[(String, Selection)], values: &Values) -> bool {
+        let sel = |i: &usize| selections[*i].1.eval(values);
+        match self {
+            Cond::Selection(i) => sel(i),
+            Cond::Not(c) => !c.eval(selections, values),
+            Cond::And(all) => all.iter().all(|c| c.eval(selections, values)),
+            Cond::Or(any) => any.iter().any(|c| c.eval(selections, values)),
+            Cond::AtLeast(n, of) => of.iter().filter(|i| sel(i)).take(*n).count() >= *n,
+            Cond::AllOf(of) => of.iter().all(sel),
+        }
+    }
+}
+
+/// compiles one rule's detection block
+struct Compiler<'a> {
+    category: Option<&'a str>,
+    extra_columns: &'a [&'a str],
+    slots: &'a mut Vec<Slot>,
+    unmapped: &'a mut Vec<String>,
+}
+
+impl Compiler<'_> {
+    fn detection(&mut self, raw: &Value) -> Result<Detection> {
+        let Value::Mapping(map) = raw else {
+            bail!("detection is not a mapping");
+        };
+        let mut selections = Vec::new();
+        let mut condition = None;
+        for (key, value) in map {
+            let key = scalar(key).ok_or_else(|| anyhow!("detection key is not text"))?;
+            match key.as_str() {
+                "condition" => condition = Some(value.clone()),
+                "timeframe" => bail!("timeframe (correlation) is not supported"),
+                _ => {
+                    let sel = self
+                        .selection(value)
+                        .with_context(|| format!("selection {}", key))?;
+                    selections.push((key, sel));
+                }
+            }
+        }
+        let condition = match condition {
+            Some(Value::String(s)) => parse_condition(&s, &selections)?,
+            Some(Value::Sequence(list)) => {
+                let mut any = Vec::new();
+                for c in list {
+                    let s = scalar(&c).ok_or_else(|| anyhow!("condition is not text"))?;
+                    any.push(parse_condition(&s, &selections)?);
+                }
+                Cond::Or(any)
+            }
+            _ => bail!("missing condition"),
+        };
+        Ok(Detection {
+            selections,
+            condition,
+        })
+    }
+
+    fn selection(&mut self, value: &Value) -> Result<Selection> {
+        match value {
+            Value::Mapping(_) => Ok(Selection::Groups(vec![self.group(value)?])),
+            Value::Sequence(items) if items.iter().all(|v| matches!(v, Value::Mapping(_))) => {
+                let groups = items
+                    .iter()
+                    .map(|v| self.group(v))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Selection::Groups(groups))
+            }
+            Value::Sequence(items) => Ok(Selection::Keywords(
+                items
+                    .iter()
+                    .filter_map(scalar)
+                    .map(|s| s.to_lowercase())
+                    .collect(),
+            )),
+            v => match scalar(v) {
+                Some(s) => Ok(Selection::Keywords(vec![s.to_lowercase()])),
+                None => bail!("unsupported selection"),
+            },
+        }
+    }
+
+    fn group(&mut self, value: &Value) -> Result<Vec<FieldMatch>> {
+        let Value::Mapping(map) = value else {
+            bail!("expected a mapping");
+        };
+        let mut out = Vec::new();
+        for (key, values) in map {
+            let key = scalar(key).ok_or_else(|| anyhow!("field name is not text"))?;
+            let mut parts = key.split('|');
+            let field = parts.next().unwrap_or("");
+            let modifiers: Vec<String> = parts.map(|m| m.to_lowercase()).collect();
+            let slot = self.slot(field);
+            out.push(field_match(slot, &modifiers, values).with_context(|| key.clone())?);
+        }
+        Ok(out)
+    }
+
+    /// slot for sigma field `name`, shared between rules
+    fn slot(&mut self, name: &str) -> usize {
+        let slot = match name {
+            "TargetObject" => Slot::TargetObject,
+            "Hashes" => Slot::Hashes,
+            _ => {
+                let mapped = FIELD_MAP
+                    .iter()
+                    .filter(|(cat, field, _)| {
+                        *field == name && cat.is_none_or(|c| Some(c) == self.category)
+                    })
+                    .map(|(_, _, column)| *column)
+                    .next()
+                    .unwrap_or(name);
+                self.column(mapped).unwrap_or_else(|| {
+                    if !self.unmapped.iter().any(|u| u == name) {
+                        self.unmapped.push(name.to_string());
+                    }
+                    Slot::Missing
+                })
+            }
+        };
+        match self.slots.iter().position(|s| *s == slot) {
+            Some(i) => i,
+            None => {
+                self.slots.push(slot);
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    fn column(&self, name: &str) -> Option<Slot> {
+        if let Some(col) = timeline_column(name) {
+            return TIMELINE_HEADERS
+                .iter()
+                .position(|h| *h == col)
+                .map(Slot::Column);
+        }
+        let key = squash(name);
+        self.extra_columns
+            .iter()
+            .any(|c| squash(c) == key)
+            .then_some(Slot::Extra(key))
+    }
+}
+
+/// a field with its modifiers and value(s)
+fn field_match(slot: usize, modifiers: &[String], values: &Value) -> Result<FieldMatch> {
+    let values: Vec<Option<String>> = match values {
+        Value::Sequence(items) => items.iter().map(scalar).collect(),
+        Value::Null => vec![None],
+        v => vec![Some(scalar(v).ok_or_else(|| anyhow!("unsupported value"))?)],
+    };
+    let mut all = false;
+    let mut op = "equals";
+    let mut cased = false;
+    let mut windash = false;
+    for m in modifiers {
+        match m.as_str() {
+            "contains" | "startswith" | "endswith" | "re" | "cidr" | "exists" => op = m,
+            "all" => all = true,
+            "cased" => cased = true,
+            "windash" => windash = true,
+            // regex flags
+            "i" | "m" | "s" => {}
+            other => bail!("modifier {} is not supported", other),
+        }
+    }
+    let mut matchers = Vec::new();
+    for value in values {
+        let Some(value) = value else {
+            matchers.push(Matcher::Null);
+            continue;
+        };
+        let variants = if windash {
+            ["-", "/", "\u{2013}", "\u{2014}", "\u{2015}"]
+                .iter()
+                .map(|d| value.replace('-', d))
+                .collect()
+        } else {
+            vec![value]
+        };
+        for value in variants {
+            matchers.push(matcher(op, &value, cased, modifiers)?);
+        }
+    }
+    Ok(FieldMatch {
+        slot,
+        cased,
+        all,
+        matchers,
+    })
+}
+
+fn matcher(op: &str, value: &str, cased: bool, modifiers: &[String]) -> Result<Matcher> {
+    match op {
+        "re" => {
+            let re = RegexBuilder::new(value)
+                .case_insensitive(!cased)
+                .multi_line(modifiers.iter().any(|m| m == "m"))
+                .dot_matches_new_line(modifiers.iter().any(|m| m == "s"))
+                .build()
+                .map_err(|e| anyhow!("invalid regex: {}", e))?;
+            return Ok(Matcher::Regex(re));
+        }
+        "cidr" => {
+            let (net, bits) = value
+                .split_once('/')
+                .ok_or_else(|| anyhow!("invalid cidr {}", value))?;
+            let net: IpAddr = net.parse().map_err(|_| anyhow!("invalid cidr {}", value))?;
+            let bits: u8 = bits
+                .parse()
+                .map_err(|_| anyhow!("invalid cidr {}", value))?;
+            return Ok(Matcher::Cidr(net, bits));
+        }
+        "exists" => return Ok(Matcher::Exists(value.eq_ignore_ascii_case("true"))),
+        _ => {}
+    }
+    // values are compared lowercased (event values are lowercased too), or as written when
+    // `cased`
+    let value = if cased {
+        value.to_string()
+    } else {
+        value.to_lowercase()
+    };
+    if has_wildcard(&value) {
+        let (start, end) = match op {
+            "contains" => ("", ""),
+            "startswith" => ("^", ""),
+            "endswith" => ("", "$"),
+            _ => ("^", "$"),
+        };
+        let re = format!("(?s){}{}{}", start, wildcard_regex(&value), end);
+        let re = RegexBuilder::new(&re)
+            .case_insensitive(!cased)
+            .build()
+            .map_err(|e| anyhow!("invalid value: {}", e))?;
+        return Ok(Matcher::Regex(re));
+    }
+    let value = unescape(&value);
+    Ok(match op {
+        "contains" => Matcher::Contains(value),
+        "startswith" => Matcher::StartsWith(value),
+        "endswith" => Matcher::EndsWith(value),
+        _ => Matcher::Equals(value),
+    })
+}
+
+/// true if `value` has a `*` or `?` not escaped by a backslash
+fn has_wildcard(value: &str) -> bool {
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            '\\' => escaped = !escaped,
+            '*' | '?' if !escaped => return true,
+            _ => escaped = false,
+        }
+    }
+    false
+}
+
+/// sigma value -> regex body: `*` any run, `?` one character, `\*` `\?` `\\` literal
+fn wildcard_regex(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some('*' | '?' | '\\')) => {
+                let next = chars.next().unwrap_or('\\');
+                out.push_str(&regex::escape(&next.to_string()));
+            }
+            '*' => out.push_str(".*"),
+            '?' => out.push('.'),
+            c => out.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    out
+}
+
+/// drop escapes before wildcards (`\*` -> `*`); other backslashes are literal
+fn unescape(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && matches!(chars.peek(), Some('*' | '?' | '\\')) {
+            out.push(chars.next().unwrap_or('\\'));
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// yaml scalar as text (numbers and booleans too)
+fn scalar(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// `selection and not (filter1 or filter2)`, `1 of selection_*`, `all of them`, …
+fn parse_condition(text: &str, selections: &[(String, Selection)]) -> Result<Cond> {
+    if text.contains('|') {
+        bail!("aggregations (| count() …) are not supported");
+    }
+    let spaced = text.replace('(', " ( ").replace(')', " ) ");
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+    let mut p = CondParser {
+        tokens,
+        pos: 0,
+        selections,
+    };
+    let cond = p.or()?;
+    if let Some(tok) = p.tokens.get(p.pos) {
+        bail!("unexpected {} in condition", tok);
+    }
+    Ok(cond)
+}
+
+struct CondParser<'a> {
+    tokens: Vec<&'a str>,
+    pos: usize,
+    selections: &'a [(String, Selection)],
+}
+
+impl CondParser<'_> {
+    fn peek_is(&self, word: &str) -> bool {
+        self.tokens
+            .get(self.pos)
+            .is_some_and(|t| t.eq_ignore_ascii_case(word))
+    }
+
+    fn or(&mut self) -> Result<Cond> {
+        let mut any = vec![self.and()?];
+        while self.peek_is("or") {
+            self.pos += 1;
+            any.push(self.and()?);
+        }
+        Ok(if any.len() == 1 {
+            any.remove(0)
+        } else {
+            Cond::Or(any)
+        })
+    }
+
+    fn and(&mut self) -> Result<Cond> {
+        let mut all = vec![self.unary()?];
+        while self.peek_is("and") {
+            self.pos += 1;
+            all.push(self.unary()?);
+        }
+        Ok(if all.len() == 1 {
+            all.remove(0)
+        } else {
+            Cond::And(all)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Cond> {
+        let Some(&tok) = self.tokens.get(self.pos) else {
+            bail!("condition ends early");
+        };
+        self.pos += 1;
+        if tok.eq_ignore_ascii_case("not") {
+            return Ok(Cond::Not(Box::new(self.unary()?)));
+        }
+        if tok == "(" {
+            let inner = self.or()?;
+            if self.tokens.get(self.pos) != Some(&")") {
+                bail!("missing ) in condition");
+            }
+            self.pos += 1;
+            return Ok(inner);
+        }
+        if self.peek_is("of") {
+            self.pos += 1;
+            let Some(&target) = self.tokens.get(self.pos) else {
+                bail!("missing selection after {} of", tok);
+            };
+            self.pos += 1;
+            let of: Vec<usize> = self
+                .selections
+                .iter()
+                .enumerate()
+                .filter(|(_, (name, _))| {
+                    target.eq_ignore_ascii_case("them") || name_matches(target, name)
+                })
+                .map(|(i, _)| i)
+                .collect();
+            if of.is_empty() {
+                bail!("no selection matches {}", target);
+            }
+            return match tok.to_ascii_lowercase().as_str() {
+                "all" => Ok(Cond::AllOf(of)),
+                "any" => Ok(Cond::AtLeast(1, of)),
+                n => Ok(Cond::AtLeast(
+                    n.parse()
+                        .map_err(|_| anyhow!("unexpected {} before of", tok))?,
+                    of,
+                )),
+            };
+        }
+        match self.selections.iter().position(|(name, _)| name == tok) {
+            Some(i) => Ok(Cond::Selection(i)),
+            None => bail!("unknown selection {}", tok),
+        }
+    }
+}
+
+/// selection name against a `selection_*` pattern
+fn name_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{event, TempPath};
+
+    #[test]
+    fn rules_map_fields_and_evaluate_conditions() {
+        let dir = TempPath::new("sigma");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("encoded.yml"),
+            r#"
+title: Encoded PowerShell
+id: 1
+level: high
+logsource: {category: process_creation, product: windows}
+detection:
+  selection_img:
+    - Image|endswith: '\powershell.exe'
+    - OriginalFileName: PowerShell.EXE
+  selection_cli:
+    CommandLine|contains|windash: ' -enc '
+  filter:
+    ParentImage|endswith: '\ccmexec.exe'
+  condition: all of selection_* and not filter
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("more.yaml"),
+            r#"
+title: Run key
+level: medium
+logsource: {category: registry_set, product: windows}
+detection:
+  sel:
+    TargetObject|contains: '\CurrentVersion\Run\'
+    Details|re: '(?i)\\temp\\'
+  condition: sel
+---
+title: Internal C2
+logsource: {category: network_connection}
+detection:
+  sel:
+    DestinationIp|cidr: 10.0.0.0/8
+    DestinationPort: 4444
+  condition: 1 of sel*
+---
+title: Counting
+logsource: {category: process_creation}
+detection:
+  sel: {Image: x}
+  condition: sel | count() > 5
+---
+title: Linux
+logsource: {product: linux, category: process_creation}
+detection:
+  sel: {Image: x}
+  condition: sel
+"#,
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "not a rule").unwrap();
+        let mut rules = SigmaRules::load(&[dir.to_path_buf()]).unwrap();
+        rules.compile(&[]);
+        assert_eq!(rules.len(), 5);
+        assert!(rules.rules[0].error.is_none());
+        assert_eq!(rules.rules[0].unmapped, ["OriginalFileName"]);
+        assert!(rules.rules[3]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("aggregation"));
+        assert!(rules.rules[4].error.as_deref().unwrap().contains("linux"));
+
+        let process = |path: &str, cmd: &str, parent: &str| {
+            event(&[
+                ("Action Type", "ProcessCreated"),
+                ("Folder Path", path),
+                ("Process Command Line", cmd),
+                ("Initiating Process Folder Path", parent),
+            ])
+        };
+        let ps = r"C:\Windows\System32\WindowsPowerShell\v1.0\PowerShell.exe";
+        assert_eq!(
+            rules.matching(&process(ps, "powershell /enc AAAA", "x")),
+            [0]
+        );
+        assert!(rules
+            .matching(&process(ps, "powershell -enc AAAA", r"c:\ccm\CcmExec.exe"))
+            .is_empty());
+        assert!(rules
+            .matching(&process(ps, "powershell -File a.ps1", "x"))
+            .is_empty());
+
+        let reg = event(&[
+            ("Action Type", "RegistryValueSet"),
+            (
+                "Registry Key",
+                r"HKEY_CURRENT_USER\Software\Microsoft\Windows\CurrentVersion\Run",
+            ),
+            ("Registry Value Name", "updater"),
+            (
+                "Registry Value Data",
+                r"C:\Users\a\AppData\Local\Temp\u.exe",
+            ),
+        ]);
+        assert_eq!(rules.matching(&reg), [1]);
+        let conn = event(&[
+            ("Action Type", "ConnectionSuccess"),
+            ("Remote IP", "10.1.2.3"),
+            ("Remote Port", "4444"),
+        ]);
+        assert_eq!(rules.matching(&conn), [2]);
+        assert!(rules.rule_matches(2, &conn));
+    }
+
+    #[test]
+    fn cased_compares_values_as_written() {
+        let dir = TempPath::new("cased");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("cased.yml"),
+            r#"
+title: Exact image
+logsource: {category: process_creation}
+detection:
+  sel:
+    Image|endswith|cased: '\Cmd.exe'
+  condition: sel
+---
+title: Exact regex
+logsource: {category: process_creation}
+detection:
+  sel:
+    Image|re|cased: '\\Cmd\.exe$'
+  condition: sel
+"#,
+        )
+        .unwrap();
+        let mut rules = SigmaRules::load(&[dir.to_path_buf()]).unwrap();
+        rules.compile(&[]);
+        let process =
+            |path: &str| event(&[("Action Type", "ProcessCreated"), ("Folder Path", path)]);
+        assert_eq!(
+            rules.matching(&process(r"C:\Windows\System32\Cmd.exe")),
+            [0, 1]
+        );
+        assert!(rules
+            .matching(&process(r"C:\Windows\System32\cmd.exe"))
+            .is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+// stacking (least frequency of occurrence): how often each combination of values of a few
+// fields occurs across a set of events, so the rare ones stand out
+
+use crate::error::Result;
+use crate::query::FieldRef;
+use crate::timeline::TimelineEvent;
+use anyhow::bail;
+use std::collections::HashMap;
+
+/// one combination of values and the events that have it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackRow {
+    /// as first seen, "" for a missing value
+    pub values: Vec<String>,
+    pub count: usize,
+}
+
+/// value combinations of `fields`, compared case-insensitively (as a search compares them)
+#[derive(Debug, Clone)]
+pub struct Stack {
+    pub fields: Vec<FieldRef>,
+    pub rows: Vec<StackRow>,
+    /// rarest first (the default) or most common first
+    pub ascending: bool,
+    /// events counted
+    pub total: usize,
+    /// lowercased values -> row
+    groups: HashMap<Vec<String>, usize>,
+}
+
+impl Stack {
+    /// fields named as in a search, separated by spaces or commas
+    /// (`initiating_process_file_name file_name`)
+    pub fn new(spec: &str, extra_columns: &[&str]) -> Result<Self> {
+        let fields = spec
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|name| !name.is_empty())
+            .map(|name| FieldRef::parse(name, extra_columns))
+            .collect::<Result<Vec<_>>>()?;
+        if fields.is_empty() {
+            bail!("name one or more fields, e.g. initiating_process_file_name file_name");
+        }
+        Ok(Self {
+            fields,
+            rows: Vec::new(),
+            ascending: true,
+            total: 0,
+            groups: HashMap::new(),
+        })
+    }
+
+    pub fn add(&mut self, ev: &TimelineEvent) {
+        let values: Vec<&str> = self
+            .fields
+            .iter()
+            .map(|f| f.value(ev).unwrap_or(""))
+            .collect();
+        let key: Vec<String> = values.iter().map(|v| v.to_lowercase()).collect();
+        self.total += 1;
+        match self.groups.get(&key) {
+            Some(&i) => self.rows[i].count += 1,
+            None => {
+                self.groups.insert(key, self.rows.len());
+                self.rows.push(StackRow {
+                    values: values.iter().map(|v| v.to_string()).collect(),
+                    count: 1,
+                });
+            }
+        }
+    }
+
+    /// order rows by count (ties by value); call once every event is added
+    pub fn sort(&mut self, ascending: bool) {
+        self.ascending = ascending;
+        let lower =
+            |r: &StackRow| -> Vec<String> { r.values.iter().map(|v| v.to_lowercase()).collect() };
+        self.rows.sort_by_cached_key(|r| {
+            let count = if ascending {
+                r.count as i64
+            } else {
+                -(r.count as i64)
+            };
+            (count, lower(r))
+        });
+        self.groups = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (lower(r), i))
+            .collect();
+    }
+
+    /// field labels as shown in the detail view
+    pub fn labels(&self) -> Vec<&str> {
+        self.fields.iter().map(|f| f.label.as_str()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_combinations_rarest_first() {
+        let ev = |parent: &str, child: &str| TimelineEvent {
+            initiating_process_file_name: Some(parent.into()),
+            file_name: (!child.is_empty()).then(|| child.into()),
+            ..TimelineEvent::default()
+        };
+        let mut stack = Stack::new("initiating_process_file_name, FileName", &[]).unwrap();
+        assert_eq!(
+            stack.labels(),
+            ["Initiating Process File Name", "File Name"]
+        );
+        for (parent, child) in [
+            ("services.exe", "svchost.exe"),
+            ("SERVICES.EXE", "svchost.exe"),
+            ("services.exe", "svchost.exe"),
+            ("winword.exe", "cmd.exe"),
+            ("explorer.exe", ""),
+        ] {
+            stack.add(&ev(parent, child));
+        }
+        stack.sort(true);
+        assert_eq!(stack.total, 5);
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack.rows[0].values, ["explorer.exe", ""]);
+        assert_eq!(stack.rows[2].count, 3);
+        stack.sort(false);
+        assert_eq!(stack.rows[0].values, ["services.exe", "svchost.exe"]);
+        stack.add(&ev("Explorer.exe", ""));
+        assert_eq!(
+            stack.rows.iter().find(|r| r.count == 2).unwrap().values[0],
+            "explorer.exe"
+        );
+
+        assert!(Stack::new(" , ", &[]).is_err());
+        assert_eq!(
+            Stack::new("file_nmae", &[]).unwrap_err().to_string(),
+            "unknown field \"file_nmae\""
+        );
+    }
+}
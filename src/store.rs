@@ -0,0 +1,607 @@
+// event store: byte offsets into a plain csv on disk (rows deserialized on demand),
+// or a row count plus sparse resume points for compressed, utf-16 and json inputs (rows
+// decoded again when read); several sources merge into one view ordered by event time
+
+use crate::csv_parser::{
+    read_layout, reader_builder, scan_kind, scan_timeline, scan_timeline_from, HeaderMap, Layout,
+    ScanKind, ScanStep, Schema,
+};
+use crate::error::Result;
+use crate::input::{self, Compression, Encoding, Format, Source};
+use crate::timeline::TimelineEvent;
+use chrono::NaiveDateTime;
+use csv::{ByteRecord, Position};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::path::Path;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+/// rows kept behind the last one read from a stream, so scrolling back does not restart it
+const STREAM_WINDOW: usize = 4_096;
+/// rows between resume points of a stream; under the window, so reading back from one
+/// resume point to the one before needs a single restart
+const RESUME_EVERY: usize = STREAM_WINDOW / 2;
+/// rows per batch decoded by a stream worker
+const STREAM_BATCH: usize = 512;
+/// batches a stream worker decodes ahead before it blocks
+const STREAM_AHEAD: usize = 4;
+
+#[derive(Debug)]
+enum Backend {
+    /// plain csv: only the byte offset of each well-formed record is kept
+    Disk {
+        headers: HeaderMap,
+        offsets: Vec<u64>,
+        /// random-access reader used by `get`
+        reader: RefCell<csv::Reader<File>>,
+    },
+    /// gzip / zip member / utf-16 / json: no random access by record offset, so only the
+    /// row count is kept, plus (row, decoded byte offset) every `RESUME_EVERY` rows; rows are
+    /// read by decoding the stream again, forward from the window last read or else from
+    /// the nearest resume point. the bytes before a resume point are still decompressed,
+    /// but not parsed.
+    Stream {
+        len: usize,
+        kind: ScanKind,
+        resume: Vec<(usize, u64)>,
+        /// offset of the last row pushed (rows of one json line share it)
+        last: u64,
+        cursor: RefCell<Option<StreamCursor>>,
+    },
+}
+
+/// all rows of one timeline source, addressed by index in file order
+#[derive(Debug)]
+pub struct SourceStore {
+    source: Source,
+    layout: Layout,
+    backend: Backend,
+}
+
+impl SourceStore {
+    /// empty store for `source` (header row read for plain csv, no rows yet); fill with `push`
+    pub fn create(source: &Source) -> Result<Self> {
+        // also resolves a zip member now, so a bad/ambiguous name fails before loading starts
+        let layout = read_layout(source)?;
+        // record offsets only make sense in an uncompressed utf-8 csv
+        let seekable = input::detect(&source.path)? == Compression::None
+            && input::detect_encoding(&source.path)? == Encoding::Utf8
+            && input::detect_format(source)? == Format::Csv;
+        let backend = if seekable {
+            let mut reader = reader_builder(layout.delimiter).from_path(&source.path)?;
+            let headers = HeaderMap::new(reader.headers()?);
+            Backend::Disk {
+                headers,
+                offsets: Vec::new(),
+                reader: RefCell::new(reader),
+            }
+        } else {
+            Backend::Stream {
+                len: 0,
+                kind: scan_kind(source)?,
+                resume: Vec::new(),
+                last: 0,
+                cursor: RefCell::new(None),
+            }
+        };
+        Ok(Self {
+            source: source.clone(),
+            layout,
+            backend,
+        })
+    }
+
+    /// index every well-formed row of `source` in one pass; `on_event` sees each indexed row.
+    /// malformed rows are skipped and never get an index.
+    pub fn open(source: &Source, mut on_event: impl FnMut(&TimelineEvent)) -> Result<Self> {
+        let mut store = Self::create(source)?;
+        scan_timeline(source, |step, _| {
+            if let ScanStep::Row { offset, event } = step {
+                on_event(&event);
+                store.push(offset);
+            }
+            true
+        })?;
+        Ok(store)
+    }
+
+    /// append the next well-formed record (its byte offset is kept for plain csv)
+    pub fn push(&mut self, offset: u64) {
+        match &mut self.backend {
+            Backend::Disk { offsets, .. } => offsets.push(offset),
+            Backend::Stream {
+                len, resume, last, ..
+            } => {
+                // only where a row starts on its own: a resumed scan begins with that row
+                let since = resume.last().map_or(0, |&(row, _)| row);
+                if *len >= since + RESUME_EVERY && offset > *last {
+                    resume.push((*len, offset));
+                }
+                *last = offset;
+                *len += 1;
+            }
+        }
+    }
+
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+
+    /// export layout detected from the header row
+    pub fn schema(&self) -> Schema {
+        self.layout.schema
+    }
+
+    /// columns with no timeline field, kept per event as extra fields
+    pub fn unmapped(&self) -> &[String] {
+        &self.layout.unmapped
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.source.path
+    }
+
+    /// true when rows are read by seeking to their offset rather than by decoding the stream
+    pub fn is_disk_backed(&self) -> bool {
+        matches!(self.backend, Backend::Disk { .. })
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.backend {
+            Backend::Disk { offsets, .. } => offsets.len(),
+            Backend::Stream { len, .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// the i-th row (read and deserialized from disk for plain csv; for a stream, read
+    /// forward to it, restarting the stream at the nearest resume point when it lies behind
+    /// the window or past that point)
+    pub fn get(&self, i: usize) -> Option<TimelineEvent> {
+        let (headers, offsets, reader) = match &self.backend {
+            Backend::Disk {
+                headers,
+                offsets,
+                reader,
+            } => (headers, offsets, reader),
+            Backend::Stream {
+                len,
+                kind,
+                resume,
+                cursor,
+                ..
+            } => {
+                if i >= *len {
+                    return None;
+                }
+                let from = resume[..resume.partition_point(|&(row, _)| row <= i)].last();
+                let start = from.map_or(0, |&(row, _)| row);
+                let mut cursor = cursor.borrow_mut();
+                if cursor
+                    .as_ref()
+                    .is_none_or(|c| i < c.first || c.end() < start)
+                {
+                    *cursor = Some(StreamCursor::new(&self.source, from.map(|&r| (kind, r))));
+                }
+                return cursor.as_mut()?.get(i).cloned();
+            }
+        };
+        let mut pos = Position::new();
+        pos.set_byte(*offsets.get(i)?);
+        let mut rdr = reader.borrow_mut();
+        rdr.seek(pos).ok()?;
+        let mut record = ByteRecord::new();
+        if !rdr.read_byte_record(&mut record).ok()? {
+            return None;
+        }
+        headers.event(&record).ok()
+    }
+
+    /// sequential scan of all rows in index order (one fresh reader, no seeking)
+    pub fn iter(&self) -> Box<dyn Iterator<Item = TimelineEvent> + '_> {
+        match &self.backend {
+            Backend::Disk {
+                headers, offsets, ..
+            } => Box::new(DiskIter {
+                headers,
+                offsets,
+                rdr: reader_builder(self.layout.delimiter)
+                    .from_path(&self.source.path)
+                    .ok(),
+                next: 0,
+                record: ByteRecord::new(),
+            }),
+            Backend::Stream { len, .. } => Box::new(
+                stream_rows(&self.source, None)
+                    .into_iter()
+                    .flatten()
+                    .take(*len),
+            ),
+        }
+    }
+}
+
+/// one or more sources behind a single row index. rows are numbered in arrival order
+/// while loading; `sort_by_time` renumbers them chronologically across sources.
+#[derive(Debug, Default)]
+pub struct EventStore {
+    sources: Vec<SourceStore>,
+    /// label per source (unique, see `input::unique_labels`), attached to events when there
+    /// is more than one source
+    labels: Vec<Arc<str>>,
+    /// global row -> (source, row within source)
+    rows: Vec<(u32, u32)>,
+    /// per source: row within source -> global row
+    global: Vec<Vec<u32>>,
+    /// event time per global row (merge key)
+    times: Vec<Option<NaiveDateTime>>,
+}
+
+impl EventStore {
+    /// empty stores for the sources that open (headers read / zip members resolved up front);
+    /// the ones that do not are left out and returned (by position in `sources`) with the reason
+    pub fn create(sources: &[Source]) -> (Self, Vec<(usize, anyhow::Error)>) {
+        let mut store = Self::default();
+        let mut failed = Vec::new();
+        for (i, source) in sources.iter().enumerate() {
+            match SourceStore::create(source) {
+                Ok(s) => {
+                    store.sources.push(s);
+                    store.global.push(Vec::new());
+                }
+                Err(e) => failed.push((i, e)),
+            }
+        }
+        let opened: Vec<Source> = store.sources().cloned().collect();
+        store.labels = input::unique_labels(&opened)
+            .into_iter()
+            .map(Arc::from)
+            .collect();
+        (store, failed)
+    }
+
+    /// append the next well-formed row of `source`; returns its global index
+    pub fn push(&mut self, source: usize, offset: u64, event: TimelineEvent) -> usize {
+        let idx = self.rows.len();
+        let local = self.sources[source].len();
+        self.times.push(event.event_time_parsed());
+        self.sources[source].push(offset);
+        self.rows.push((source as u32, local as u32));
+        self.global[source].push(idx as u32);
+        idx
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = &Source> {
+        self.sources.iter().map(SourceStore::source)
+    }
+
+    /// name of source `i` in list columns, filters and reports
+    pub fn label(&self, i: usize) -> Option<&Arc<str>> {
+        self.labels.get(i)
+    }
+
+    pub fn source_count(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// detected schema of every source, in source order
+    pub fn schemas(&self) -> impl Iterator<Item = Schema> + '_ {
+        self.sources.iter().map(SourceStore::schema)
+    }
+
+    /// unmapped columns across all sources (first-seen order, no duplicates)
+    pub fn unmapped_columns(&self) -> Vec<&str> {
+        let mut out: Vec<&str> = Vec::new();
+        for name in self.sources.iter().flat_map(|s| s.unmapped()) {
+            if !out.contains(&name.as_str()) {
+                out.push(name);
+            }
+        }
+        out
+    }
+
+    /// rows indexed so far for source `i`
+    pub fn source_len(&self, i: usize) -> usize {
+        self.sources.get(i).map_or(0, SourceStore::len)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// event at global index `i` (tagged with its source label when merged)
+    pub fn get(&self, i: usize) -> Option<TimelineEvent> {
+        let &(src, local) = self.rows.get(i)?;
+        let mut ev = self.sources[src as usize].get(local as usize)?;
+        self.tag(src as usize, &mut ev);
+        Some(ev)
+    }
+
+    /// every row as (global index, event), scanning each source sequentially.
+    /// indices are not ascending once rows have been sorted by time.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, TimelineEvent)> + '_ {
+        self.sources
+            .iter()
+            .enumerate()
+            .flat_map(move |(src, store)| {
+                store.iter().enumerate().map(move |(local, mut ev)| {
+                    self.tag(src, &mut ev);
+                    (self.global[src][local] as usize, ev)
+                })
+            })
+    }
+
+    /// renumber rows chronologically across sources (rows without a time go last;
+    /// ties keep source order); returns the new row of every old row
+    pub fn sort_by_time(&mut self) -> Vec<u32> {
+        let mut order: Vec<usize> = (0..self.rows.len()).collect();
+        order.sort_by_key(|&i| (self.times[i].is_none(), self.times[i]));
+        self.rows = order.iter().map(|&i| self.rows[i]).collect();
+        self.times = order.iter().map(|&i| self.times[i]).collect();
+        for (idx, &(src, local)) in self.rows.iter().enumerate() {
+            self.global[src as usize][local as usize] = idx as u32;
+        }
+        let mut new_of_old = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            new_of_old[old] = new as u32;
+        }
+        new_of_old
+    }
+
+    /// event time (utc) of event `i`, without reading it
+    pub fn time(&self, i: usize) -> Option<NaiveDateTime> {
+        self.times.get(i).copied().flatten()
+    }
+
+    /// which source event `i` came from
+    pub fn source_of(&self, i: usize) -> Option<usize> {
+        self.rows.get(i).map(|&(src, _)| src as usize)
+    }
+
+    fn tag(&self, src: usize, ev: &mut TimelineEvent) {
+        if self.sources.len() > 1 {
+            ev.source = Some(self.labels[src].clone());
+        }
+    }
+}
+
+/// a stream decoded in order on a worker thread, with the latest rows kept in a window
+#[derive(Debug)]
+struct StreamCursor {
+    rows: Receiver<Vec<TimelineEvent>>,
+    /// row index of `window[0]`
+    first: usize,
+    window: VecDeque<TimelineEvent>,
+}
+
+impl StreamCursor {
+    /// decode from the start, or from a (row, offset) resume point
+    fn new(source: &Source, from: Option<(&ScanKind, (usize, u64))>) -> Self {
+        Self {
+            rows: stream_rows(source, from.map(|(kind, (_, offset))| (kind, offset))),
+            first: from.map_or(0, |(_, (row, _))| row),
+            window: VecDeque::with_capacity(STREAM_WINDOW + STREAM_BATCH),
+        }
+    }
+
+    /// row index just past the rows decoded so far
+    fn end(&self) -> usize {
+        self.first + self.window.len()
+    }
+
+    /// row `i`, reading forward to it; None if it is behind the window or past the end
+    fn get(&mut self, i: usize) -> Option<&TimelineEvent> {
+        while i.checked_sub(self.first)? >= self.window.len() {
+            let batch = self.rows.recv().ok()?;
+            self.window.extend(batch);
+            let behind = self.window.len().saturating_sub(STREAM_WINDOW);
+            self.window.drain(..behind);
+            self.first += behind;
+        }
+        self.window.get(i - self.first)
+    }
+}
+
+/// decode `source` again on a worker thread, from the start or from a decoded byte offset,
+/// and send its well-formed rows in batches (the same rows in the same order as when it was
+/// indexed); the worker stops when the receiver is dropped
+fn stream_rows(source: &Source, from: Option<(&ScanKind, u64)>) -> Receiver<Vec<TimelineEvent>> {
+    let (tx, rx) = sync_channel(STREAM_AHEAD);
+    let source = source.clone();
+    let from = from.map(|(kind, offset)| (kind.clone(), offset));
+    thread::spawn(move || {
+        let mut batch = Vec::with_capacity(STREAM_BATCH);
+        let on_step = |step, _: &_| {
+            if let ScanStep::Row { event, .. } = step {
+                batch.push(event);
+            }
+            batch.len() < STREAM_BATCH
+                || tx
+                    .send(std::mem::replace(
+                        &mut batch,
+                        Vec::with_capacity(STREAM_BATCH),
+                    ))
+                    .is_ok()
+        };
+        // a read error ends the stream where indexing stopped too
+        let _ = match &from {
+            Some((kind, offset)) => scan_timeline_from(&source, kind, *offset, on_step),
+            None => scan_timeline(&source, on_step),
+        };
+        if !batch.is_empty() {
+            let _ = tx.send(batch);
+        }
+    });
+    rx
+}
+
+/// disk scan in index order; rows skipped at index time are skipped again
+struct DiskIter<'a> {
+    headers: &'a HeaderMap,
+    offsets: &'a [u64],
+    rdr: Option<csv::Reader<File>>,
+    next: usize,
+    record: ByteRecord,
+}
+
+impl Iterator for DiskIter<'_> {
+    type Item = TimelineEvent;
+
+    fn next(&mut self) -> Option<TimelineEvent> {
+        let want = *self.offsets.get(self.next)?;
+        let rdr = self.rdr.as_mut()?;
+        loop {
+            match rdr.read_byte_record(&mut self.record) {
+                Ok(true) => {}
+                Err(e) if !e.is_io_error() => continue,
+                _ => {
+                    self.rdr = None;
+                    return None;
+                }
+            }
+            if self.record.position().map(|p| p.byte()) != Some(want) {
+                continue;
+            }
+            self.next += 1;
+            return self.headers.event(&self.record).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_fixture_and_read_on_demand() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixture_sample.csv");
+        let mut seen = 0;
+        let store = SourceStore::open(&Source::new(path), |_| seen += 1).unwrap();
+        assert!(store.is_disk_backed());
+        assert_eq!(seen, 2);
+        assert_eq!(store.len(), 2);
+        let ev = store.get(1).unwrap();
+        assert_eq!(ev.action_type.as_deref(), Some("ConnectionSuccess"));
+        let ev = store.get(0).unwrap();
+        assert_eq!(ev.action_type.as_deref(), Some("ProcessCreated"));
+        assert!(store.get(2).is_none());
+        assert_eq!(store.iter().count(), 2);
+    }
+
+    #[test]
+    fn read_stream_sources_in_reverse() {
+        use std::io::Write;
+        let dir = std::env::temp_dir().join(format!("rusty-lens-store-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let rows = 3 * STREAM_WINDOW + 7;
+        let gz = dir.join("timeline.csv.gz");
+        let mut enc =
+            flate2::write::GzEncoder::new(File::create(&gz).unwrap(), flate2::Compression::fast());
+        writeln!(enc, "Event Time,Action Type,File Name").unwrap();
+        for i in 0..rows {
+            writeln!(enc, "2024-01-01T00:00:00Z,ProcessCreated,f{}.exe", i).unwrap();
+        }
+        enc.finish().unwrap();
+        let json = dir.join("timeline.json");
+        let items: Vec<String> = (0..rows)
+            .map(|i| {
+                format!(
+                    "  {{\"ActionType\": \"FileCreated\", \"FileName\": \"f{}.exe\"}}",
+                    i
+                )
+            })
+            .collect();
+        std::fs::write(
+            &json,
+            format!("{{\"value\": [\n{}\n]}}\n", items.join(",\n")),
+        )
+        .unwrap();
+        for path in [gz, json] {
+            let store = SourceStore::open(&Source::new(&path), |_| {}).unwrap();
+            assert!(!store.is_disk_backed());
+            assert_eq!(store.len(), rows);
+            let Backend::Stream { resume, cursor, .. } = &store.backend else {
+                unreachable!()
+            };
+            assert_eq!(resume.len(), rows / RESUME_EVERY);
+            for i in (0..rows).rev() {
+                let ev = store.get(i).unwrap();
+                assert_eq!(
+                    ev.file_name.as_deref(),
+                    Some(format!("f{}.exe", i).as_str())
+                );
+            }
+            // a read far ahead starts at the resume point before it, not from the top
+            assert!(store.get(rows - 1).is_some());
+            let first = cursor.borrow().as_ref().map(|c| c.first);
+            assert_eq!(first, Some(resume.last().unwrap().0));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn create_leaves_out_sources_that_fail_to_open() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let sources = [
+            Source::new(dir.join("fixture_sample.csv")),
+            Source::with_member(dir.join("fixture_bundle.zip"), Some("missing.csv".into())),
+            Source::new(dir.join("fixture_sample.csv.gz")),
+        ];
+        let (store, failed) = EventStore::create(&sources);
+        assert_eq!(store.source_count(), 2);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, 1);
+        assert!(failed[0].1.to_string().contains("missing.csv"));
+    }
+
+    #[test]
+    fn merge_zip_members_by_time() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixture_bundle.zip");
+        let sources = input::expand_inputs(&[path], None).unwrap();
+        assert_eq!(sources.len(), 2);
+        let (mut store, failed) = EventStore::create(&sources);
+        assert!(failed.is_empty());
+        for (i, source) in sources.iter().enumerate() {
+            scan_timeline(source, |step, _| {
+                if let ScanStep::Row { offset, event } = step {
+                    store.push(i, offset, event);
+                }
+                true
+            })
+            .unwrap();
+        }
+        store.sort_by_time();
+        let got: Vec<(String, String)> = (0..store.len())
+            .map(|i| store.get(i).unwrap())
+            .map(|ev| (ev.source.unwrap().to_string(), ev.action_type.unwrap()))
+            .collect();
+        assert_eq!(
+            got,
+            [
+                ("host1/timeline.csv", "ProcessCreated"),
+                ("host2/timeline.csv", "ProcessCreated"),
+                ("host1/timeline.csv", "ConnectionSuccess"),
+            ]
+            .map(|(s, a)| (s.to_string(), a.to_string()))
+        );
+        let mut seen: Vec<usize> = store.iter().map(|(i, _)| i).collect();
+        seen.sort_unstable();
+        assert_eq!(seen, [0, 1, 2]);
+        // zip members are not seekable: rows are decoded again, also after reading past them
+        assert!(!store.sources[0].is_disk_backed());
+        assert_eq!(
+            store.get(0).unwrap().action_type.as_deref(),
+            Some("ProcessCreated")
+        );
+    }
+}
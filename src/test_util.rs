@@ -0,0 +1,60 @@
+// helpers for the unit tests: events built from column values, and temp paths that are removed
+// when the test ends, passed or failed
+
+use crate::csv_parser::TIMELINE_HEADERS;
+use crate::timeline::TimelineEvent;
+use serde_json::{Map, Value};
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// event with the given columns set, by timeline header: `event(&[("Action Type", "x")])`
+pub fn event(columns: &[(&str, &str)]) -> TimelineEvent {
+    let mut map = Map::new();
+    for &(column, value) in columns {
+        assert!(
+            TIMELINE_HEADERS.contains(&column),
+            "no timeline column {column:?}"
+        );
+        map.insert(column.to_string(), Value::from(value));
+    }
+    serde_json::from_value(Value::Object(map)).unwrap()
+}
+
+/// a file or directory path under the temp dir, unique to this process and call, removed (with
+/// everything under it) when dropped, so also when an assertion fails
+pub struct TempPath(PathBuf);
+
+impl TempPath {
+    /// `name` ends the file name (keep the extension the test needs)
+    pub fn new(name: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        Self(std::env::temp_dir().join(format!("rusty-lens-{}-{}-{}", std::process::id(), n, name)))
+    }
+}
+
+impl Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if self.0.is_dir() {
+            let _ = fs::remove_dir_all(&self.0);
+        } else {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+}
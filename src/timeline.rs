@@ -1,9 +1,14 @@
 // defender timeline event: one row of the 66-column csv
 
-use chrono::{Duration, Local, NaiveDateTime};
+use crate::csv_parser::TIMELINE_HEADERS;
+use crate::query::Query;
+use crate::zone::{parse_offset, DisplayZone};
+use chrono::{Duration, FixedOffset, NaiveDateTime, Offset, Utc};
 use serde::Deserialize;
+use std::sync::Arc;
 
-/// parse relative time range from string; `now` is reference (e.g. Local::now().naive_local()).
+/// parse relative time range from string; `now` is the reference wall-clock time in the zone the
+/// range is meant for (see `DisplayZone::now`), and the range is in that zone too.
 /// returns (start, end) inclusive; e.g. "today" -> (start_of_today, end_of_today).
 pub fn parse_relative_range(
     s: &str,
@@ -55,39 +60,67 @@ pub fn parse_relative_range(
     }
 }
 
-/// reference "now" for relative times (local time)
-pub fn now_for_relative() -> NaiveDateTime {
-    Local::now().naive_local()
+/// true if `t` falls within [start, end] (inclusive); missing start/end means no bound and an
+/// event without a time only passes when there is no bound at all
+pub fn time_in_range(
+    t: Option<NaiveDateTime>,
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+) -> bool {
+    let Some(t) = t else {
+        return start.is_none() && end.is_none();
+    };
+    start.is_none_or(|s| t >= s) && end.is_none_or(|e| t <= e)
 }
 
-/// parse iso-like timestamp (event time or user input); tries a few formats
+/// parse iso-like timestamp as utc (event times are utc; a `Z` or `+hh:mm` suffix is honoured).
+/// tries a few formats; any number of fractional digits is accepted.
 pub fn parse_time(s: &str) -> Option<NaiveDateTime> {
+    let (t, offset) = parse_time_parts(s)?;
+    Some(t - offset.unwrap_or(Utc.fix()))
+}
+
+/// timestamp as written plus its `Z` / `+hh:mm` suffix, if any (see `parse_time`)
+pub fn parse_time_parts(s: &str) -> Option<(NaiveDateTime, Option<FixedOffset>)> {
     let s = s.trim().trim_matches('"').trim();
+    let (s, offset) = split_offset(s);
     if s.is_empty() {
         return None;
     }
     const FORMATS: &[&str] = &[
-        "%Y-%m-%dT%H:%M:%S%.3f",
-        "%Y-%m-%dT%H:%M:%S",
-        "%Y-%m-%d %H:%M:%S%.3f",
-        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
         "%Y-%m-%d %H:%M",
-        "%Y-%m-%d",
     ];
     for fmt in FORMATS {
         if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
-            return Some(dt);
+            return Some((dt, offset));
         }
     }
     // allow date without time -> start of day
     if s.len() >= 10 {
         if let Ok(d) = chrono::NaiveDate::parse_from_str(&s[..10], "%Y-%m-%d") {
-            return Some(d.and_hms_opt(0, 0, 0).unwrap());
+            return Some((d.and_hms_opt(0, 0, 0).unwrap(), offset));
         }
     }
     None
 }
 
+/// split a trailing `Z` or utc offset off a timestamp (only after the date part)
+fn split_offset(s: &str) -> (&str, Option<FixedOffset>) {
+    if let Some(rest) = s.strip_suffix(['Z', 'z']) {
+        return (rest.trim_end(), Some(Utc.fix()));
+    }
+    if let Some(i) = s.rfind(['+', '-']) {
+        if i > 10 {
+            if let Some(o) = parse_offset(s[i..].trim()) {
+                return (s[..i].trim_end(), Some(o));
+            }
+        }
+    }
+    (s, None)
+}
+
 /// one device timeline event (66 columns); empty csv cells deserialize as None
 #[derive(Debug, Clone, Default, Deserialize)]
 #[serde(default)]
@@ -190,6 +223,7 @@ pub struct TimelineEvent {
     pub typed_details: Option<String>,
     #[serde(rename = "App Guard Container Id")]
     pub app_guard_container_id: Option<String>,
+    #[serde(rename = "Protocol")]
     pub protocol: Option<String>,
     #[serde(rename = "Logon Type")]
     pub logon_type: Option<String>,
@@ -215,12 +249,21 @@ pub struct TimelineEvent {
     pub is_azure_info_protection_applied: Option<String>,
     #[serde(rename = "Alert Ids")]
     pub alert_ids: Option<String>,
+    #[serde(rename = "Categories")]
     pub categories: Option<String>,
+    #[serde(rename = "Severities")]
     pub severities: Option<String>,
     #[serde(rename = "Is Marked")]
     pub is_marked: Option<String>,
     #[serde(rename = "Data Type")]
     pub data_type: Option<String>,
+    /// columns with no field above (new export columns, advanced hunting extras), in column
+    /// order; filled by the readers, not by serde
+    #[serde(skip)]
+    pub extra: Vec<(Arc<str>, String)>,
+    /// label of the file this row came from; set only when several files are merged
+    #[serde(skip)]
+    pub source: Option<Arc<str>>,
 }
 
 impl TimelineEvent {
@@ -231,26 +274,25 @@ impl TimelineEvent {
 
     /// true if event time falls within [start, end] (inclusive); missing start/end means no bound
     pub fn in_time_range(&self, start: Option<NaiveDateTime>, end: Option<NaiveDateTime>) -> bool {
-        let t = match self.event_time_parsed() {
-            Some(t) => t,
-            None => return start.is_none() && end.is_none(),
-        };
-        if let Some(s) = start {
-            if t < s {
-                return false;
-            }
-        }
-        if let Some(e) = end {
-            if t > e {
-                return false;
-            }
+        time_in_range(self.event_time_parsed(), start, end)
+    }
+
+    /// event time as wall-clock time in `zone` (the raw text if it does not parse)
+    pub fn display_time(&self, zone: &DisplayZone) -> String {
+        match self.event_time_parsed() {
+            Some(t) => zone.format(t, "%Y-%m-%d %H:%M:%S%.3f"),
+            None => self
+                .event_time
+                .as_deref()
+                .unwrap_or("")
+                .trim_matches('"')
+                .to_string(),
         }
-        true
     }
 
-    /// short one-line summary for list view: time | action | file or process
-    pub fn list_line(&self) -> String {
-        let time = self.event_time.as_deref().unwrap_or("").trim_matches('"');
+    /// short one-line summary for list view: time (in `zone`) | [source |] action | file or process
+    pub fn list_line(&self, zone: &DisplayZone) -> String {
+        let time = self.display_time(zone);
         let action = self.action_type.as_deref().unwrap_or("—");
         let file = self
             .file_name
@@ -263,238 +305,133 @@ impl TimelineEvent {
             .as_deref()
             .unwrap_or("")
             .trim_matches('"');
-        if file.is_empty() {
-            format!("{} | {} | {}", time, action, computer)
-        } else {
-            format!("{} | {} | {}", time, action, file)
+        let last = if file.is_empty() { computer } else { file };
+        match self.source.as_deref() {
+            Some(src) => format!("{} | {} | {} | {}", time, src, action, last),
+            None => format!("{} | {} | {}", time, action, last),
         }
     }
 
+    /// every timeline field as (column name, value), in `TIMELINE_HEADERS` order
+    pub fn fields(&self) -> impl Iterator<Item = (&'static str, Option<&str>)> {
+        TIMELINE_HEADERS.iter().copied().zip(self.values())
+    }
+
+    /// value of the field at `column` in `TIMELINE_HEADERS`
+    pub fn field(&self, column: usize) -> Option<&str> {
+        self.values().get(column).copied().flatten()
+    }
+
+    /// field values in `TIMELINE_HEADERS` order (the struct's field order)
+    fn values(&self) -> [Option<&str>; 66] {
+        [
+            self.event_time.as_deref(),
+            self.machine_id.as_deref(),
+            self.computer_name.as_deref(),
+            self.action_type.as_deref(),
+            self.file_name.as_deref(),
+            self.folder_path.as_deref(),
+            self.sha1.as_deref(),
+            self.sha256.as_deref(),
+            self.md5.as_deref(),
+            self.process_command_line.as_deref(),
+            self.account_domain.as_deref(),
+            self.account_name.as_deref(),
+            self.account_sid.as_deref(),
+            self.logon_id.as_deref(),
+            self.process_id.as_deref(),
+            self.process_creation_time.as_deref(),
+            self.process_token_elevation.as_deref(),
+            self.registry_key.as_deref(),
+            self.registry_value_name.as_deref(),
+            self.registry_value_data.as_deref(),
+            self.remote_url.as_deref(),
+            self.remote_computer_name.as_deref(),
+            self.remote_ip.as_deref(),
+            self.remote_port.as_deref(),
+            self.local_ip.as_deref(),
+            self.local_port.as_deref(),
+            self.file_origin_url.as_deref(),
+            self.file_origin_ip.as_deref(),
+            self.initiating_process_sha1.as_deref(),
+            self.initiating_process_sha256.as_deref(),
+            self.initiating_process_file_name.as_deref(),
+            self.initiating_process_folder_path.as_deref(),
+            self.initiating_process_id.as_deref(),
+            self.initiating_process_command_line.as_deref(),
+            self.initiating_process_creation_time.as_deref(),
+            self.initiating_process_integrity_level.as_deref(),
+            self.initiating_process_token_elevation.as_deref(),
+            self.initiating_process_parent_id.as_deref(),
+            self.initiating_process_parent_file_name.as_deref(),
+            self.initiating_process_parent_creation_time.as_deref(),
+            self.initiating_process_md5.as_deref(),
+            self.initiating_process_account_domain.as_deref(),
+            self.initiating_process_account_name.as_deref(),
+            self.initiating_process_account_sid.as_deref(),
+            self.initiating_process_logon_id.as_deref(),
+            self.report_id.as_deref(),
+            self.additional_fields.as_deref(),
+            self.typed_details.as_deref(),
+            self.app_guard_container_id.as_deref(),
+            self.protocol.as_deref(),
+            self.logon_type.as_deref(),
+            self.process_integrity_level.as_deref(),
+            self.registry_value_type.as_deref(),
+            self.previous_registry_value_name.as_deref(),
+            self.previous_registry_value_data.as_deref(),
+            self.previous_registry_key.as_deref(),
+            self.file_origin_referrer_url.as_deref(),
+            self.sensitivity_label.as_deref(),
+            self.sensitivity_sub_label.as_deref(),
+            self.is_endpoint_dlp_applied.as_deref(),
+            self.is_azure_info_protection_applied.as_deref(),
+            self.alert_ids.as_deref(),
+            self.categories.as_deref(),
+            self.severities.as_deref(),
+            self.is_marked.as_deref(),
+            self.data_type.as_deref(),
+        ]
+    }
+
     /// all non-empty fields for detail view (label: value)
     pub fn detail_lines(&self) -> Vec<(String, String)> {
         let mut out = Vec::new();
-        let mut push = |label: &str, v: Option<&String>| {
-            if let Some(s) = v {
-                let s = s.trim_matches('"').trim();
-                if !s.is_empty() {
-                    out.push((label.to_string(), s.to_string()));
-                }
+        for (label, value) in self.fields() {
+            let s = value.unwrap_or("").trim_matches('"').trim();
+            if !s.is_empty() {
+                out.push((label.to_string(), s.to_string()));
             }
-        };
-        push("Event Time", self.event_time.as_ref());
-        push("Machine Id", self.machine_id.as_ref());
-        push("Computer Name", self.computer_name.as_ref());
-        push("Action Type", self.action_type.as_ref());
-        push("File Name", self.file_name.as_ref());
-        push("Folder Path", self.folder_path.as_ref());
-        push("Sha1", self.sha1.as_ref());
-        push("Sha256", self.sha256.as_ref());
-        push("MD5", self.md5.as_ref());
-        push("Process Command Line", self.process_command_line.as_ref());
-        push("Account Domain", self.account_domain.as_ref());
-        push("Account Name", self.account_name.as_ref());
-        push("Account Sid", self.account_sid.as_ref());
-        push("Logon Id", self.logon_id.as_ref());
-        push("Process Id", self.process_id.as_ref());
-        push("Process Creation Time", self.process_creation_time.as_ref());
-        push(
-            "Process Token Elevation",
-            self.process_token_elevation.as_ref(),
-        );
-        push("Registry Key", self.registry_key.as_ref());
-        push("Registry Value Name", self.registry_value_name.as_ref());
-        push("Registry Value Data", self.registry_value_data.as_ref());
-        push("Remote Url", self.remote_url.as_ref());
-        push("Remote Computer Name", self.remote_computer_name.as_ref());
-        push("Remote IP", self.remote_ip.as_ref());
-        push("Remote Port", self.remote_port.as_ref());
-        push("Local IP", self.local_ip.as_ref());
-        push("Local Port", self.local_port.as_ref());
-        push("File Origin Url", self.file_origin_url.as_ref());
-        push("File Origin IP", self.file_origin_ip.as_ref());
-        push(
-            "Initiating Process SHA1",
-            self.initiating_process_sha1.as_ref(),
-        );
-        push(
-            "Initiating Process SHA256",
-            self.initiating_process_sha256.as_ref(),
-        );
-        push(
-            "Initiating Process File Name",
-            self.initiating_process_file_name.as_ref(),
-        );
-        push(
-            "Initiating Process Folder Path",
-            self.initiating_process_folder_path.as_ref(),
-        );
-        push("Initiating Process Id", self.initiating_process_id.as_ref());
-        push(
-            "Initiating Process Command Line",
-            self.initiating_process_command_line.as_ref(),
-        );
-        push(
-            "Initiating Process Creation Time",
-            self.initiating_process_creation_time.as_ref(),
-        );
-        push(
-            "Initiating Process Integrity Level",
-            self.initiating_process_integrity_level.as_ref(),
-        );
-        push(
-            "Initiating Process Token Elevation",
-            self.initiating_process_token_elevation.as_ref(),
-        );
-        push(
-            "Initiating Process Parent Id",
-            self.initiating_process_parent_id.as_ref(),
-        );
-        push(
-            "Initiating Process Parent File Name",
-            self.initiating_process_parent_file_name.as_ref(),
-        );
-        push(
-            "Initiating Process Parent Creation Time",
-            self.initiating_process_parent_creation_time.as_ref(),
-        );
-        push(
-            "Initiating Process MD5",
-            self.initiating_process_md5.as_ref(),
-        );
-        push(
-            "Initiating Process Account Domain",
-            self.initiating_process_account_domain.as_ref(),
-        );
-        push(
-            "Initiating Process Account Name",
-            self.initiating_process_account_name.as_ref(),
-        );
-        push(
-            "Initiating Process Account Sid",
-            self.initiating_process_account_sid.as_ref(),
-        );
-        push(
-            "Initiating Process Logon Id",
-            self.initiating_process_logon_id.as_ref(),
-        );
-        push("Report Id", self.report_id.as_ref());
-        push("Additional Fields", self.additional_fields.as_ref());
-        push("Typed Details", self.typed_details.as_ref());
-        push(
-            "App Guard Container Id",
-            self.app_guard_container_id.as_ref(),
-        );
-        push("Protocol", self.protocol.as_ref());
-        push("Logon Type", self.logon_type.as_ref());
-        push(
-            "Process Integrity Level",
-            self.process_integrity_level.as_ref(),
-        );
-        push("Registry Value Type", self.registry_value_type.as_ref());
-        push(
-            "Previous Registry Value Name",
-            self.previous_registry_value_name.as_ref(),
-        );
-        push(
-            "Previous Registry Value Data",
-            self.previous_registry_value_data.as_ref(),
-        );
-        push("Previous Registry Key", self.previous_registry_key.as_ref());
-        push(
-            "File Origin Referrer Url",
-            self.file_origin_referrer_url.as_ref(),
-        );
-        push("Sensitivity Label", self.sensitivity_label.as_ref());
-        push("Sensitivity Sub Label", self.sensitivity_sub_label.as_ref());
-        push(
-            "Is Endpoint Dlp Applied",
-            self.is_endpoint_dlp_applied.as_ref(),
-        );
-        push(
-            "Is Azure Info Protection Applied",
-            self.is_azure_info_protection_applied.as_ref(),
-        );
-        push("Alert Ids", self.alert_ids.as_ref());
-        push("Categories", self.categories.as_ref());
-        push("Severities", self.severities.as_ref());
-        push("Is Marked", self.is_marked.as_ref());
-        push("Data Type", self.data_type.as_ref());
+        }
+        for (name, value) in &self.extra {
+            out.push((name.to_string(), value.clone()));
+        }
+        if let Some(src) = self.source.as_deref() {
+            out.push(("Source".to_string(), src.to_string()));
+        }
         out
     }
 
-    /// concatenation of all searchable text (one string for multi-token matching)
-    fn searchable_text(&self) -> String {
+    /// every field value lowercased, one per line (so a term never matches across two fields)
+    pub(crate) fn searchable_text(&self) -> String {
         let mut out = String::new();
-        let mut push = |s: Option<&String>| {
-            if let Some(x) = s {
-                out.push_str(&x.to_lowercase());
-                out.push(' ');
-            }
-        };
-        push(self.event_time.as_ref());
-        push(self.machine_id.as_ref());
-        push(self.computer_name.as_ref());
-        push(self.action_type.as_ref());
-        push(self.file_name.as_ref());
-        push(self.folder_path.as_ref());
-        push(self.sha1.as_ref());
-        push(self.sha256.as_ref());
-        push(self.md5.as_ref());
-        push(self.process_command_line.as_ref());
-        push(self.account_domain.as_ref());
-        push(self.account_name.as_ref());
-        push(self.account_sid.as_ref());
-        push(self.process_id.as_ref());
-        push(self.process_creation_time.as_ref());
-        push(self.registry_key.as_ref());
-        push(self.registry_value_name.as_ref());
-        push(self.registry_value_data.as_ref());
-        push(self.remote_url.as_ref());
-        push(self.remote_computer_name.as_ref());
-        push(self.remote_ip.as_ref());
-        push(self.remote_port.as_ref());
-        push(self.local_ip.as_ref());
-        push(self.local_port.as_ref());
-        push(self.file_origin_url.as_ref());
-        push(self.file_origin_ip.as_ref());
-        push(self.initiating_process_sha1.as_ref());
-        push(self.initiating_process_sha256.as_ref());
-        push(self.initiating_process_file_name.as_ref());
-        push(self.initiating_process_folder_path.as_ref());
-        push(self.initiating_process_id.as_ref());
-        push(self.initiating_process_command_line.as_ref());
-        push(self.initiating_process_creation_time.as_ref());
-        push(self.initiating_process_parent_file_name.as_ref());
-        push(self.initiating_process_account_domain.as_ref());
-        push(self.initiating_process_account_name.as_ref());
-        push(self.report_id.as_ref());
-        push(self.additional_fields.as_ref());
-        push(self.typed_details.as_ref());
-        push(self.protocol.as_ref());
-        push(self.alert_ids.as_ref());
-        push(self.categories.as_ref());
-        push(self.severities.as_ref());
-        push(self.data_type.as_ref());
+        let values = self
+            .values()
+            .into_iter()
+            .flatten()
+            .chain(self.extra.iter().map(|(_, v)| v.as_str()));
+        for value in values {
+            out.push_str(&value.to_lowercase());
+            out.push('\n');
+        }
         out
     }
 
-    /// true if event matches `needle` (case-insensitive). empty needle = match all.
-    /// multi-word: space-separated tokens are ANDed (all must appear in searchable fields).
+    /// true if event matches the search query `needle` (see `Query`); empty matches all and
+    /// a query that does not parse matches nothing. the event's extra columns can be named as
+    /// fields. compile a `Query` once to test many events.
     pub fn matches_search(&self, needle: &str) -> bool {
-        let needle = needle.trim();
-        if needle.is_empty() {
-            return true;
-        }
-        let haystack = self.searchable_text();
-        let tokens: Vec<String> = needle
-            .to_lowercase()
-            .split_whitespace()
-            .map(|s| s.to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
-        if tokens.is_empty() {
-            return true;
-        }
-        tokens.iter().all(|t| haystack.contains(t.as_str()))
+        let extra: Vec<&str> = self.extra.iter().map(|(name, _)| &**name).collect();
+        Query::parse(needle, &extra).is_ok_and(|q| q.matches(self))
     }
 }
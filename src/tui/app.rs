@@ -1,12 +1,36 @@
 // app state for timeline tui
 
-use crate::csv_parser;
-use crate::filters::{unique_action_types, unique_dates_from_events, unique_hours_for_date};
-use crate::timeline::{now_for_relative, parse_relative_range, parse_time, TimelineEvent};
+use crate::csv_parser::{LoadReport, ScanProgress, Schema};
+use crate::filters::{ActionTypeFilter, TimelineSummary};
+use crate::histogram::Histogram;
+use crate::index::{Bits, Candidates, SearchIndex};
+use crate::input::{self, Source};
+use crate::ioc::IocList;
+use crate::library::{FilterLibrary, SavedFilter};
+use crate::loader::{self, LoadMsg};
+use crate::noise::{NoiseProfile, Rule};
+use crate::proctree::ProcessTree;
+use crate::query::{self, Highlighter, Query};
+use crate::sigma::SigmaRules;
+use crate::stacking::Stack;
+use crate::store::EventStore;
+use crate::timeline::{parse_relative_range, time_in_range, TimelineEvent};
+use crate::zone::DisplayZone;
 use chrono::{NaiveDate, NaiveDateTime, Timelike};
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::{Duration, Instant};
 
-const MAX_LOAD_ROWS: usize = 100_000;
+/// `apply_filters` scans the whole store in order once more than 1/N of the rows need reading
+const SCAN_FRACTION: usize = 8;
+/// quiet time after a keystroke before the search input is previewed
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(150);
+/// max time spent on a live search pass per ui tick
+const SEARCH_POLL_BUDGET: Duration = Duration::from_millis(30);
+/// max time spent draining loader batches per ui tick
+const LOAD_POLL_BUDGET: Duration = Duration::from_millis(50);
+/// unmapped column names spelled out in the load message
+const MAX_UNMAPPED_LISTED: usize = 6;
 
 /// preset labels for time picker; last two are custom (date picker, then type range)
 pub const TIME_PRESETS: &[&str] = &[
@@ -25,6 +49,51 @@ pub enum Mode {
     SearchInput,
     ActionTypeFilter,
     TimeFilter,
+    /// inspecting rows rejected at load time
+    Rejected,
+    /// picking one source file to restrict the view to
+    SourceFilter,
+    /// exclusion rules panel
+    Exclusions,
+    /// sigma rules with their hit counts
+    Detections,
+    /// saved filters library
+    Library,
+    /// process genealogy around the selected event
+    ProcessTree,
+    /// field cursor in the detail panel
+    Detail,
+    /// event density over time, with a bar cursor
+    Histogram,
+    /// value combinations of a few fields with their counts
+    Stacking,
+}
+
+/// filters in effect before a process pivot, restored by going back
+#[derive(Debug, Clone)]
+pub struct Breadcrumb {
+    /// the view as shown in the trail ("all events", "filtered" or a process)
+    pub label: String,
+    search: String,
+    query: Query,
+    action_type_filter: ActionTypeFilter,
+    source_filter: Option<usize>,
+    time_range_start: Option<NaiveDateTime>,
+    time_range_end: Option<NaiveDateTime>,
+    ioc_only: bool,
+    detection_filter: Option<usize>,
+    process_filter: Option<usize>,
+    /// selected row (event index) to return to
+    row: Option<usize>,
+}
+
+/// one line of the process tree view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeLine {
+    /// a process (node in `App::processes`)
+    Process { node: usize, depth: usize },
+    /// an event the process above initiated (row in `App::events`)
+    Activity { row: u32, depth: usize },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -37,17 +106,147 @@ pub enum TimeFilterSub {
     Custom,
 }
 
+/// background load in progress: batches arrive on `rx`
+#[derive(Debug)]
+pub struct LoadState {
+    pub rx: Receiver<LoadMsg>,
+    pub progress: ScanProgress,
+    /// sources that could not be read, with the reason
+    pub failed: Vec<String>,
+}
+
+/// the current filters as seen by the index (see `App::apply_filters`)
+#[derive(Debug)]
+struct FilterPlan {
+    /// rows that may pass search and action type
+    rows: Bits,
+    query: Candidates,
+    /// per enabled exclusion rule, in `NoiseProfile::in_effect` order
+    rules: Vec<Candidates>,
+    /// rows the index cannot decide, so they must be read
+    to_read: usize,
+    source: Option<usize>,
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+}
+
+/// a live search pass over `plan.rows`, run a slice per tick (see `App::poll_search`)
+#[derive(Debug)]
+struct SearchPass {
+    plan: FilterPlan,
+    /// next row to decide
+    next: usize,
+    hidden: Vec<usize>,
+    /// rows the loader added meanwhile that pass the filters; they come after every row the
+    /// pass decides, so they are listed once it is done (keeping the list in row order)
+    arrived: Vec<usize>,
+}
+
+enum Verdict {
+    Shown,
+    /// hidden by this exclusion rule
+    Hidden(usize),
+    Filtered,
+}
+
 #[derive(Debug)]
 pub struct App {
-    pub path: PathBuf,
-    pub events: Vec<TimelineEvent>,
-    pub action_types: Vec<String>,
+    /// input files (zip members count separately), in load order
+    pub sources: Vec<Source>,
+    /// on-disk index of all rows; events are read on demand
+    pub events: EventStore,
+    /// action types, dates and hours seen at load time (for pickers)
+    pub summary: TimelineSummary,
+    /// tokens and action types -> rows, built as rows load (answers `apply_filters`)
+    pub index: SearchIndex,
+    /// set while the worker is still indexing the file
+    pub loading: Option<LoadState>,
+    /// rows that failed to parse (filled in as the loader runs)
+    pub load_report: LoadReport,
+    pub rejected_list_state: ratatui::widgets::ListState,
+    /// action types seen so far with their event counts
+    pub action_types: Vec<(String, usize)>,
     pub filtered_indices: Vec<usize>,
     pub list_state: ratatui::widgets::ListState,
-    pub action_type_filter: Option<String>,
+    /// applied action type include/exclude sets
+    pub action_type_filter: ActionTypeFilter,
+    /// marks being edited in the action type picker (applied on Enter)
+    pub action_type_marks: ActionTypeFilter,
+    /// type-ahead text narrowing the action type picker
+    pub action_type_query: String,
+    /// restrict to one source (index into `sources`); only offered when several files are
+    /// loaded
+    pub source_filter: Option<usize>,
+    pub source_list_state: ratatui::widgets::ListState,
+    /// indicators from `--ioc`; events touching one are flagged
+    pub ioc: IocList,
+    /// rows that hit an indicator, ascending
+    pub ioc_rows: Vec<u32>,
+    /// show only rows that hit an indicator
+    pub ioc_only: bool,
+    /// rules from `--sigma`, most severe first; matching events are tagged
+    pub sigma: SigmaRules,
+    /// per rule: rows it matched, ascending
+    pub detection_rows: Vec<Vec<u32>>,
+    /// rows matched by any rule, ascending
+    pub detected_rows: Vec<u32>,
+    /// show only the hits of this rule
+    pub detection_filter: Option<usize>,
+    /// processes and their parents, children and activity, built as rows load
+    pub processes: ProcessTree,
+    /// show only events this process initiated or was created by (see `pivot_to_process`)
+    pub process_filter: Option<usize>,
+    /// views left by pivoting, oldest first
+    pub breadcrumbs: Vec<Breadcrumb>,
+    /// process tree view: flattened lines under `tree_root`, and the expanded nodes
+    pub tree_lines: Vec<TreeLine>,
+    pub tree_root: Option<usize>,
+    pub tree_expanded: HashSet<usize>,
+    pub tree_list_state: ratatui::widgets::ListState,
+    /// histogram of the filtered events as last built, and the (rows, buckets) it was built for
+    pub histogram: Option<Histogram>,
+    histogram_key: (usize, usize),
+    /// bar under the cursor in the histogram
+    pub histogram_cursor: usize,
+    /// time ranges left by zooming into a bar, oldest first
+    pub histogram_zoom: Vec<(Option<NaiveDateTime>, Option<NaiveDateTime>)>,
+    /// stacking view: the counted combinations, the fields being typed (None when not
+    /// typing) and the fields last counted
+    pub stack: Option<Stack>,
+    pub stack_input: Option<String>,
+    pub stack_fields: String,
+    pub stack_error: Option<String>,
+    pub stack_list_state: ratatui::widgets::ListState,
+    pub detections_list_state: ratatui::widgets::ListState,
+    /// exclusion rules: events matching an enabled rule are hidden
+    pub noise: NoiseProfile,
+    /// per rule: events it hid in the last filter pass (first matching rule counts)
+    pub noise_hidden: Vec<usize>,
+    pub noise_list_state: ratatui::widgets::ListState,
+    /// query being typed for a new exclusion rule (in the Exclusions panel)
+    pub noise_input: Option<String>,
+    /// named filters (search, action types, time range, exclusions) from `--filters`
+    pub library: FilterLibrary,
+    pub library_list_state: ratatui::widgets::ListState,
+    /// name being typed to save the current filters under (in the Library panel)
+    pub library_input: Option<String>,
     pub search: String,
+    /// `search` parsed (see `Query`)
+    pub query: Query,
+    /// marks what `query` matched; built with it (see `set_query`), not per frame
+    pub highlighter: Highlighter,
     pub search_input: String,
-    /// time range filter (inclusive)
+    /// SEARCH mode takes a regular expression instead of a query (`search` then starts "re:")
+    pub search_regex: bool,
+    /// why `search_input` did not parse (shown while still in SEARCH mode)
+    pub search_error: Option<String>,
+    /// when `search_input` last changed, until it is previewed
+    search_edited: Option<Instant>,
+    /// input (with `re:` when a regex) whose results the list shows while typing
+    pub search_preview: Option<String>,
+    /// preview still filtering; the list fills in as it runs
+    search_pass: Option<SearchPass>,
+    /// time range filter (inclusive), as wall-clock times in `zone`
     pub time_range_start: Option<NaiveDateTime>,
     pub time_range_end: Option<NaiveDateTime>,
     /// buffer while in TimeFilter mode (Custom sub)
@@ -55,7 +254,11 @@ pub struct App {
     /// when TimeFilter: show preset list, date picker, or custom text input
     pub time_filter_sub: TimeFilterSub,
     pub time_picker_list_state: ratatui::widgets::ListState,
-    /// unique dates in loaded events (for custom date range picker)
+    /// zone times are shown and entered in (event times are utc)
+    pub zone: DisplayZone,
+    /// zone from `--tz`; `z` toggles between it and utc
+    pub configured_zone: DisplayZone,
+    /// unique dates in loaded events, in `zone` (for custom date range picker)
     pub unique_dates: Vec<NaiveDate>,
     /// when CustomRangeEnd: dates >= start to choose end from
     pub date_picker_end_dates: Vec<NaiveDate>,
@@ -64,6 +267,10 @@ pub struct App {
     pub date_picker_list_state: ratatui::widgets::ListState,
     pub should_quit: bool,
     pub detail_scroll: u16,
+    /// detail rows holding a search match, as last drawn (for n / N)
+    pub detail_match_rows: Vec<u16>,
+    /// field under the cursor in `Mode::Detail` (index into `detail_lines`)
+    pub detail_cursor: usize,
     pub theme: crate::tui::theme::Theme,
     pub flash: Option<String>,
     pub error: Option<String>,
@@ -72,65 +279,360 @@ pub struct App {
 }
 
 impl App {
-    pub fn new(path: PathBuf) -> anyhow::Result<Self> {
-        let events = csv_parser::load_timeline(&path, Some(MAX_LOAD_ROWS))?;
-        let action_types = unique_action_types(&events);
-        let unique_dates = unique_dates_from_events(&events);
-        let filtered_indices = (0..events.len()).collect::<Vec<_>>();
-        let mut list_state = ratatui::widgets::ListState::default();
-        if !filtered_indices.is_empty() {
-            list_state.select(Some(0));
-        }
-        Ok(Self {
-            path,
+    /// open `sources` (header rows only) and start indexing rows in the background; a source
+    /// that cannot be opened is reported and left out (an error only when none can be);
+    /// several sources are merged into one view, sorted by event time once loaded;
+    /// times are shown in `zone`, events matching `noise` are hidden and events touching an
+    /// indicator in `ioc` or matching a rule in `sigma` are flagged; `library` holds the saved
+    /// filters
+    pub fn new(
+        sources: Vec<Source>,
+        zone: DisplayZone,
+        mut noise: NoiseProfile,
+        ioc: IocList,
+        mut sigma: SigmaRules,
+        library: FilterLibrary,
+    ) -> anyhow::Result<Self> {
+        let (events, failed) = EventStore::create(&sources);
+        let labels = input::unique_labels(&sources);
+        let failed: Vec<String> = failed
+            .into_iter()
+            .map(|(i, e)| format!("{}: {}", labels[i], e))
+            .collect();
+        if events.source_count() == 0 && !failed.is_empty() {
+            anyhow::bail!("{}", failed.join("; "));
+        }
+        let sources: Vec<Source> = events.sources().cloned().collect();
+        noise.compile(&events.unmapped_columns());
+        sigma.compile(&events.unmapped_columns());
+        let loading = Some(LoadState {
+            rx: loader::spawn(sources.clone()),
+            progress: ScanProgress::default(),
+            failed: Vec::new(),
+        });
+        let mut app = Self {
+            sources,
             events,
-            action_types,
-            filtered_indices,
-            list_state,
-            action_type_filter: None,
+            summary: TimelineSummary::default(),
+            index: SearchIndex::default(),
+            loading,
+            load_report: LoadReport::default(),
+            rejected_list_state: ratatui::widgets::ListState::default(),
+            action_types: Vec::new(),
+            filtered_indices: Vec::new(),
+            list_state: ratatui::widgets::ListState::default(),
+            action_type_filter: ActionTypeFilter::default(),
+            action_type_marks: ActionTypeFilter::default(),
+            action_type_query: String::new(),
+            source_filter: None,
+            source_list_state: ratatui::widgets::ListState::default(),
+            ioc,
+            ioc_rows: Vec::new(),
+            ioc_only: false,
+            detection_rows: vec![Vec::new(); sigma.len()],
+            sigma,
+            detected_rows: Vec::new(),
+            detection_filter: None,
+            detections_list_state: ratatui::widgets::ListState::default(),
+            processes: ProcessTree::default(),
+            process_filter: None,
+            breadcrumbs: Vec::new(),
+            tree_lines: Vec::new(),
+            tree_root: None,
+            tree_expanded: HashSet::new(),
+            tree_list_state: ratatui::widgets::ListState::default(),
+            histogram: None,
+            histogram_key: (0, 0),
+            histogram_cursor: 0,
+            histogram_zoom: Vec::new(),
+            stack: None,
+            stack_input: None,
+            stack_fields: String::new(),
+            stack_error: None,
+            stack_list_state: ratatui::widgets::ListState::default(),
+            noise_hidden: vec![0; noise.rules.len()],
+            noise,
+            noise_list_state: ratatui::widgets::ListState::default(),
+            noise_input: None,
+            library,
+            library_list_state: ratatui::widgets::ListState::default(),
+            library_input: None,
             search: String::new(),
+            query: Query::default(),
+            highlighter: Highlighter::default(),
             search_input: String::new(),
+            search_regex: false,
+            search_error: None,
+            search_edited: None,
+            search_preview: None,
+            search_pass: None,
             time_range_start: None,
             time_range_end: None,
             time_input: String::new(),
             time_filter_sub: TimeFilterSub::Picker,
             time_picker_list_state: ratatui::widgets::ListState::default(),
-            unique_dates,
+            zone,
+            configured_zone: zone,
+            unique_dates: Vec::new(),
             date_picker_end_dates: Vec::new(),
             date_picker_hours: Vec::new(),
             date_picker_list_state: ratatui::widgets::ListState::default(),
             should_quit: false,
             detail_scroll: 0,
+            detail_match_rows: Vec::new(),
+            detail_cursor: 0,
             theme: crate::tui::theme::Theme,
             flash: None,
             error: None,
             mode: Mode::Normal,
             action_type_list_state: ratatui::widgets::ListState::default(),
-        })
+        };
+        for e in failed {
+            app.load_failed(e);
+        }
+        Ok(app)
     }
 
-    /// recompute filtered indices from current filters
-    pub fn apply_filters(&mut self) {
-        let action_filter = self.action_type_filter.as_deref();
-        let start = self.time_range_start;
-        let end = self.time_range_end;
-        let filtered: Vec<usize> = self
-            .events
-            .iter()
-            .enumerate()
-            .filter(|(_, ev)| {
-                if let Some(at) = action_filter {
-                    if ev.action_type.as_deref() != Some(at) {
-                        return false;
+    /// drain batches from the background loader (bounded per call so the ui stays responsive)
+    pub fn poll_loader(&mut self) {
+        let started = Instant::now();
+        while started.elapsed() < LOAD_POLL_BUDGET {
+            let msg = match &self.loading {
+                Some(state) => match state.rx.try_recv() {
+                    Ok(msg) => msg,
+                    Err(TryRecvError::Empty) => return,
+                    Err(TryRecvError::Disconnected) => {
+                        self.loading = None;
+                        self.set_error("Load failed: loader stopped unexpectedly".to_string());
+                        return;
+                    }
+                },
+                None => return,
+            };
+            match msg {
+                LoadMsg::Batch {
+                    source,
+                    rows,
+                    rejected,
+                    progress,
+                } => {
+                    self.append_rows(source, rows);
+                    for row in rejected {
+                        self.load_report.add(row);
+                    }
+                    if let Some(state) = self.loading.as_mut() {
+                        state.progress = progress;
+                    }
+                }
+                LoadMsg::Done(progress) => {
+                    self.loading = None;
+                    if self.events.source_count() > 1 {
+                        let new_of_old = self.events.sort_by_time();
+                        self.index.remap(&new_of_old);
+                        self.processes.remap(&new_of_old);
+                        for r in &mut self.ioc_rows {
+                            *r = new_of_old[*r as usize];
+                        }
+                        self.ioc_rows.sort_unstable();
+                        for rows in self
+                            .detection_rows
+                            .iter_mut()
+                            .chain(std::iter::once(&mut self.detected_rows))
+                        {
+                            for r in rows.iter_mut() {
+                                *r = new_of_old[*r as usize];
+                            }
+                            rows.sort_unstable();
+                        }
+                        self.apply_filters();
+                    }
+                    let mut msg = format!("Loaded {} events", progress.rows_parsed);
+                    // name the layout unless it is the usual portal timeline
+                    let mut schemas: Vec<String> = Vec::new();
+                    for schema in self.events.schemas() {
+                        let name = schema.name();
+                        if schema != Schema::Timeline && !schemas.contains(&name) {
+                            schemas.push(name);
+                        }
+                    }
+                    if !schemas.is_empty() {
+                        msg.push_str(&format!(" from {}", schemas.join(", ")));
                     }
+                    if progress.rows_skipped > 0 {
+                        msg.push_str(&format!(
+                            " ({} rejected, [ r ] to inspect)",
+                            progress.rows_skipped
+                        ));
+                    }
+                    if !self.ioc.is_empty() {
+                        msg.push_str(&format!(
+                            "  |  {} events hit the {} indicators ([ i ] to show only them)",
+                            self.ioc_rows.len(),
+                            self.ioc.len()
+                        ));
+                    }
+                    if !self.sigma.is_empty() {
+                        let firing = self.detection_rows.iter().filter(|r| !r.is_empty()).count();
+                        msg.push_str(&format!(
+                            "  |  {} of {} sigma rules matched {} events ([ d ] detections)",
+                            firing,
+                            self.sigma.len(),
+                            self.detected_rows.len()
+                        ));
+                    }
+                    let unmapped = self.events.unmapped_columns();
+                    if !unmapped.is_empty() {
+                        let mut names = unmapped
+                            .iter()
+                            .take(MAX_UNMAPPED_LISTED)
+                            .copied()
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        if unmapped.len() > MAX_UNMAPPED_LISTED {
+                            names.push_str(&format!(
+                                ", … (+{})",
+                                unmapped.len() - MAX_UNMAPPED_LISTED
+                            ));
+                        }
+                        msg.push_str(&format!(
+                            "  |  {} unmapped columns kept as extra fields: {}",
+                            unmapped.len(),
+                            names
+                        ));
+                    }
+                    self.set_flash(msg);
+                    return;
                 }
-                if !ev.in_time_range(start, end) {
-                    return false;
+                LoadMsg::Failed(e) => self.load_failed(e),
+            }
+        }
+    }
+
+    /// a source could not be read; the other sources keep loading and Done still merges them
+    fn load_failed(&mut self, e: String) {
+        if let Some(state) = self.loading.as_mut() {
+            state.failed.push(e);
+            let msg = format!("Could not load {}", state.failed.join("; "));
+            self.set_error(msg);
+        }
+    }
+
+    /// index a batch of freshly parsed rows and extend the filtered list with matches
+    fn append_rows(&mut self, source: usize, rows: Vec<(u64, TimelineEvent)>) {
+        let label = if self.sources.len() > 1 {
+            self.events.label(source).cloned()
+        } else {
+            None
+        };
+        for (offset, mut ev) in rows {
+            ev.source = label.clone();
+            self.summary.add(&ev);
+            let matched = self.matches_filters(source, &ev) && !self.hidden_as_noise(&ev);
+            self.index.add(self.events.len(), &ev);
+            self.processes.add(self.events.len(), &ev);
+            if self.ioc.is_hit(&ev) {
+                self.ioc_rows.push(self.events.len() as u32);
+            }
+            let rules = self.sigma.matching(&ev);
+            if !rules.is_empty() {
+                self.detected_rows.push(self.events.len() as u32);
+            }
+            for rule in rules {
+                self.detection_rows[rule].push(self.events.len() as u32);
+            }
+            let idx = self.events.push(source, offset, ev);
+            if matched {
+                match self.search_pass.as_mut() {
+                    Some(pass) => pass.arrived.push(idx),
+                    None => self.filtered_indices.push(idx),
                 }
-                ev.matches_search(self.search.trim())
-            })
-            .map(|(i, _)| i)
-            .collect();
+            }
+        }
+        if self.list_state.selected().is_none() && !self.filtered_indices.is_empty() {
+            self.list_state.select(Some(0));
+        }
+        // keep the action-type picker on the same entry as the list grows
+        let selected_at = self.selected_action_type();
+        self.action_types = self.summary.action_types();
+        if let Some(at) = selected_at {
+            let pos = self
+                .visible_action_types()
+                .iter()
+                .position(|&i| self.action_types[i].0 == at);
+            self.action_type_list_state.select(pos);
+        }
+        self.unique_dates = self.summary.dates(&self.zone);
+    }
+
+    /// true if `ev`, from source `source`, passes the current search, action-type and time
+    /// filters
+    fn matches_filters(&self, source: usize, ev: &TimelineEvent) -> bool {
+        if self.source_filter.is_some_and(|s| s != source) {
+            return false;
+        }
+        if !self.action_type_filter.matches(ev.action_type.as_deref()) {
+            return false;
+        }
+        if self.ioc_only && !self.ioc.is_hit(ev) {
+            return false;
+        }
+        if let Some(rule) = self.detection_filter {
+            if !self.sigma.rule_matches(rule, ev) {
+                return false;
+            }
+        }
+        if let Some(node) = self.process_filter {
+            if !self.processes.involves(node, ev) {
+                return false;
+            }
+        }
+        let start = self.time_range_start.map(|t| self.zone.to_utc(t));
+        let end = self.time_range_end.map(|t| self.zone.to_utc(t));
+        if !ev.in_time_range(start, end) {
+            return false;
+        }
+        self.query.matches(ev)
+    }
+
+    /// true if an exclusion rule hides `ev` (counted against that rule)
+    fn hidden_as_noise(&mut self, ev: &TimelineEvent) -> bool {
+        match self.noise.hiding_rule(ev) {
+            Some(rule) => {
+                self.noise_hidden[rule] += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// recompute filtered indices from current filters and exclusion rules. the index narrows
+    /// the rows; only rows it cannot decide are read back, or the whole store is scanned in
+    /// order when that is most of it.
+    pub fn apply_filters(&mut self) {
+        self.search_pass = None;
+        self.histogram = None;
+        let plan = self.filter_plan();
+        let mut hidden = vec![0; self.noise.rules.len()];
+        let mut filtered = Vec::new();
+        let mut tally = |i: usize, verdict: Verdict| match verdict {
+            Verdict::Shown => filtered.push(i),
+            Verdict::Hidden(rule) => hidden[rule] += 1,
+            Verdict::Filtered => {}
+        };
+        if plan.to_read > self.events.len() / SCAN_FRACTION {
+            for (i, ev) in self.events.iter() {
+                if plan.rows.contains(i) {
+                    tally(i, self.verdict(&plan, i, &mut Some(ev)));
+                }
+            }
+            // sources are scanned one after another; restore merged (time) order
+            filtered.sort_unstable();
+        } else {
+            for i in plan.rows.iter() {
+                tally(i, self.verdict(&plan, i, &mut None));
+            }
+        }
+        self.noise_hidden = hidden;
         self.filtered_indices = filtered;
         self.list_state.select(if self.filtered_indices.is_empty() {
             None
@@ -140,8 +642,94 @@ impl App {
         self.detail_scroll = 0;
     }
 
-    /// selected event (by filtered list index)
-    pub fn selected_event(&self) -> Option<&TimelineEvent> {
+    /// what the index says about the current filters
+    fn filter_plan(&self) -> FilterPlan {
+        let query = self.query.candidates(&self.index);
+        let mut rows = query.sure.clone();
+        rows.or(&query.maybe);
+        rows.and(&self.action_type_filter.rows(&self.index));
+        if self.ioc_only {
+            let mut hits = Bits::new(rows.len());
+            for &r in &self.ioc_rows {
+                hits.insert(r as usize);
+            }
+            rows.and(&hits);
+        }
+        if let Some(rule) = self.detection_filter {
+            let mut hits = Bits::new(rows.len());
+            for &r in &self.detection_rows[rule] {
+                hits.insert(r as usize);
+            }
+            rows.and(&hits);
+        }
+        if let Some(node) = self.process_filter {
+            let mut own = Bits::new(rows.len());
+            for r in self.processes.involving(node) {
+                own.insert(r as usize);
+            }
+            rows.and(&own);
+        }
+        let rules: Vec<Candidates> = self
+            .noise
+            .in_effect()
+            .map(|(_, q)| q.candidates(&self.index))
+            .collect();
+        let mut unsure = query.maybe.clone();
+        for rule in &rules {
+            unsure.or(&rule.maybe);
+        }
+        unsure.and(&rows);
+        FilterPlan {
+            to_read: unsure.count(),
+            rows,
+            query,
+            rules,
+            source: self.source_filter,
+            start: self.time_range_start.map(|t| self.zone.to_utc(t)),
+            end: self.time_range_end.map(|t| self.zone.to_utc(t)),
+        }
+    }
+
+    /// decide row `i` of `plan.rows`; `ev` is read from the store the first time it is needed
+    fn verdict(&self, plan: &FilterPlan, i: usize, ev: &mut Option<TimelineEvent>) -> Verdict {
+        if plan
+            .source
+            .is_some_and(|s| self.events.source_of(i) != Some(s))
+        {
+            return Verdict::Filtered;
+        }
+        if !time_in_range(self.events.time(i), plan.start, plan.end) {
+            return Verdict::Filtered;
+        }
+        let read = |ev: &mut Option<TimelineEvent>| {
+            if ev.is_none() {
+                *ev = self.events.get(i);
+            }
+            ev.is_some()
+        };
+        if plan.query.maybe.contains(i)
+            && !(read(ev) && ev.as_ref().is_some_and(|e| self.query.matches(e)))
+        {
+            return Verdict::Filtered;
+        }
+        for ((rule, q), found) in self.noise.in_effect().zip(&plan.rules) {
+            let hides = found.sure.contains(i)
+                || (found.maybe.contains(i)
+                    && read(ev)
+                    && ev.as_ref().is_some_and(|e| q.matches(e)));
+            if hides {
+                return Verdict::Hidden(rule);
+            }
+        }
+        Verdict::Shown
+    }
+
+    /// selected event (by filtered list index, or the tree line in the process tree), read
+    /// from disk
+    pub fn selected_event(&self) -> Option<TimelineEvent> {
+        if self.mode == Mode::ProcessTree {
+            return self.tree_selected_row().and_then(|r| self.events.get(r));
+        }
         self.list_state
             .selected()
             .and_then(|i| self.filtered_indices.get(i))
@@ -176,6 +764,51 @@ impl App {
         self.detail_scroll = 0;
     }
 
+    /// open the rejected-rows panel
+    pub fn start_rejected_view(&mut self) {
+        if self.load_report.rejected.is_empty() {
+            self.set_flash("No rejected rows.".to_string());
+            return;
+        }
+        self.mode = Mode::Rejected;
+        if self.rejected_list_state.selected().is_none() {
+            self.rejected_list_state.select(Some(0));
+        }
+        self.detail_scroll = 0;
+    }
+
+    pub fn close_rejected_view(&mut self) {
+        self.mode = Mode::Normal;
+        self.detail_scroll = 0;
+    }
+
+    pub fn rejected_next(&mut self) {
+        let len = self.load_report.rejected.len();
+        let i = self
+            .rejected_list_state
+            .selected()
+            .map(|i| (i + 1).min(len.saturating_sub(1)))
+            .unwrap_or(0);
+        self.rejected_list_state
+            .select(if len == 0 { None } else { Some(i) });
+        self.detail_scroll = 0;
+    }
+
+    pub fn rejected_previous(&mut self) {
+        let i = self
+            .rejected_list_state
+            .selected()
+            .map(|i| i.saturating_sub(1))
+            .unwrap_or(0);
+        self.rejected_list_state
+            .select(if self.load_report.rejected.is_empty() {
+                None
+            } else {
+                Some(i)
+            });
+        self.detail_scroll = 0;
+    }
+
     pub fn scroll_detail_down(&mut self, amount: u16) {
         self.detail_scroll = self.detail_scroll.saturating_add(amount);
     }
@@ -184,6 +817,755 @@ impl App {
         self.detail_scroll = self.detail_scroll.saturating_sub(amount);
     }
 
+    /// put a field cursor in the detail panel of the selected event
+    pub fn start_detail_cursor(&mut self) {
+        if self.selected_event().is_none() {
+            self.set_flash("Select an event first.".to_string());
+            return;
+        }
+        self.mode = Mode::Detail;
+        self.detail_cursor = 0;
+        self.flash = None;
+    }
+
+    pub fn close_detail_cursor(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    pub fn detail_cursor_next(&mut self) {
+        let fields = self
+            .selected_event()
+            .map_or(0, |ev| ev.detail_lines().len());
+        self.detail_cursor = (self.detail_cursor + 1).min(fields.saturating_sub(1));
+    }
+
+    pub fn detail_cursor_previous(&mut self) {
+        self.detail_cursor = self.detail_cursor.saturating_sub(1);
+    }
+
+    /// label and value of the field under the detail cursor
+    pub fn detail_field(&self) -> Option<(String, String)> {
+        self.selected_event()
+            .and_then(|ev| ev.detail_lines().into_iter().nth(self.detail_cursor))
+    }
+
+    /// narrow the search to events with the field under the cursor equal to its value
+    pub fn filter_to_detail_value(&mut self) {
+        let Some((label, value)) = self.detail_field() else {
+            return;
+        };
+        if self.search.starts_with("re:") {
+            self.set_flash("Clear the regex search to add a field filter ([ x ]).".to_string());
+            return;
+        }
+        let Some(term) = query::field_term(&label, &value) else {
+            self.set_flash(format!("\"{}\" cannot be named in a search.", label));
+            return;
+        };
+        self.narrow_search(&term);
+    }
+
+    /// AND `terms` to the current search
+    fn narrow_search(&mut self, terms: &str) {
+        let search = match self.search.trim() {
+            "" => terms.to_string(),
+            // OR binds loosest: keep the current search whole
+            s if s.split_whitespace().any(|w| w == "OR") => format!("({}) {}", s, terms),
+            s => format!("{} {}", s, terms),
+        };
+        self.search_for(search);
+    }
+
+    /// hide events with the field under the cursor equal to its value (an exclusion rule)
+    pub fn exclude_detail_value(&mut self) {
+        let Some((field, value)) = self.detail_field() else {
+            return;
+        };
+        self.mode = Mode::Normal;
+        self.add_exclusion(Rule::Field { field, value });
+    }
+
+    /// search every field for the value under the cursor (replacing the search)
+    pub fn search_detail_value(&mut self) {
+        if let Some((_, value)) = self.detail_field() {
+            self.search_for(query::phrase(&value));
+        }
+    }
+
+    /// apply `search` as if typed; the selected event stays selected when it still matches
+    fn search_for(&mut self, search: String) {
+        let row = self
+            .list_state
+            .selected()
+            .and_then(|i| self.filtered_indices.get(i))
+            .copied();
+        self.mode = Mode::SearchInput;
+        self.search_regex = false;
+        self.search_input = search;
+        self.commit_search();
+        if let Some(pos) = row.and_then(|r| self.filtered_indices.binary_search(&r).ok()) {
+            self.list_state.select(Some(pos));
+        }
+    }
+
+    /// show only events that hit an indicator, or everything again
+    pub fn toggle_ioc_only(&mut self) {
+        if self.ioc.is_empty() {
+            self.set_flash("No IOC list loaded (start with --ioc FILE)".to_string());
+            return;
+        }
+        self.ioc_only = !self.ioc_only;
+        self.apply_filters();
+        let msg = if self.ioc_only {
+            format!("IOC hits only: {} events", self.filtered_indices.len())
+        } else {
+            "Showing all events (IOC hits flagged)".to_string()
+        };
+        self.set_flash(msg);
+    }
+
+    /// show everything the selected event's process did: every event it initiated, plus the
+    /// event that created it. the current filters are kept as a breadcrumb to go back to.
+    pub fn pivot_to_process(&mut self) {
+        let Some(ev) = self.selected_event() else {
+            self.set_flash("Select an event first.".to_string());
+            return;
+        };
+        let Some(node) = self.processes.process_of(&ev) else {
+            self.set_flash("The selected event names no process (no process id).".to_string());
+            return;
+        };
+        if self.process_filter == Some(node) {
+            return;
+        }
+        let label = match self.process_filter {
+            Some(p) => self.processes.get(p).label(),
+            None if !self.search.is_empty() => format!("\"{}\"", self.search),
+            None if self.has_filters() => "filtered".to_string(),
+            None => "all events".to_string(),
+        };
+        let row = self
+            .list_state
+            .selected()
+            .and_then(|i| self.filtered_indices.get(i))
+            .copied();
+        let query = self.set_query(Query::default());
+        self.breadcrumbs.push(Breadcrumb {
+            label,
+            search: std::mem::take(&mut self.search),
+            query,
+            action_type_filter: std::mem::take(&mut self.action_type_filter),
+            source_filter: self.source_filter.take(),
+            time_range_start: self.time_range_start.take(),
+            time_range_end: self.time_range_end.take(),
+            ioc_only: std::mem::take(&mut self.ioc_only),
+            detection_filter: self.detection_filter.take(),
+            process_filter: self.process_filter.replace(node),
+            row,
+        });
+        self.apply_filters();
+        // stay on the event pivoted from
+        if let Some(row) = row {
+            if let Ok(pos) = self.filtered_indices.binary_search(&row) {
+                self.list_state.select(Some(pos));
+            }
+        }
+        self.set_flash(format!(
+            "Everything {} did: {} events ([ Backspace ] back)",
+            self.processes.get(node).label(),
+            self.filtered_indices.len()
+        ));
+    }
+
+    /// return to the view before the last pivot
+    pub fn pivot_back(&mut self) {
+        let Some(crumb) = self.breadcrumbs.pop() else {
+            return;
+        };
+        self.search = crumb.search;
+        self.set_query(crumb.query);
+        self.action_type_filter = crumb.action_type_filter;
+        self.source_filter = crumb.source_filter;
+        self.time_range_start = crumb.time_range_start;
+        self.time_range_end = crumb.time_range_end;
+        self.ioc_only = crumb.ioc_only;
+        self.detection_filter = crumb.detection_filter;
+        self.process_filter = crumb.process_filter;
+        self.apply_filters();
+        if let Some(row) = crumb.row {
+            if let Ok(pos) = self.filtered_indices.binary_search(&row) {
+                self.list_state.select(Some(pos));
+            }
+        }
+        self.set_flash(format!(
+            "Back to {} ({} events)",
+            crumb.label,
+            self.filtered_indices.len()
+        ));
+    }
+
+    /// true if any search or filter narrows the list (exclusions aside)
+    fn has_filters(&self) -> bool {
+        !self.search.is_empty()
+            || !self.action_type_filter.is_empty()
+            || self.source_filter.is_some()
+            || self.time_range_start.is_some()
+            || self.time_range_end.is_some()
+            || self.ioc_only
+            || self.detection_filter.is_some()
+            || self.process_filter.is_some()
+    }
+
+    /// open the process tree around the selected event's process: its ancestors down to it
+    /// are expanded
+    pub fn start_process_tree(&mut self) {
+        let Some(ev) = self.selected_event() else {
+            self.set_flash("Select an event first.".to_string());
+            return;
+        };
+        self.processes.link();
+        let Some(node) = self.processes.process_of(&ev) else {
+            self.set_flash("The selected event names no process (no process id).".to_string());
+            return;
+        };
+        let lineage = self.processes.lineage(node);
+        self.tree_root = lineage.first().copied();
+        self.tree_expanded = lineage.into_iter().collect();
+        self.rebuild_tree_lines();
+        let pos = self
+            .tree_lines
+            .iter()
+            .position(|l| matches!(l, TreeLine::Process { node: n, .. } if *n == node));
+        self.tree_list_state.select(pos.or(Some(0)));
+        self.mode = Mode::ProcessTree;
+        self.detail_scroll = 0;
+    }
+
+    pub fn close_process_tree(&mut self) {
+        self.mode = Mode::Normal;
+        self.detail_scroll = 0;
+    }
+
+    /// flatten the tree under `tree_root`: each expanded process lists its children, then the
+    /// events it initiated
+    fn rebuild_tree_lines(&mut self) {
+        fn walk(app: &App, node: usize, depth: usize, out: &mut Vec<TreeLine>) {
+            out.push(TreeLine::Process { node, depth });
+            if !app.tree_expanded.contains(&node) {
+                return;
+            }
+            for &child in app.processes.children(node) {
+                walk(app, child, depth + 1, out);
+            }
+            out.extend(
+                app.processes
+                    .get(node)
+                    .rows
+                    .iter()
+                    .map(|&row| TreeLine::Activity {
+                        row,
+                        depth: depth + 1,
+                    }),
+            );
+        }
+        let mut lines = Vec::new();
+        if let Some(root) = self.tree_root {
+            walk(self, root, 0, &mut lines);
+        }
+        self.tree_lines = lines;
+    }
+
+    fn selected_tree_line(&self) -> Option<TreeLine> {
+        self.tree_list_state
+            .selected()
+            .and_then(|i| self.tree_lines.get(i))
+            .copied()
+    }
+
+    /// event row behind the selected tree line (a process shows the event that created it)
+    fn tree_selected_row(&self) -> Option<usize> {
+        match self.selected_tree_line()? {
+            TreeLine::Activity { row, .. } => Some(row as usize),
+            TreeLine::Process { node, .. } => {
+                self.processes.get(node).created_row.map(|r| r as usize)
+            }
+        }
+    }
+
+    /// process on the selected tree line
+    pub fn tree_selected_process(&self) -> Option<usize> {
+        match self.selected_tree_line()? {
+            TreeLine::Process { node, .. } => Some(node),
+            TreeLine::Activity { .. } => None,
+        }
+    }
+
+    pub fn tree_next(&mut self) {
+        let i = self
+            .tree_list_state
+            .selected()
+            .map(|i| (i + 1).min(self.tree_lines.len().saturating_sub(1)))
+            .unwrap_or(0);
+        self.tree_list_state.select(Some(i));
+        self.detail_scroll = 0;
+    }
+
+    pub fn tree_previous(&mut self) {
+        let i = self
+            .tree_list_state
+            .selected()
+            .map(|i| i.saturating_sub(1))
+            .unwrap_or(0);
+        self.tree_list_state.select(Some(i));
+        self.detail_scroll = 0;
+    }
+
+    /// expand or collapse the selected process; on an event line, show it in the event list
+    pub fn tree_open(&mut self) {
+        match self.selected_tree_line() {
+            Some(TreeLine::Process { node, .. }) => {
+                if !self.tree_expanded.remove(&node) {
+                    self.tree_expanded.insert(node);
+                }
+                self.rebuild_tree_lines();
+            }
+            Some(TreeLine::Activity { row, .. }) => self.show_event_in_list(row as usize),
+            None => {}
+        }
+    }
+
+    /// collapse the selected process, or move up to the process the line belongs to
+    pub fn tree_collapse(&mut self) {
+        let Some(i) = self.tree_list_state.selected() else {
+            return;
+        };
+        let depth = match self.tree_lines.get(i) {
+            Some(TreeLine::Process { node, .. }) if self.tree_expanded.remove(node) => {
+                self.rebuild_tree_lines();
+                return;
+            }
+            Some(TreeLine::Process { depth, .. } | TreeLine::Activity { depth, .. }) => *depth,
+            None => return,
+        };
+        let parent = self.tree_lines[..i]
+            .iter()
+            .rposition(|l| matches!(l, TreeLine::Process { depth: d, .. } if *d + 1 == depth));
+        if let Some(p) = parent {
+            self.tree_list_state.select(Some(p));
+            self.detail_scroll = 0;
+        }
+    }
+
+    /// leave the tree and select event `row` in the list, if the current filters show it
+    fn show_event_in_list(&mut self, row: usize) {
+        match self.filtered_indices.binary_search(&row) {
+            Ok(pos) => {
+                self.mode = Mode::Normal;
+                self.list_state.select(Some(pos));
+                self.detail_scroll = 0;
+            }
+            Err(_) => self.set_flash(
+                "That event is hidden by the current filters ([ x ] clears them).".to_string(),
+            ),
+        }
+    }
+
+    /// open the histogram with the cursor on the selected event's bar
+    pub fn start_histogram(&mut self) {
+        if self.filtered_indices.is_empty() {
+            self.set_flash("No events to chart.".to_string());
+            return;
+        }
+        self.mode = Mode::Histogram;
+        self.histogram_zoom.clear();
+        self.histogram = None;
+        // placed on the selected event once the histogram is built for the panel's width
+        self.histogram_cursor = usize::MAX;
+    }
+
+    pub fn close_histogram(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    /// build the histogram of the filtered events (times in the display zone) in at most
+    /// `buckets` bars, unless the last one still fits
+    pub fn refresh_histogram(&mut self, buckets: usize) {
+        let key = (self.filtered_indices.len(), buckets);
+        if self.histogram.is_none() || self.histogram_key != key {
+            let mut shown = Bits::new(self.events.len());
+            for &i in &self.filtered_indices {
+                shown.insert(i);
+            }
+            let mut points = Vec::with_capacity(self.filtered_indices.len());
+            for (action_type, rows) in self.index.action_types() {
+                for &r in rows {
+                    let r = r as usize;
+                    if r < shown.len() && shown.contains(r) {
+                        if let Some(t) = self.events.time(r) {
+                            points.push((self.zone.to_local(t), action_type));
+                        }
+                    }
+                }
+            }
+            self.histogram = Some(Histogram::build(&points, buckets));
+            self.histogram_key = key;
+        }
+        let Some(histogram) = &self.histogram else {
+            return;
+        };
+        if self.histogram_cursor == usize::MAX {
+            let at = self
+                .selected_event()
+                .and_then(|ev| ev.event_time_parsed())
+                .and_then(|t| histogram.bucket_of(self.zone.to_local(t)));
+            self.histogram_cursor = at.unwrap_or(0);
+        }
+        self.histogram_cursor = self.histogram_cursor.min(histogram.len().saturating_sub(1));
+    }
+
+    pub fn histogram_next(&mut self) {
+        let len = self.histogram.as_ref().map_or(0, Histogram::len);
+        if self.histogram_cursor + 1 < len {
+            self.histogram_cursor += 1;
+            self.select_histogram_bar();
+        }
+    }
+
+    pub fn histogram_previous(&mut self) {
+        if self.histogram_cursor > 0 && self.histogram_cursor != usize::MAX {
+            self.histogram_cursor -= 1;
+            self.select_histogram_bar();
+        }
+    }
+
+    /// select the first event of the bar under the cursor in the list
+    fn select_histogram_bar(&mut self) {
+        let Some(histogram) = &self.histogram else {
+            return;
+        };
+        let (start, end) = histogram.range(self.histogram_cursor);
+        if let Some(pos) = self.first_shown_between(start, end) {
+            self.list_state.select(Some(pos));
+            self.detail_scroll = 0;
+        }
+    }
+
+    /// list position of the first shown event in [start, end) (display zone); a single
+    /// source keeps its file order, so the list is not always in time order
+    fn first_shown_between(&self, start: NaiveDateTime, end: NaiveDateTime) -> Option<usize> {
+        self.filtered_indices.iter().position(|&r| {
+            self.events
+                .time(r)
+                .map(|t| self.zone.to_local(t))
+                .is_some_and(|t| t >= start && t < end)
+        })
+    }
+
+    /// set the time range to the bar under the cursor (the histogram then spans just that bar)
+    pub fn zoom_histogram(&mut self) {
+        let Some(histogram) = &self.histogram else {
+            return;
+        };
+        if histogram.total(self.histogram_cursor) == 0 {
+            self.set_flash("No events in this bar.".to_string());
+            return;
+        }
+        let (start, end) = histogram.range(self.histogram_cursor);
+        self.histogram_zoom
+            .push((self.time_range_start, self.time_range_end));
+        self.time_range_start = Some(start);
+        // the range end is inclusive: the last instant before the next bar (times carry
+        // nanoseconds, so a coarser step would drop events late in the bar)
+        self.time_range_end = Some(end - chrono::TimeDelta::nanoseconds(1));
+        self.apply_filters();
+        self.histogram_cursor = 0;
+        let flash = self.time_range_summary();
+        self.set_flash(flash);
+    }
+
+    /// back to the time range before the last zoom
+    pub fn unzoom_histogram(&mut self) {
+        let Some((start, end)) = self.histogram_zoom.pop() else {
+            self.set_flash("Not zoomed in ([ Enter ] zooms into a bar)".to_string());
+            return;
+        };
+        let zoomed = (self.time_range_start, self.time_range_end);
+        self.time_range_start = start;
+        self.time_range_end = end;
+        self.apply_filters();
+        // the cursor goes back to the bar zoomed into (the bar of its first event)
+        if let (Some(from), Some(to)) = zoomed {
+            let to = to + chrono::TimeDelta::nanoseconds(1);
+            if let Some(pos) = self.first_shown_between(from, to) {
+                self.list_state.select(Some(pos));
+            }
+        }
+        self.histogram_cursor = usize::MAX;
+        let flash = self.time_range_summary();
+        self.set_flash(flash);
+    }
+
+    /// open the stacking view, asking for the fields to count
+    pub fn start_stacking(&mut self) {
+        self.mode = Mode::Stacking;
+        // counts are for the filters at the time; a new view starts over
+        self.stack = None;
+        self.start_stack_input();
+    }
+
+    pub fn close_stacking(&mut self) {
+        self.stack_input = None;
+        self.stack_error = None;
+        self.mode = Mode::Normal;
+    }
+
+    /// type the fields to count (prefilled with the last ones)
+    pub fn start_stack_input(&mut self) {
+        self.stack_input = Some(self.stack_fields.clone());
+        self.stack_error = None;
+    }
+
+    pub fn push_stack_char(&mut self, c: char) {
+        if let Some(input) = self.stack_input.as_mut() {
+            input.push(c);
+            self.stack_error = None;
+        }
+    }
+
+    pub fn pop_stack_char(&mut self) {
+        if let Some(input) = self.stack_input.as_mut() {
+            input.pop();
+            self.stack_error = None;
+        }
+    }
+
+    /// stop typing; back to the list when there is one
+    pub fn cancel_stack_input(&mut self) {
+        self.stack_input = None;
+        self.stack_error = None;
+        if self.stack.is_none() {
+            self.mode = Mode::Normal;
+        }
+    }
+
+    /// count the typed fields' value combinations across the filtered events; unknown
+    /// fields stay in the input with the error shown
+    pub fn commit_stack_input(&mut self) {
+        let Some(input) = self.stack_input.clone() else {
+            return;
+        };
+        let extra = self.events.unmapped_columns();
+        let mut stack = match Stack::new(&input, &extra) {
+            Ok(stack) => stack,
+            Err(e) => {
+                self.stack_error = Some(e.to_string());
+                return;
+            }
+        };
+        if self.filtered_indices.len() > self.events.len() / SCAN_FRACTION {
+            let mut shown = Bits::new(self.events.len());
+            for &i in &self.filtered_indices {
+                shown.insert(i);
+            }
+            for (i, ev) in self.events.iter() {
+                if i < shown.len() && shown.contains(i) {
+                    stack.add(&ev);
+                }
+            }
+        } else {
+            for &i in &self.filtered_indices {
+                if let Some(ev) = self.events.get(i) {
+                    stack.add(&ev);
+                }
+            }
+        }
+        stack.sort(self.stack.as_ref().is_none_or(|s| s.ascending));
+        self.set_flash(format!(
+            "{} combinations of {} in {} events",
+            stack.len(),
+            stack.labels().join(" + "),
+            stack.total
+        ));
+        self.stack_fields = input.trim().to_string();
+        self.stack_list_state
+            .select((!stack.is_empty()).then_some(0));
+        self.stack = Some(stack);
+        self.stack_input = None;
+    }
+
+    /// rarest first or most common first
+    pub fn toggle_stack_order(&mut self) {
+        if let Some(stack) = self.stack.as_mut() {
+            stack.sort(!stack.ascending);
+            self.stack_list_state
+                .select((!stack.is_empty()).then_some(0));
+        }
+    }
+
+    pub fn stack_next(&mut self) {
+        let len = self.stack.as_ref().map_or(0, Stack::len);
+        if len > 0 {
+            let i = self
+                .stack_list_state
+                .selected()
+                .map_or(0, |i| (i + 1).min(len - 1));
+            self.stack_list_state.select(Some(i));
+        }
+    }
+
+    pub fn stack_previous(&mut self) {
+        if let Some(i) = self.stack_list_state.selected() {
+            self.stack_list_state.select(Some(i.saturating_sub(1)));
+        }
+    }
+
+    /// narrow the search to the selected combination
+    pub fn filter_to_stack_row(&mut self) {
+        let Some(stack) = &self.stack else {
+            return;
+        };
+        let Some(row) = self
+            .stack_list_state
+            .selected()
+            .and_then(|i| stack.rows.get(i))
+        else {
+            return;
+        };
+        if self.search.starts_with("re:") {
+            self.set_flash(
+                "Clear the regex search to filter to a combination ([ x ]).".to_string(),
+            );
+            return;
+        }
+        let terms: Option<Vec<String>> = stack
+            .labels()
+            .iter()
+            .zip(&row.values)
+            .map(|(label, value)| query::field_term(label, value))
+            .collect();
+        let Some(terms) = terms else {
+            self.set_flash("A field of this stack cannot be named in a search.".to_string());
+            return;
+        };
+        self.narrow_search(&terms.join(" "));
+    }
+
+    /// open the detections panel (sigma rules and their hits)
+    pub fn start_detections(&mut self) {
+        if self.sigma.is_empty() {
+            self.set_flash("No sigma rules loaded (start with --sigma PATH)".to_string());
+            return;
+        }
+        self.mode = Mode::Detections;
+        let sel = self
+            .detection_filter
+            .or(self.detections_list_state.selected())
+            .unwrap_or(0);
+        self.detections_list_state.select(Some(sel));
+    }
+
+    pub fn close_detections(&mut self) {
+        self.mode = Mode::Normal;
+    }
+
+    pub fn detection_next(&mut self) {
+        let i = self
+            .detections_list_state
+            .selected()
+            .map(|i| (i + 1).min(self.sigma.len().saturating_sub(1)))
+            .unwrap_or(0);
+        self.detections_list_state.select(Some(i));
+    }
+
+    pub fn detection_previous(&mut self) {
+        let i = self
+            .detections_list_state
+            .selected()
+            .map(|i| i.saturating_sub(1))
+            .unwrap_or(0);
+        self.detections_list_state.select(Some(i));
+    }
+
+    /// show only the hits of the selected rule and exit
+    pub fn commit_detection_filter(&mut self) {
+        let Some(i) = self
+            .detections_list_state
+            .selected()
+            .filter(|&i| i < self.sigma.len())
+        else {
+            return;
+        };
+        let rule = &self.sigma.rules[i];
+        if let Some(err) = &rule.error {
+            self.set_flash(format!("{} cannot run: {}", rule.title, err));
+            return;
+        }
+        let title = rule.title.clone();
+        self.detection_filter = Some(i);
+        self.apply_filters();
+        self.set_flash(format!(
+            "Detection: {} ({} events, [ x ] to clear)",
+            title,
+            self.filtered_indices.len()
+        ));
+        self.mode = Mode::Normal;
+    }
+
+    /// scroll the detail panel to the next row with a search match (wrapping around)
+    pub fn next_detail_match(&mut self) {
+        let rows = &self.detail_match_rows;
+        let pos = rows
+            .iter()
+            .position(|&r| r > self.detail_scroll)
+            .unwrap_or(0);
+        self.jump_to_detail_match(pos);
+    }
+
+    /// scroll the detail panel to the previous row with a search match (wrapping around)
+    pub fn previous_detail_match(&mut self) {
+        let rows = &self.detail_match_rows;
+        let pos = rows
+            .iter()
+            .rposition(|&r| r < self.detail_scroll)
+            .unwrap_or(rows.len().saturating_sub(1));
+        self.jump_to_detail_match(pos);
+    }
+
+    fn jump_to_detail_match(&mut self, pos: usize) {
+        let Some(&row) = self.detail_match_rows.get(pos) else {
+            let msg = if self.query.is_empty() {
+                "No search to jump to ([ / ] to search)"
+            } else {
+                "No matches in this event"
+            };
+            self.set_flash(msg.to_string());
+            return;
+        };
+        self.detail_scroll = row;
+        self.set_flash(format!(
+            "Match {} of {}",
+            pos + 1,
+            self.detail_match_rows.len()
+        ));
+    }
+
+    /// switch between utc and the configured zone (local time when `--tz` was utc); a time
+    /// range keeps covering the same instants
+    pub fn toggle_zone(&mut self) {
+        let next = match (self.zone, self.configured_zone) {
+            (DisplayZone::Utc, DisplayZone::Utc) => DisplayZone::Local,
+            (DisplayZone::Utc, configured) => configured,
+            _ => DisplayZone::Utc,
+        };
+        let convert = |t: NaiveDateTime| next.to_local(self.zone.to_utc(t));
+        self.time_range_start = self.time_range_start.map(convert);
+        self.time_range_end = self.time_range_end.map(convert);
+        self.zone = next;
+        self.unique_dates = self.summary.dates(&self.zone);
+        self.histogram = None;
+        self.set_flash(format!("Times shown in {}", self.zone));
+    }
+
     pub fn set_flash(&mut self, msg: String) {
         self.flash = Some(msg);
     }
@@ -196,21 +1578,77 @@ impl App {
         self.error = None;
     }
 
+    /// filter by `query` from now on (with its highlighter); returns the previous query
+    fn set_query(&mut self, query: Query) -> Query {
+        self.highlighter = query.highlighter();
+        std::mem::replace(&mut self.query, query)
+    }
+
     /// enter search mode; pre-fill with current search
     pub fn start_search(&mut self) {
         self.mode = Mode::SearchInput;
-        self.search_input = self.search.clone();
+        match self.search.strip_prefix("re:") {
+            Some(pattern) => {
+                self.search_regex = true;
+                self.search_input = pattern.to_string();
+            }
+            None => {
+                self.search_regex = false;
+                self.search_input = self.search.clone();
+            }
+        }
+    }
+
+    /// switch the search input between the query language and one regular expression
+    pub fn toggle_search_regex(&mut self) {
+        self.search_regex = !self.search_regex;
+        self.search_error = None;
+        self.search_edited = Some(Instant::now());
+    }
+
+    /// the search input as stored in `search` (`re:` in front of a regex) and its parse
+    fn parse_search_input(&self) -> (String, crate::error::Result<Query>) {
+        let mut input = self.search_input.trim().to_string();
+        let extra = self.events.unmapped_columns();
+        let parsed = if self.search_regex {
+            Query::regex(&input, &extra)
+        } else {
+            Query::parse(&input, &extra)
+        };
+        if self.search_regex && !input.is_empty() {
+            input.insert_str(0, "re:");
+        }
+        (input, parsed)
     }
 
-    /// apply search input and exit search mode
+    /// apply search input and exit search mode; a query that does not parse stays in the
+    /// input with the error shown
     pub fn commit_search(&mut self) {
-        self.search = std::mem::take(&mut self.search_input).trim().to_string();
-        self.apply_filters();
+        let (input, parsed) = self.parse_search_input();
+        match parsed {
+            Ok(query) => {
+                self.set_query(query);
+            }
+            Err(e) => {
+                self.search_error = Some(e.to_string());
+                return;
+            }
+        }
+        self.search_error = None;
+        self.search_input.clear();
+        self.search_edited = None;
+        // a finished preview of the same input already is the result
+        let previewed = self.search_pass.is_none() && self.search_preview.as_ref() == Some(&input);
+        self.search_preview = None;
+        self.search = input;
+        if !previewed {
+            self.apply_filters();
+        }
         self.mode = Mode::Normal;
         let n = self.filtered_indices.len();
         let flash = if n == 0 && !self.search.is_empty() {
             format!("No results for \"{}\"", self.search)
-        } else if n == 0 && self.action_type_filter.is_some() {
+        } else if n == 0 && !self.action_type_filter.is_empty() {
             "No events match the current filter.".to_string()
         } else {
             format!("Search: \"{}\" ({} events)", self.search, n)
@@ -218,18 +1656,115 @@ impl App {
         self.set_flash(flash);
     }
 
-    /// cancel search mode, keep current search
+    /// cancel search mode, keep current search (undoing any preview)
     pub fn cancel_search(&mut self) {
         self.search_input.clear();
+        self.search_error = None;
+        self.search_edited = None;
         self.mode = Mode::Normal;
+        if self.search_preview.take().is_some() {
+            let extra = self.events.unmapped_columns();
+            self.set_query(Query::parse(&self.search, &extra).unwrap_or_default());
+            self.apply_filters();
+        }
     }
 
     pub fn push_search_char(&mut self, c: char) {
         self.search_input.push(c);
+        self.search_error = None;
+        self.search_edited = Some(Instant::now());
     }
 
     pub fn pop_search_char(&mut self) {
         self.search_input.pop();
+        self.search_error = None;
+        self.search_edited = Some(Instant::now());
+    }
+
+    /// true while a typed search is waiting to be previewed or still filtering
+    pub fn search_busy(&self) -> bool {
+        self.search_edited.is_some() || self.search_pass.is_some()
+    }
+
+    /// search text behind the current list: the preview while typing, else the applied search
+    pub fn shown_search(&self) -> &str {
+        self.search_preview.as_deref().unwrap_or(&self.search)
+    }
+
+    /// search-as-you-type: once typing pauses, start filtering by the input; then filter a
+    /// slice of rows per call so keys stay responsive. a new keystroke drops an unfinished pass.
+    pub fn poll_search(&mut self) {
+        if self
+            .search_edited
+            .is_some_and(|at| at.elapsed() >= SEARCH_DEBOUNCE)
+        {
+            self.search_edited = None;
+            self.start_search_pass();
+        }
+        if self.search_edited.is_some() {
+            return;
+        }
+        let Some(mut pass) = self.search_pass.take() else {
+            return;
+        };
+        let started = Instant::now();
+        let end = pass.plan.rows.len();
+        while pass.next < end {
+            let i = pass.next;
+            pass.next += 1;
+            if pass.plan.rows.contains(i) {
+                match self.verdict(&pass.plan, i, &mut None) {
+                    Verdict::Shown => self.filtered_indices.push(i),
+                    Verdict::Hidden(rule) => pass.hidden[rule] += 1,
+                    Verdict::Filtered => {}
+                }
+            }
+            if i % 1024 == 0 && started.elapsed() >= SEARCH_POLL_BUDGET {
+                break;
+            }
+        }
+        if self.list_state.selected().is_none() && !self.filtered_indices.is_empty() {
+            self.list_state.select(Some(0));
+        }
+        if pass.next < end {
+            self.search_pass = Some(pass);
+            return;
+        }
+        self.filtered_indices.extend(pass.arrived);
+        for (total, n) in self.noise_hidden.iter_mut().zip(pass.hidden) {
+            *total += n;
+        }
+    }
+
+    /// restart the list as a preview of the search input (a query that does not parse keeps
+    /// the list and shows the error)
+    fn start_search_pass(&mut self) {
+        let (input, parsed) = self.parse_search_input();
+        if self.search_preview.as_ref() == Some(&input)
+            || (self.search_preview.is_none() && input == self.search)
+        {
+            return;
+        }
+        let query = match parsed {
+            Ok(query) => query,
+            Err(e) => {
+                self.search_error = Some(e.to_string());
+                return;
+            }
+        };
+        self.set_query(query);
+        self.search_preview = Some(input);
+        let plan = self.filter_plan();
+        self.search_pass = Some(SearchPass {
+            plan,
+            next: 0,
+            hidden: vec![0; self.noise.rules.len()],
+            arrived: Vec::new(),
+        });
+        self.filtered_indices.clear();
+        self.noise_hidden = vec![0; self.noise.rules.len()];
+        self.list_state.select(None);
+        self.detail_scroll = 0;
     }
 
     /// enter time range filter mode; show preset picker first
@@ -260,7 +1795,7 @@ impl App {
             if self.unique_dates.len() == 1 {
                 let d = self.unique_dates[0];
                 self.time_filter_sub = TimeFilterSub::CustomRangeStartHour(d);
-                self.date_picker_hours = unique_hours_for_date(&self.events, d);
+                self.date_picker_hours = self.summary.hours_for_date(&self.zone, d);
                 self.date_picker_list_state
                     .select(if self.date_picker_hours.is_empty() {
                         None
@@ -269,8 +1804,7 @@ impl App {
                     });
             } else {
                 self.time_filter_sub = TimeFilterSub::CustomRangeStart;
-                self.date_picker_list_state
-                    .select(Some(0.min(self.unique_dates.len().saturating_sub(1))));
+                self.date_picker_list_state.select(Some(0));
             }
             return;
         }
@@ -290,7 +1824,7 @@ impl App {
             }
             return;
         }
-        let now = now_for_relative();
+        let now = self.zone.now();
         let label = TIME_PRESETS[idx].to_lowercase();
         if let Some((start, end)) = parse_relative_range(&label, now) {
             self.time_range_start = start;
@@ -385,7 +1919,7 @@ impl App {
         };
         let start_date = dates[idx];
         self.time_filter_sub = TimeFilterSub::CustomRangeStartHour(start_date);
-        self.date_picker_hours = unique_hours_for_date(&self.events, start_date);
+        self.date_picker_hours = self.summary.hours_for_date(&self.zone, start_date);
         self.date_picker_list_state
             .select(if self.date_picker_hours.is_empty() {
                 None
@@ -448,12 +1982,13 @@ impl App {
         let end_date = dates[idx];
         let start_date = start_dt.date();
         self.date_picker_hours = if end_date == start_date {
-            unique_hours_for_date(&self.events, end_date)
+            self.summary
+                .hours_for_date(&self.zone, end_date)
                 .into_iter()
                 .filter(|&h| h >= start_dt.hour())
                 .collect()
         } else {
-            unique_hours_for_date(&self.events, end_date)
+            self.summary.hours_for_date(&self.zone, end_date)
         };
         self.time_filter_sub = TimeFilterSub::CustomRangeEndHour(start_dt, end_date);
         self.date_picker_list_state
@@ -496,8 +2031,7 @@ impl App {
     pub fn commit_time_filter(&mut self) {
         let raw = std::mem::take(&mut self.time_input);
         let s = raw.trim();
-        let s_lower = s.to_lowercase();
-        if s_lower == "clear" || s.is_empty() {
+        if s.eq_ignore_ascii_case("clear") || s.is_empty() {
             self.time_range_start = None;
             self.time_range_end = None;
             self.apply_filters();
@@ -505,104 +2039,74 @@ impl App {
             self.set_flash("Time range cleared".to_string());
             return;
         }
-        // try relative first (today, yesterday, last 7 days, etc.)
-        let now = now_for_relative();
-        if let Some((start, end)) = parse_relative_range(&s_lower, now) {
-            self.time_range_start = start;
-            self.time_range_end = end;
-            self.apply_filters();
-            self.mode = Mode::Normal;
-            self.time_filter_sub = TimeFilterSub::Picker;
-            let flash = match (start, end) {
-                (Some(s), Some(e)) => format!(
-                    "{} to {} ({} events)",
-                    s.format("%Y-%m-%d %H:%M"),
-                    e.format("%Y-%m-%d %H:%M"),
-                    self.filtered_indices.len()
-                ),
-                _ => format!("{} events", self.filtered_indices.len()),
-            };
-            self.set_flash(flash);
+        let Some((start, end)) = self.parse_time_input(s) else {
+            self.time_input = raw;
+            self.set_flash(
+                "Invalid time. Try: today, last 7 days, after <time>, <time> to <time>, clear"
+                    .to_string(),
+            );
             return;
+        };
+        self.time_range_start = start;
+        self.time_range_end = end;
+        self.apply_filters();
+        self.mode = Mode::Normal;
+        self.time_filter_sub = TimeFilterSub::Picker;
+        let flash = self.time_range_summary();
+        self.set_flash(flash);
+    }
+
+    /// range for time prompt text (also used by saved filters): relative ranges first, then
+    /// "after/from <t>", "<t> to <t>", "before <t>", "<t>..<t>" or a single start time
+    fn parse_time_input(
+        &self,
+        raw: &str,
+    ) -> Option<(Option<NaiveDateTime>, Option<NaiveDateTime>)> {
+        let s = raw.trim();
+        let s_lower = s.to_lowercase();
+        if let Some(range) = parse_relative_range(&s_lower, self.zone.now()) {
+            return Some(range);
         }
         if let Some(rest) = s_lower
             .strip_prefix("after ")
             .or_else(|| s_lower.strip_prefix("from "))
         {
-            if let Some(t) = parse_time(rest) {
-                self.time_range_start = Some(t);
-                self.time_range_end = None;
-                self.apply_filters();
-                self.mode = Mode::Normal;
-                self.set_flash(format!(
-                    "Events after {} ({} events)",
-                    t.format("%Y-%m-%d %H:%M"),
-                    self.filtered_indices.len()
-                ));
-                return;
+            if let Some(t) = self.zone.parse_input(rest) {
+                return Some((Some(t), None));
             }
         }
         if let Some((a, b)) = s_lower.split_once(" to ") {
-            if let (Some(t1), Some(t2)) = (parse_time(a), parse_time(b.trim())) {
-                self.time_range_start = Some(t1);
-                self.time_range_end = Some(t2);
-                self.apply_filters();
-                self.mode = Mode::Normal;
-                self.set_flash(format!(
-                    "{} to {} ({} events)",
-                    t1.format("%Y-%m-%d %H:%M"),
-                    t2.format("%Y-%m-%d %H:%M"),
-                    self.filtered_indices.len()
-                ));
-                return;
+            if let (Some(t1), Some(t2)) =
+                (self.zone.parse_input(a), self.zone.parse_input(b.trim()))
+            {
+                return Some((Some(t1), Some(t2)));
             }
-        }
-        if let Some(rest) = s_lower.strip_prefix("before ") {
-            if let Some(t) = parse_time(rest) {
-                self.time_range_start = None;
-                self.time_range_end = Some(t);
-                self.apply_filters();
-                self.mode = Mode::Normal;
-                self.set_flash(format!(
-                    "Events before {} ({} events)",
-                    t.format("%Y-%m-%d %H:%M"),
-                    self.filtered_indices.len()
-                ));
-                return;
+        }
+        if let Some(rest) = s_lower.strip_prefix("before ") {
+            if let Some(t) = self.zone.parse_input(rest) {
+                return Some((None, Some(t)));
             }
         }
-        if let Some((a, b)) = raw.trim().split_once("..") {
-            if let (Some(t1), Some(t2)) = (parse_time(a), parse_time(b.trim())) {
-                self.time_range_start = Some(t1);
-                self.time_range_end = Some(t2);
-                self.apply_filters();
-                self.mode = Mode::Normal;
-                self.set_flash(format!(
-                    "{} to {} ({} events)",
-                    t1.format("%Y-%m-%d %H:%M"),
-                    t2.format("%Y-%m-%d %H:%M"),
-                    self.filtered_indices.len()
-                ));
-                return;
+        if let Some((a, b)) = s.split_once("..") {
+            if let (Some(t1), Some(t2)) =
+                (self.zone.parse_input(a), self.zone.parse_input(b.trim()))
+            {
+                return Some((Some(t1), Some(t2)));
             }
         }
-        if let Some(t) = parse_time(raw.trim()) {
-            self.time_range_start = Some(t);
-            self.time_range_end = None;
-            self.apply_filters();
-            self.mode = Mode::Normal;
-            self.set_flash(format!(
-                "Events from {} ({} events)",
-                t.format("%Y-%m-%d %H:%M"),
-                self.filtered_indices.len()
-            ));
-            return;
+        self.zone.parse_input(s).map(|t| (Some(t), None))
+    }
+
+    /// flash text for the applied time range
+    fn time_range_summary(&self) -> String {
+        let fmt = |t: NaiveDateTime| t.format("%Y-%m-%d %H:%M").to_string();
+        let n = self.filtered_indices.len();
+        match (self.time_range_start, self.time_range_end) {
+            (Some(s), Some(e)) => format!("{} to {} ({} events)", fmt(s), fmt(e), n),
+            (Some(s), None) => format!("Events after {} ({} events)", fmt(s), n),
+            (None, Some(e)) => format!("Events before {} ({} events)", fmt(e), n),
+            (None, None) => format!("{} events", n),
         }
-        self.time_input = raw;
-        self.set_flash(
-            "Invalid time. Try: today, last 7 days, after <time>, <time> to <time>, clear"
-                .to_string(),
-        );
     }
 
     pub fn cancel_time_filter(&mut self) {
@@ -635,7 +2139,7 @@ impl App {
             TimeFilterSub::CustomRangeEnd(start_dt) => {
                 let start_date = start_dt.date();
                 self.time_filter_sub = TimeFilterSub::CustomRangeStartHour(start_date);
-                self.date_picker_hours = unique_hours_for_date(&self.events, start_date);
+                self.date_picker_hours = self.summary.hours_for_date(&self.zone, start_date);
                 let pos = self
                     .date_picker_hours
                     .iter()
@@ -651,8 +2155,7 @@ impl App {
             TimeFilterSub::CustomRangeStartHour(_) => {
                 self.time_filter_sub = TimeFilterSub::CustomRangeStart;
                 self.date_picker_hours.clear();
-                self.date_picker_list_state
-                    .select(Some(0.min(self.unique_dates.len().saturating_sub(1))));
+                self.date_picker_list_state.select(Some(0));
             }
             TimeFilterSub::CustomRangeStart => {
                 self.time_filter_sub = TimeFilterSub::Picker;
@@ -678,59 +2181,507 @@ impl App {
         self.time_input.pop();
     }
 
-    /// enter action-type filter mode; select current filter if any
+    /// enter action-type filter mode with the applied marks and an empty type-ahead
     pub fn start_action_type_filter(&mut self) {
         self.mode = Mode::ActionTypeFilter;
-        if self.action_types.is_empty() {
-            self.action_type_list_state.select(None);
-            return;
+        self.action_type_marks = self.action_type_filter.clone();
+        self.action_type_query.clear();
+        self.action_type_list_state
+            .select(if self.action_types.is_empty() {
+                None
+            } else {
+                Some(0)
+            });
+    }
+
+    /// positions in `action_types` matching the type-ahead (case-insensitive substring)
+    pub fn visible_action_types(&self) -> Vec<usize> {
+        let q = self.action_type_query.to_lowercase();
+        self.action_types
+            .iter()
+            .enumerate()
+            .filter(|(_, (at, _))| q.is_empty() || at.to_lowercase().contains(&q))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// action type under the picker cursor
+    fn selected_action_type(&self) -> Option<String> {
+        let visible = self.visible_action_types();
+        self.action_type_list_state
+            .selected()
+            .and_then(|i| visible.get(i))
+            .map(|&i| self.action_types[i].0.clone())
+    }
+
+    /// include -> exclude -> off for the action type under the cursor
+    pub fn cycle_action_type_mark(&mut self) {
+        if let Some(at) = self.selected_action_type() {
+            self.action_type_marks.cycle(&at);
         }
-        let idx = self
-            .action_type_filter
-            .as_ref()
-            .and_then(|at| self.action_types.iter().position(|x| x == at))
-            .unwrap_or(0);
+    }
+
+    /// drop every include/exclude mark in the picker
+    pub fn clear_action_type_marks(&mut self) {
+        self.action_type_marks = ActionTypeFilter::default();
+    }
+
+    pub fn push_action_type_char(&mut self, c: char) {
+        self.action_type_query.push(c);
+        self.reset_action_type_cursor();
+    }
+
+    pub fn pop_action_type_char(&mut self) {
+        self.action_type_query.pop();
+        self.reset_action_type_cursor();
+    }
+
+    fn reset_action_type_cursor(&mut self) {
+        let any = !self.visible_action_types().is_empty();
         self.action_type_list_state
-            .select(Some(idx.min(self.action_types.len().saturating_sub(1))));
+            .select(if any { Some(0) } else { None });
     }
 
-    /// set filter to selected action type and exit
+    /// apply the picker's marks and exit
     pub fn commit_action_type_filter(&mut self) {
-        let at = self
-            .action_type_list_state
+        self.action_type_filter = std::mem::take(&mut self.action_type_marks);
+        self.action_type_query.clear();
+        self.apply_filters();
+        self.mode = Mode::Normal;
+        if self.action_type_filter.is_empty() {
+            self.set_flash("Filter cleared".to_string());
+        } else {
+            self.set_flash(format!(
+                "Filter: {} ({} events)",
+                self.action_type_filter.describe(),
+                self.filtered_indices.len()
+            ));
+        }
+    }
+
+    /// Esc in the picker: clear the type-ahead first, then leave without applying
+    pub fn cancel_action_type_filter(&mut self) {
+        if !self.action_type_query.is_empty() {
+            self.action_type_query.clear();
+            self.reset_action_type_cursor();
+            return;
+        }
+        self.action_type_marks = ActionTypeFilter::default();
+        self.mode = Mode::Normal;
+    }
+
+    /// enter source filter mode (only when several files are loaded)
+    pub fn start_source_filter(&mut self) {
+        if self.sources.len() < 2 {
+            self.set_flash("Only one source loaded.".to_string());
+            return;
+        }
+        self.mode = Mode::SourceFilter;
+        self.source_list_state
+            .select(Some(self.source_filter.unwrap_or(0)));
+    }
+
+    /// restrict the view to the selected source and exit
+    pub fn commit_source_filter(&mut self) {
+        let picked = self
+            .source_list_state
             .selected()
-            .and_then(|i| self.action_types.get(i))
-            .cloned();
-        if let Some(at) = at {
-            self.action_type_filter = Some(at.clone());
+            .and_then(|i| Some((i, self.events.label(i)?.clone())));
+        if let Some((i, label)) = picked {
+            self.source_filter = Some(i);
             self.apply_filters();
             self.set_flash(format!(
-                "Filter: {} ({} events)",
-                at,
+                "Source: {} ({} events)",
+                label,
                 self.filtered_indices.len()
             ));
         }
         self.mode = Mode::Normal;
     }
 
-    /// clear action type filter and exit (from picker)
-    pub fn clear_action_type_filter(&mut self) {
-        self.action_type_filter = None;
+    /// clear source filter and exit (from picker)
+    pub fn clear_source_filter(&mut self) {
+        self.source_filter = None;
+        self.apply_filters();
+        self.mode = Mode::Normal;
+        self.set_flash("Source filter cleared".to_string());
+    }
+
+    pub fn source_next(&mut self) {
+        let i = self
+            .source_list_state
+            .selected()
+            .map(|i| (i + 1).min(self.sources.len().saturating_sub(1)))
+            .unwrap_or(0);
+        self.source_list_state.select(Some(i));
+    }
+
+    pub fn source_previous(&mut self) {
+        let i = self
+            .source_list_state
+            .selected()
+            .map(|i| i.saturating_sub(1))
+            .unwrap_or(0);
+        self.source_list_state.select(Some(i));
+    }
+
+    /// open the exclusion rules panel
+    pub fn start_exclusions(&mut self) {
+        self.mode = Mode::Exclusions;
+        self.noise_input = None;
+        let sel = self
+            .noise_list_state
+            .selected()
+            .filter(|&i| i < self.noise.rules.len())
+            .or(if self.noise.rules.is_empty() {
+                None
+            } else {
+                Some(0)
+            });
+        self.noise_list_state.select(sel);
+    }
+
+    pub fn close_exclusions(&mut self) {
+        self.noise_input = None;
+        self.mode = Mode::Normal;
+    }
+
+    /// events hidden by exclusion rules in the current view
+    pub fn noise_hidden_total(&self) -> usize {
+        self.noise_hidden.iter().sum()
+    }
+
+    /// add an exclusion rule, re-filter and save the profile
+    pub fn add_exclusion(&mut self, rule: Rule) {
+        let label = rule.label();
+        match self.noise.add(rule, &self.events.unmapped_columns()) {
+            Ok(i) => {
+                self.noise_list_state.select(Some(i));
+                self.noise_changed(format!("Excluding {}", label));
+            }
+            Err(e) => self.set_error(format!("Exclusion not added: {}", e)),
+        }
+    }
+
+    /// exclude the selected event's action type
+    pub fn exclude_selected_action_type(&mut self) {
+        match self.selected_event().and_then(|ev| ev.action_type) {
+            Some(action_type) => self.add_exclusion(Rule::ActionType { action_type }),
+            None => self.set_flash("Selected event has no action type.".to_string()),
+        }
+    }
+
+    /// exclude the selected event's value of `field` (a timeline column name)
+    pub fn exclude_selected_field(&mut self, field: &str) {
+        let value = self.selected_event().and_then(|ev| {
+            ev.fields()
+                .find(|(name, _)| *name == field)
+                .and_then(|(_, v)| v)
+                .map(|v| v.trim_matches('"').trim().to_string())
+                .filter(|v| !v.is_empty())
+        });
+        match value {
+            Some(value) => self.add_exclusion(Rule::Field {
+                field: field.to_string(),
+                value,
+            }),
+            None => self.set_flash(format!("Selected event has no {}.", field)),
+        }
+    }
+
+    /// switch the selected rule on or off
+    pub fn toggle_exclusion(&mut self) {
+        let Some(i) = self.noise_list_state.selected() else {
+            return;
+        };
+        self.noise.toggle(i);
+        if let Some(ex) = self.noise.rules.get(i) {
+            let state = if ex.enabled { "on" } else { "off" };
+            self.noise_changed(format!("Exclusion {}: {}", state, ex.rule.label()));
+        }
+    }
+
+    /// delete the selected rule
+    pub fn remove_exclusion(&mut self) {
+        let Some(i) = self.noise_list_state.selected() else {
+            return;
+        };
+        if let Some(rule) = self.noise.remove(i) {
+            let n = self.noise.rules.len();
+            self.noise_list_state
+                .select(if n == 0 { None } else { Some(i.min(n - 1)) });
+            self.noise_changed(format!("Exclusion removed: {}", rule.label()));
+        }
+    }
+
+    /// start typing a query for a new rule
+    pub fn start_exclusion_input(&mut self) {
+        self.noise_input = Some(String::new());
+    }
+
+    pub fn push_exclusion_char(&mut self, c: char) {
+        if let Some(input) = &mut self.noise_input {
+            input.push(c);
+        }
+    }
+
+    pub fn pop_exclusion_char(&mut self) {
+        if let Some(input) = &mut self.noise_input {
+            input.pop();
+        }
+    }
+
+    pub fn cancel_exclusion_input(&mut self) {
+        self.noise_input = None;
+    }
+
+    /// add the typed query as a rule
+    pub fn commit_exclusion_input(&mut self) {
+        let Some(query) = self.noise_input.take() else {
+            return;
+        };
+        let query = query.trim().to_string();
+        if !query.is_empty() {
+            self.add_exclusion(Rule::Query { query });
+        }
+    }
+
+    pub fn exclusion_next(&mut self) {
+        let i = self
+            .noise_list_state
+            .selected()
+            .map(|i| (i + 1).min(self.noise.rules.len().saturating_sub(1)))
+            .unwrap_or(0);
+        self.noise_list_state.select(Some(i));
+    }
+
+    pub fn exclusion_previous(&mut self) {
+        let i = self
+            .noise_list_state
+            .selected()
+            .map(|i| i.saturating_sub(1))
+            .unwrap_or(0);
+        self.noise_list_state.select(Some(i));
+    }
+
+    /// re-filter and save after the rules changed
+    fn noise_changed(&mut self, msg: String) {
+        self.apply_filters();
+        if let Err(e) = self.noise.save() {
+            self.set_error(format!("Noise profile not saved: {:#}", e));
+        }
+        self.set_flash(format!(
+            "{} ({} events hidden)",
+            msg,
+            self.noise_hidden_total()
+        ));
+    }
+
+    /// open the saved filters panel
+    pub fn start_library(&mut self) {
+        self.mode = Mode::Library;
+        self.library_input = None;
+        let sel = self
+            .library_list_state
+            .selected()
+            .filter(|&i| i < self.library.len())
+            .or(if self.library.is_empty() {
+                None
+            } else {
+                Some(0)
+            });
+        self.library_list_state.select(sel);
+    }
+
+    pub fn close_library(&mut self) {
+        self.library_input = None;
+        self.mode = Mode::Normal;
+    }
+
+    pub fn library_next(&mut self) {
+        let i = self
+            .library_list_state
+            .selected()
+            .map(|i| (i + 1).min(self.library.len().saturating_sub(1)))
+            .unwrap_or(0);
+        self.library_list_state
+            .select((!self.library.is_empty()).then_some(i));
+    }
+
+    pub fn library_previous(&mut self) {
+        let i = self
+            .library_list_state
+            .selected()
+            .map(|i| i.saturating_sub(1))
+            .unwrap_or(0);
+        self.library_list_state
+            .select((!self.library.is_empty()).then_some(i));
+    }
+
+    /// the current search, action types, time range and enabled exclusions as a saved filter;
+    /// times are written in utc so the filter means the same in any zone
+    pub fn current_filter(&self, name: &str) -> SavedFilter {
+        let utc =
+            |t: NaiveDateTime| format!("{}Z", self.zone.to_utc(t).format("%Y-%m-%d %H:%M:%S"));
+        let time = match (self.time_range_start, self.time_range_end) {
+            (Some(s), Some(e)) => Some(format!("{} to {}", utc(s), utc(e))),
+            (Some(s), None) => Some(format!("after {}", utc(s))),
+            (None, Some(e)) => Some(format!("before {}", utc(e))),
+            (None, None) => None,
+        };
+        SavedFilter {
+            name: name.trim().to_string(),
+            search: self.search.clone(),
+            action_types: self.action_type_filter.clone(),
+            time,
+            exclusions: self.noise.enabled_rules(),
+        }
+    }
+
+    /// start typing a name to save the current filters under (prefilled with the selected one)
+    pub fn start_library_input(&mut self) {
+        let name = self
+            .library_list_state
+            .selected()
+            .and_then(|i| self.library.filters.get(i))
+            .map(|f| f.name.clone())
+            .unwrap_or_default();
+        self.library_input = Some(name);
+    }
+
+    pub fn push_library_char(&mut self, c: char) {
+        if let Some(input) = self.library_input.as_mut() {
+            input.push(c);
+        }
+    }
+
+    pub fn pop_library_char(&mut self) {
+        if let Some(input) = self.library_input.as_mut() {
+            input.pop();
+        }
+    }
+
+    pub fn cancel_library_input(&mut self) {
+        self.library_input = None;
+    }
+
+    /// save the current filters under the typed name (replacing a filter of that name)
+    pub fn commit_library_input(&mut self) {
+        let Some(name) = self.library_input.take() else {
+            return;
+        };
+        let filter = self.current_filter(&name);
+        match self.library.put(filter) {
+            Ok(i) => {
+                self.library_list_state.select(Some(i));
+                self.library_changed(format!("Saved filter {}", name.trim()));
+            }
+            Err(e) => self.set_error(format!("Filter not saved: {}", e)),
+        }
+    }
+
+    pub fn remove_saved_filter(&mut self) {
+        let Some(i) = self.library_list_state.selected() else {
+            return;
+        };
+        if let Some(filter) = self.library.remove(i) {
+            let len = self.library.len();
+            self.library_list_state
+                .select((len > 0).then(|| i.min(len - 1)));
+            self.library_changed(format!("Removed filter {}", filter.name));
+        }
+    }
+
+    fn library_changed(&mut self, msg: String) {
+        match self.library.save() {
+            Ok(()) => self.set_flash(msg),
+            Err(e) => self.set_error(format!("Filter library not saved: {:#}", e)),
+        }
+    }
+
+    /// replace the search, action types, time range and exclusions with the selected saved
+    /// filter and exit; the source, process, ioc and detection filters and the pivot trail are
+    /// cleared, as the saved filter does not hold them. nothing changes if part of it does not
+    /// apply to the loaded data
+    pub fn apply_saved_filter(&mut self) {
+        let Some(filter) = self
+            .library_list_state
+            .selected()
+            .and_then(|i| self.library.filters.get(i))
+            .cloned()
+        else {
+            return;
+        };
+        let extra = self.events.unmapped_columns();
+        let query = match filter.search.strip_prefix("re:") {
+            Some(re) => Query::regex(re, &extra),
+            None => Query::parse(&filter.search, &extra),
+        };
+        let query = match query {
+            Ok(q) => q,
+            Err(e) => {
+                self.set_error(format!("{}: search does not parse: {}", filter.name, e));
+                return;
+            }
+        };
+        let time = match &filter.time {
+            Some(text) => match self.parse_time_input(text) {
+                Some(range) => range,
+                None => {
+                    self.set_error(format!("{}: invalid time range {}", filter.name, text));
+                    return;
+                }
+            },
+            None => (None, None),
+        };
+        if let Err(e) = self.noise.enable_only(&filter.exclusions, &extra) {
+            self.set_error(format!(
+                "{}: exclusion does not apply: {:#}",
+                filter.name, e
+            ));
+            return;
+        }
+        if let Err(e) = self.noise.save() {
+            self.set_error(format!("Noise profile not saved: {:#}", e));
+        }
+        self.search = filter.search.clone();
+        self.set_query(query);
+        self.action_type_filter = filter.action_types.clone();
+        (self.time_range_start, self.time_range_end) = time;
+        self.source_filter = None;
+        self.ioc_only = false;
+        self.detection_filter = None;
+        self.process_filter = None;
+        self.breadcrumbs.clear();
         self.apply_filters();
         self.mode = Mode::Normal;
-        self.set_flash("Filter cleared".to_string());
+        self.set_flash(format!(
+            "Filter {}: {} events",
+            filter.name,
+            self.filtered_indices.len()
+        ));
     }
 
-    /// clear search, action-type filter, and/or time range from Normal mode
+    /// clear search, action-type/source filter, and/or time range from Normal mode
     pub fn clear_search_and_filter_in_normal(&mut self) {
         let had_search = !self.search.is_empty();
-        let had_filter = self.action_type_filter.is_some();
+        let had_filter = !self.action_type_filter.is_empty()
+            || self.source_filter.is_some()
+            || self.ioc_only
+            || self.detection_filter.is_some()
+            || self.process_filter.is_some();
         let had_time = self.time_range_start.is_some() || self.time_range_end.is_some();
         if had_search {
             self.search.clear();
+            self.set_query(Query::default());
         }
         if had_filter {
-            self.action_type_filter = None;
+            self.action_type_filter = ActionTypeFilter::default();
+            self.source_filter = None;
+            self.ioc_only = false;
+            self.detection_filter = None;
+            self.process_filter = None;
+            self.breadcrumbs.clear();
         }
         if had_time {
             self.time_range_start = None;
@@ -753,30 +2704,263 @@ impl App {
     }
 
     pub fn action_type_next(&mut self) {
+        let n = self.visible_action_types().len();
         let i = self
             .action_type_list_state
             .selected()
-            .map(|i| (i + 1).min(self.action_types.len().saturating_sub(1)))
+            .map(|i| (i + 1).min(n.saturating_sub(1)))
             .unwrap_or(0);
         self.action_type_list_state
-            .select(if self.action_types.is_empty() {
-                None
-            } else {
-                Some(i)
-            });
+            .select(if n == 0 { None } else { Some(i) });
     }
 
     pub fn action_type_previous(&mut self) {
+        let n = self.visible_action_types().len();
         let i = self
             .action_type_list_state
             .selected()
             .map(|i| i.saturating_sub(1))
             .unwrap_or(0);
         self.action_type_list_state
-            .select(if self.action_types.is_empty() {
-                None
-            } else {
-                Some(i)
-            });
+            .select(if n == 0 { None } else { Some(i) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempPath;
+
+    /// explorer starts cmd, cmd drops a file and starts whoami in the last half millisecond of
+    /// the 09:00 hour, and an unrelated svchost connects out at 10:30
+    const EVENTS: &[&[(&str, &str)]] = &[
+        &[
+            ("Event Time", "2024-05-01T09:00:00Z"),
+            ("Action Type", "ProcessCreated"),
+            ("File Name", "cmd.exe"),
+            ("Process Id", "200"),
+            ("Process Creation Time", "2024-05-01T09:00:00Z"),
+            ("Initiating Process File Name", "explorer.exe"),
+            ("Initiating Process Id", "100"),
+            ("Initiating Process Creation Time", "2024-05-01T08:00:00Z"),
+        ],
+        &[
+            ("Event Time", "2024-05-01T09:00:30Z"),
+            ("Action Type", "FileCreated"),
+            ("File Name", "drop.exe"),
+            ("Initiating Process File Name", "cmd.exe"),
+            ("Initiating Process Id", "200"),
+            ("Initiating Process Creation Time", "2024-05-01T09:00:00Z"),
+            ("Initiating Process Parent Id", "100"),
+            (
+                "Initiating Process Parent Creation Time",
+                "2024-05-01T08:00:00Z",
+            ),
+        ],
+        &[
+            ("Event Time", "2024-05-01T09:59:59.9995Z"),
+            ("Action Type", "ProcessCreated"),
+            ("File Name", "whoami.exe"),
+            ("Process Id", "300"),
+            ("Process Creation Time", "2024-05-01T09:59:59.9995Z"),
+            ("Initiating Process File Name", "cmd.exe"),
+            ("Initiating Process Id", "200"),
+            ("Initiating Process Creation Time", "2024-05-01T09:00:00Z"),
+            ("Initiating Process Parent Id", "100"),
+            (
+                "Initiating Process Parent Creation Time",
+                "2024-05-01T08:00:00Z",
+            ),
+        ],
+        &[
+            ("Event Time", "2024-05-01T10:30:00Z"),
+            ("Action Type", "ConnectionSuccess"),
+            ("Remote IP", "10.0.0.5"),
+            ("Initiating Process File Name", "svchost.exe"),
+            ("Initiating Process Id", "400"),
+            ("Initiating Process Creation Time", "2024-05-01T07:00:00Z"),
+        ],
+    ];
+
+    /// an app over `EVENTS` (as json lines), loaded; the file lives as long as the app reads it
+    fn loaded_app(library: FilterLibrary) -> (App, TempPath) {
+        let path = TempPath::new("app.jsonl");
+        let lines: Vec<String> = EVENTS
+            .iter()
+            .map(|columns| {
+                let map: serde_json::Map<String, serde_json::Value> = columns
+                    .iter()
+                    .map(|&(k, v)| (k.to_string(), v.into()))
+                    .collect();
+                serde_json::Value::Object(map).to_string()
+            })
+            .collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let mut app = App::new(
+            vec![Source::new(path.to_path_buf())],
+            DisplayZone::Utc,
+            NoiseProfile::default(),
+            IocList::default(),
+            SigmaRules::default(),
+            library,
+        )
+        .unwrap();
+        let started = Instant::now();
+        while app.loading.is_some() {
+            assert!(started.elapsed() < Duration::from_secs(10), "load hangs");
+            app.poll_loader();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(app.filtered_indices, [0, 1, 2, 3]);
+        (app, path)
+    }
+
+    fn process_line(app: &App, name: &str) -> Option<usize> {
+        app.tree_lines.iter().position(|l| {
+            matches!(l, TreeLine::Process { node, .. }
+                if app.processes.get(*node).name.as_deref() == Some(name))
+        })
+    }
+
+    #[test]
+    fn process_tree_expands_and_collapses() {
+        let (mut app, _path) = loaded_app(FilterLibrary::default());
+        app.list_state.select(Some(2));
+        app.start_process_tree();
+        assert_eq!(app.mode, Mode::ProcessTree);
+        // explorer > cmd > whoami, then cmd's two events, then the one explorer initiated
+        assert_eq!(app.tree_lines.len(), 6);
+        assert_eq!(process_line(&app, "whoami.exe"), Some(2));
+        assert_eq!(app.tree_list_state.selected(), Some(2));
+        assert_eq!(
+            app.tree_lines[3..],
+            [
+                TreeLine::Activity { row: 1, depth: 2 },
+                TreeLine::Activity { row: 2, depth: 2 },
+                TreeLine::Activity { row: 0, depth: 1 },
+            ]
+        );
+
+        // whoami did nothing, so collapsing it changes no line; again moves up to cmd
+        app.tree_collapse();
+        assert_eq!(app.tree_lines.len(), 6);
+        app.tree_collapse();
+        assert_eq!(
+            app.tree_list_state.selected(),
+            process_line(&app, "cmd.exe")
+        );
+        app.tree_collapse();
+        assert_eq!(app.tree_lines.len(), 3);
+        assert_eq!(process_line(&app, "whoami.exe"), None);
+        app.tree_open();
+        assert_eq!(app.tree_lines.len(), 6);
+
+        // an event line opens that event in the list
+        app.tree_list_state.select(Some(3));
+        app.tree_open();
+        assert_eq!(app.mode, Mode::Normal);
+        assert_eq!(app.list_state.selected(), Some(1));
+    }
+
+    #[test]
+    fn pivot_to_process_and_value_then_back() {
+        let (mut app, _path) = loaded_app(FilterLibrary::default());
+        app.list_state.select(Some(1));
+        app.pivot_to_process();
+        assert_eq!(app.filtered_indices, [0, 1, 2]);
+        assert_eq!(app.list_state.selected(), Some(1));
+        assert_eq!(app.breadcrumbs.len(), 1);
+        assert_eq!(app.breadcrumbs[0].label, "all events");
+
+        app.start_detail_cursor();
+        let lines = app.selected_event().unwrap().detail_lines();
+        app.detail_cursor = lines.iter().position(|(l, _)| l == "File Name").unwrap();
+        app.filter_to_detail_value();
+        assert_eq!(app.filtered_indices, [1]);
+        assert!(app.search.contains("drop.exe"), "{}", app.search);
+        assert!(app.process_filter.is_some());
+
+        app.pivot_back();
+        assert!(app.breadcrumbs.is_empty());
+        assert_eq!(app.process_filter, None);
+        assert_eq!(app.search, "");
+        assert_eq!(app.filtered_indices, [0, 1, 2, 3]);
+        assert_eq!(app.list_state.selected(), Some(1));
+    }
+
+    #[test]
+    fn saved_filter_replaces_the_view() {
+        let mut library = FilterLibrary::default();
+        library
+            .put(SavedFilter {
+                name: "no connections".into(),
+                search: String::new(),
+                action_types: ActionTypeFilter::default(),
+                time: None,
+                exclusions: vec![Rule::ActionType {
+                    action_type: "ConnectionSuccess".into(),
+                }],
+            })
+            .unwrap();
+        library
+            .put(SavedFilter {
+                name: "early drops".into(),
+                search: "drop.exe".into(),
+                action_types: ActionTypeFilter::default(),
+                time: Some("2024-05-01 09:00 to 2024-05-01 09:30".into()),
+                exclusions: Vec::new(),
+            })
+            .unwrap();
+        let (mut app, _path) = loaded_app(library);
+
+        // from a pivot: the process filter and its trail go
+        app.list_state.select(Some(3));
+        app.pivot_to_process();
+        assert_eq!(app.filtered_indices, [3]);
+        app.start_library();
+        app.library_list_state.select(Some(1));
+        app.apply_saved_filter();
+        assert_eq!(app.mode, Mode::Normal);
+        assert!(app.breadcrumbs.is_empty());
+        assert_eq!(app.process_filter, None);
+        assert_eq!(app.filtered_indices, [0, 1, 2]);
+        assert_eq!(app.noise_hidden, [1]);
+
+        // the next one turns that exclusion off again and sets search and time
+        app.library_list_state.select(Some(0));
+        app.apply_saved_filter();
+        assert_eq!(app.search, "drop.exe");
+        assert!(app.time_range_start.is_some() && app.time_range_end.is_some());
+        assert_eq!(app.filtered_indices, [1]);
+        assert_eq!(app.noise.active(), 0);
+    }
+
+    #[test]
+    fn histogram_zooms_into_a_bar_and_back() {
+        let (mut app, _path) = loaded_app(FilterLibrary::default());
+        app.start_histogram();
+        // two bars: the 09:00 hour and the 10:00 hour
+        app.refresh_histogram(2);
+        assert_eq!(app.histogram.as_ref().unwrap().len(), 2);
+        assert_eq!(app.histogram_cursor, 0);
+        app.zoom_histogram();
+        // whoami at 09:59:59.9995 is still inside the bar
+        assert_eq!(app.filtered_indices, [0, 1, 2]);
+        assert_eq!(app.histogram_zoom, [(None, None)]);
+        app.refresh_histogram(60);
+        let histogram = app.histogram.as_ref().unwrap();
+        assert_eq!(
+            histogram.bucket_of(app.events.time(2).unwrap()),
+            Some(histogram.len() - 1)
+        );
+
+        app.list_state.select(Some(2));
+        app.unzoom_histogram();
+        assert!(app.histogram_zoom.is_empty());
+        assert_eq!((app.time_range_start, app.time_range_end), (None, None));
+        assert_eq!(app.filtered_indices, [0, 1, 2, 3]);
+        assert_eq!(app.list_state.selected(), Some(0));
+        app.unzoom_histogram();
+        assert!(app.flash.as_deref().unwrap().starts_with("Not zoomed in"));
     }
 }
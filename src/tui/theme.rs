@@ -26,10 +26,32 @@ impl Theme {
         Color::Rgb(0, 255, 255) // bright cyan
     }
 
+    /// search matches: black text on this background
+    pub fn match_color(&self) -> Color {
+        Color::Rgb(255, 255, 0) // yellow
+    }
+
     pub fn error_color(&self) -> Color {
         Color::Rgb(255, 0, 0) // bright red
     }
 
+    /// events tagged by a sigma rule
+    pub fn detection_color(&self) -> Color {
+        Color::Rgb(255, 165, 0) // orange
+    }
+
+    /// histogram series, most frequent action type first; the last is also "other"
+    pub fn series_colors(&self) -> [Color; 6] {
+        [
+            Color::Rgb(0, 255, 255),  // bright cyan
+            Color::Rgb(255, 255, 0),  // yellow
+            Color::Rgb(255, 0, 255),  // magenta
+            Color::Rgb(255, 165, 0),  // orange
+            Color::Rgb(80, 160, 255), // blue
+            Color::Rgb(0, 200, 0),    // medium green
+        ]
+    }
+
     /// command bar: black text on green for contrast
     pub fn command_bar_text_color(&self) -> Color {
         Color::Black
@@ -1,13 +1,16 @@
 // draw event list, detail panel, command bar (hacker theme)
 
+use crate::query::Highlighter;
 use crate::timeline::TimelineEvent;
+use crate::zone::DisplayZone;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{
-    Block, Borders, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState,
-    Wrap,
+    Block, Borders, Gauge, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarOrientation,
+    ScrollbarState, Wrap,
 };
+use std::ops::Range;
 use std::rc::Rc;
 
 use super::app::App;
@@ -40,6 +43,34 @@ pub fn draw_list(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
         draw_action_type_picker(f, area, app);
         return;
     }
+    if app.mode == Mode::SourceFilter {
+        draw_source_picker(f, area, app);
+        return;
+    }
+    if app.mode == Mode::Rejected {
+        draw_rejected_list(f, area, app);
+        return;
+    }
+    if app.mode == Mode::Exclusions {
+        draw_exclusions(f, area, app);
+        return;
+    }
+    if app.mode == Mode::Detections {
+        draw_detections(f, area, app);
+        return;
+    }
+    if app.mode == Mode::Library {
+        draw_library(f, area, app);
+        return;
+    }
+    if app.mode == Mode::ProcessTree {
+        draw_process_tree(f, area, app);
+        return;
+    }
+    if app.mode == Mode::Stacking {
+        draw_stacking(f, area, app);
+        return;
+    }
     if app.mode == Mode::TimeFilter && app.time_filter_sub == TimeFilterSub::Picker {
         draw_time_picker(f, area, app);
         return;
@@ -57,29 +88,96 @@ pub fn draw_list(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
         return;
     }
 
+    // while the loader runs, the progress bar sits on top of the (already usable) list
+    let area = if app.loading.is_some() {
+        let chunks = Layout::default()
+            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+            .direction(Direction::Vertical)
+            .split(area);
+        draw_load_progress(f, chunks[0], app);
+        chunks[1]
+    } else {
+        area
+    };
+
     let t = theme();
     let has_time = app.time_range_start.is_some() || app.time_range_end.is_some();
-    let has_filter = !app.search.is_empty() || app.action_type_filter.is_some() || has_time;
+    let has_filter = !app.shown_search().is_empty()
+        || !app.action_type_filter.is_empty()
+        || app.source_filter.is_some()
+        || app.ioc_only
+        || app.detection_filter.is_some()
+        || app.process_filter.is_some()
+        || has_time;
     let empty = app.filtered_indices.is_empty();
 
-    if empty && has_filter {
+    if empty && !app.search_busy() && (has_filter || app.noise_hidden_total() > 0) {
         draw_no_results(f, area, app);
         return;
     }
 
+    let highlighter = &app.highlighter;
+    // rows are read from disk, so only build items for the visible window
+    let height = area.height.saturating_sub(2) as usize;
+    let (offset, mut window_state) = window(&mut app.list_state, height);
     let items: Vec<ListItem> = app
         .filtered_indices
         .iter()
-        .take(5000)
-        .filter_map(|&idx| app.events.get(idx))
-        .map(|ev| {
-            let line = ev.list_line();
-            let line = truncate_for_display(&line, area.width.saturating_sub(4) as usize);
-            ListItem::new(Line::from(Span::raw(line)))
+        .skip(offset)
+        .take(height)
+        .filter_map(|&idx| app.events.get(idx).map(|ev| (idx, ev)))
+        .map(|(idx, ev)| {
+            // with an ioc list or sigma rules loaded, rows touching an indicator get a `!`
+            // gutter and rows a rule matched a `*`
+            let (mut spans, width) = if app.ioc.is_empty() && app.sigma.is_empty() {
+                (Vec::new(), area.width.saturating_sub(4))
+            } else if !app.ioc.is_empty() && app.ioc.is_hit(&ev) {
+                let flag = Style::default()
+                    .fg(t.error_color())
+                    .add_modifier(Modifier::BOLD);
+                (vec![Span::styled("! ", flag)], area.width.saturating_sub(6))
+            } else if app.detected_rows.binary_search(&(idx as u32)).is_ok() {
+                let flag = Style::default()
+                    .fg(t.detection_color())
+                    .add_modifier(Modifier::BOLD);
+                (vec![Span::styled("* ", flag)], area.width.saturating_sub(6))
+            } else {
+                (vec![Span::raw("  ")], area.width.saturating_sub(6))
+            };
+            let line = ev.list_line(&app.zone);
+            let line = truncate_for_display(&line, width as usize);
+            let marks = highlighter.ranges(None, &line);
+            spans.extend(marked_spans(&line, &marks));
+            ListItem::new(Line::from(spans))
         })
         .collect();
 
-    let title = format!(" Events ({}) ", app.filtered_indices.len());
+    let mut title = format!(" Events ({}) ", app.filtered_indices.len());
+    if let Some(node) = app.process_filter {
+        // breadcrumb trail: the views pivoted from, then the process shown
+        let mut trail: Vec<String> = app.breadcrumbs.iter().map(|b| b.label.clone()).collect();
+        trail.push(app.processes.get(node).label());
+        title.push_str(&format!("— {} ", trail.join(" › ")));
+    }
+    if app.search_busy() {
+        title.push_str("— searching… ");
+    }
+    if app.ioc_only {
+        title.push_str("— IOC hits only ");
+    } else if !app.ioc.is_empty() {
+        title.push_str(&format!("— {} IOC hits ", app.ioc_rows.len()));
+    }
+    if let Some(rule) = app.detection_filter {
+        title.push_str(&format!("— {} ", app.sigma.rules[rule].title));
+    } else if !app.sigma.is_empty() {
+        title.push_str(&format!("— {} detections ", app.detected_rows.len()));
+    }
+    if app.noise_hidden_total() > 0 {
+        title.push_str(&format!("— {} hidden ", app.noise_hidden_total()));
+    }
+    if app.load_report.rejected_total > 0 {
+        title.push_str(&format!("— {} rejected ", app.load_report.rejected_total));
+    }
     let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(t.border_color()))
@@ -103,17 +201,114 @@ pub fn draw_list(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
         )
         .highlight_symbol("> ");
 
-    f.render_stateful_widget(list, area, &mut app.list_state);
+    f.render_stateful_widget(list, area, &mut window_state);
+}
+
+/// scroll `state` so its selection is among the `height` visible rows; returns the first
+/// visible item and the state to render just the visible items with
+fn window(state: &mut ListState, height: usize) -> (usize, ListState) {
+    let selected = state.selected();
+    let mut offset = state.offset();
+    if let Some(sel) = selected {
+        if sel < offset {
+            offset = sel;
+        } else if height > 0 && sel >= offset + height {
+            offset = sel + 1 - height;
+        }
+    }
+    *state.offset_mut() = offset;
+    let visible = ListState::default().with_selected(selected.map(|sel| sel - offset));
+    (offset, visible)
+}
+
+fn draw_load_progress(f: &mut ratatui::Frame, area: Rect, app: &App) {
+    let t = theme();
+    let p = match &app.loading {
+        Some(state) => state.progress,
+        None => return,
+    };
+    let ratio = if p.total_bytes == 0 {
+        0.0
+    } else {
+        (p.bytes_read as f64 / p.total_bytes as f64).clamp(0.0, 1.0)
+    };
+    let label = format!(
+        "{} / {}  |  {} rows  |  {} skipped",
+        format_bytes(p.bytes_read),
+        format_bytes(p.total_bytes),
+        p.rows_parsed,
+        p.rows_skipped
+    );
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(t.border_color()))
+        .style(Style::default().bg(t.background_color()))
+        .title(Span::styled(
+            " Loading ",
+            Style::default()
+                .fg(t.title_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        ));
+    let gauge = Gauge::default()
+        .block(block)
+        .gauge_style(
+            Style::default()
+                .fg(t.border_color())
+                .bg(t.background_color()),
+        )
+        .label(Span::styled(
+            label,
+            Style::default()
+                .fg(t.title_color())
+                .add_modifier(Modifier::BOLD),
+        ))
+        .ratio(ratio);
+    f.render_widget(gauge, area);
+}
+
+/// human-readable byte count (binary units)
+fn format_bytes(n: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut v = n as f64;
+    let mut unit = 0;
+    while v >= 1024.0 && unit < UNITS.len() - 1 {
+        v /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", n, UNITS[0])
+    } else {
+        format!("{:.1} {}", v, UNITS[unit])
+    }
 }
 
 fn draw_no_results(f: &mut ratatui::Frame, area: Rect, app: &App) {
     let t = theme();
     let mut lines = vec!["No events match.".to_string(), String::new()];
-    if !app.search.is_empty() {
-        lines.push(format!("Search: \"{}\"", app.search));
+    if !app.shown_search().is_empty() {
+        lines.push(format!("Search: \"{}\"", app.shown_search()));
+    }
+    if !app.action_type_filter.is_empty() {
+        lines.push(format!(
+            "Action type filter: {}",
+            app.action_type_filter.describe()
+        ));
+    }
+    if let Some(label) = app.source_filter.and_then(|i| app.events.label(i)) {
+        lines.push(format!("Source filter: {}", label));
+    }
+    if app.ioc_only {
+        lines.push("IOC hits only ([ i ] to show all)".to_string());
+    }
+    if let Some(rule) = app.detection_filter {
+        lines.push(format!("Detection: {}", app.sigma.rules[rule].title));
     }
-    if let Some(ref at) = app.action_type_filter {
-        lines.push(format!("Action type filter: {}", at));
+    if let Some(node) = app.process_filter {
+        lines.push(format!(
+            "Process: {} ([ Backspace ] back)",
+            app.processes.get(node).label()
+        ));
     }
     if app.time_range_start.is_some() || app.time_range_end.is_some() {
         let tr = match (&app.time_range_start, &app.time_range_end) {
@@ -130,6 +325,12 @@ fn draw_no_results(f: &mut ratatui::Frame, area: Rect, app: &App) {
             lines.push(format!("Time range: {}", tr));
         }
     }
+    if app.noise_hidden_total() > 0 {
+        lines.push(format!(
+            "Hidden by exclusions: {} ([ e ] to review)",
+            app.noise_hidden_total()
+        ));
+    }
     lines.push(String::new());
     lines.push("Try different terms or press [ x ] to clear search & filter.".to_string());
     let text = lines.join("\n");
@@ -283,17 +484,38 @@ fn draw_date_range_picker(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
 }
 
 fn draw_action_type_picker(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
+    use crate::filters::Mark;
+
     let t = theme();
-    let items: Vec<ListItem> = app
-        .action_types
+    let width = area.width.saturating_sub(4) as usize;
+    let visible = app.visible_action_types();
+    let items: Vec<ListItem> = visible
         .iter()
-        .map(|s| {
-            let line = truncate_for_display(s, area.width.saturating_sub(4) as usize);
-            ListItem::new(Line::from(Span::raw(line)))
+        .map(|&i| {
+            let (at, n) = &app.action_types[i];
+            let (mark, style) = match app.action_type_marks.mark(at) {
+                Mark::Include => ("[+]", Style::default().fg(t.highlight_color())),
+                Mark::Exclude => (
+                    "[-]",
+                    Style::default()
+                        .fg(t.border_color())
+                        .add_modifier(Modifier::CROSSED_OUT),
+                ),
+                Mark::Off => ("[ ]", Style::default()),
+            };
+            let line = truncate_for_display(&format!("{} {} ({})", mark, at, n), width);
+            ListItem::new(Line::from(Span::styled(line, style)))
         })
         .collect();
 
-    let title = " Esc to go back — Filter by action type (Enter apply) ";
+    let marks = &app.action_type_marks;
+    let title = format!(
+        " Esc back — Action types ({} of {}) — {} included, {} excluded (Enter apply) ",
+        visible.len(),
+        app.action_types.len(),
+        marks.include.len(),
+        marks.exclude.len()
+    );
     let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(t.border_color()))
@@ -320,12 +542,732 @@ fn draw_action_type_picker(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
     f.render_stateful_widget(list, area, &mut app.action_type_list_state);
 }
 
-pub fn draw_detail(f: &mut ratatui::Frame, area: Rect, app: &App) {
+fn draw_source_picker(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
+    let t = theme();
+    let items: Vec<ListItem> = (0..app.sources.len())
+        .map(|i| {
+            let label = app.events.label(i).map_or("", |l| &**l);
+            let line = format!("{} ({})", label, app.events.source_len(i));
+            let line = truncate_for_display(&line, area.width.saturating_sub(4) as usize);
+            ListItem::new(Line::from(Span::raw(line)))
+        })
+        .collect();
+
+    let title = " Esc to go back — Filter by source (Enter apply) ";
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(t.border_color()))
+        .style(Style::default().bg(t.background_color()))
+        .title(Span::styled(
+            title,
+            Style::default()
+                .fg(t.title_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        ));
+
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().fg(t.text_color()).bg(t.background_color()))
+        .highlight_style(
+            Style::default()
+                .fg(t.highlight_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    f.render_stateful_widget(list, area, &mut app.source_list_state);
+}
+
+fn draw_exclusions(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
+    let t = theme();
+    let width = area.width.saturating_sub(4) as usize;
+    let items: Vec<ListItem> = if app.noise.rules.is_empty() {
+        vec![ListItem::new(Line::from(Span::raw(
+            "No exclusion rules. [ a ] action type, [ p ] process or [ f ] file name of the selected event, [ n ] query.",
+        )))]
+    } else {
+        app.noise
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(i, ex)| {
+                let check = if ex.enabled { "[x]" } else { "[ ]" };
+                let count = match &ex.error {
+                    Some(e) => format!("error: {}", e),
+                    None if ex.enabled => {
+                        format!("{} hidden", app.noise_hidden.get(i).copied().unwrap_or(0))
+                    }
+                    None => "off".to_string(),
+                };
+                let line = format!("{} {}  ({})", check, ex.rule.label(), count);
+                ListItem::new(Line::from(Span::raw(truncate_for_display(&line, width))))
+            })
+            .collect()
+    };
+
+    let title = match &app.noise.path {
+        Some(path) => format!(
+            " Esc back — Exclusions ({} on) — {} ",
+            app.noise.active(),
+            path.display()
+        ),
+        None => format!(
+            " Esc back — Exclusions ({} on, not saved) ",
+            app.noise.active()
+        ),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(t.border_color()))
+        .style(Style::default().bg(t.background_color()))
+        .title(Span::styled(
+            truncate_for_display(&title, width),
+            Style::default()
+                .fg(t.title_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        ));
+
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().fg(t.text_color()).bg(t.background_color()))
+        .highlight_style(
+            Style::default()
+                .fg(t.highlight_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    f.render_stateful_widget(list, area, &mut app.noise_list_state);
+}
+
+/// value combinations with their counts; only the visible window is built
+fn draw_stacking(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
+    let t = theme();
+    let width = area.width.saturating_sub(4) as usize;
+    let height = area.height.saturating_sub(2) as usize;
+    let (offset, mut window_state) = window(&mut app.stack_list_state, height);
+    let (items, title): (Vec<ListItem>, String) = match &app.stack {
+        Some(stack) => {
+            let digits = stack
+                .rows
+                .iter()
+                .map(|r| r.count)
+                .max()
+                .unwrap_or(0)
+                .to_string()
+                .len();
+            let items = stack
+                .rows
+                .iter()
+                .skip(offset)
+                .take(height)
+                .map(|row| {
+                    let values: Vec<&str> = row
+                        .values
+                        .iter()
+                        .map(|v| if v.is_empty() { "(empty)" } else { v.as_str() })
+                        .collect();
+                    let line = format!("{:>digits$}  {}", row.count, values.join("  →  "));
+                    ListItem::new(Line::from(truncate_for_display(&line, width)))
+                })
+                .collect();
+            let order = if stack.ascending {
+                "rarest first"
+            } else {
+                "most common first"
+            };
+            let title = format!(
+                " Esc back — {} ({} combinations in {} events, {}) ",
+                stack.labels().join(" → "),
+                stack.len(),
+                stack.total,
+                order
+            );
+            (items, title)
+        }
+        None => (
+            vec![ListItem::new(Line::from(
+                "Type the fields to count, e.g. initiating_process_file_name file_name",
+            ))],
+            " Esc back — Stacking ".to_string(),
+        ),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(t.border_color()))
+        .style(Style::default().bg(t.background_color()))
+        .title(Span::styled(
+            truncate_for_display(&title, width),
+            Style::default()
+                .fg(t.title_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        ));
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().fg(t.text_color()).bg(t.background_color()))
+        .highlight_style(
+            Style::default()
+                .fg(t.highlight_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, area, &mut window_state);
+}
+
+/// eighths of a cell, for the top of a bar
+const BAR_EIGHTHS: [&str; 9] = [" ", "▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+
+/// events per time bucket across the filtered set, one column per bucket, stacked by action
+/// type; the bar under the cursor is described above the chart
+fn draw_histogram(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
+    let t = theme();
+    let inner_width = area.width.saturating_sub(2) as usize;
+    app.refresh_histogram(inner_width);
+    let Some(h) = &app.histogram else {
+        return;
+    };
+    let lines = if h.is_empty() {
+        vec![Line::from("No event times to chart.")]
+    } else {
+        histogram_lines(h, app.histogram_cursor, inner_width, area.height)
+    };
+
+    let mut title = format!(
+        " Esc back — Events over time ({} bars, {} events) ",
+        h.bucket_label(),
+        app.filtered_indices.len()
+    );
+    if !app.histogram_zoom.is_empty() {
+        title.push_str(&format!("— zoomed in {}× ", app.histogram_zoom.len()));
+    }
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(t.border_color()))
+        .style(Style::default().bg(t.background_color()))
+        .title(Span::styled(
+            truncate_for_display(&title, inner_width),
+            Style::default()
+                .fg(t.title_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        ));
+    let para = Paragraph::new(lines)
+        .block(block)
+        .style(Style::default().fg(t.text_color()).bg(t.background_color()));
+    f.render_widget(para, area);
+}
+
+/// the histogram panel's rows: `inner_width` columns inside the border, `height` rows with it
+fn histogram_lines(
+    h: &crate::histogram::Histogram,
+    cursor: usize,
+    inner_width: usize,
+    height: u16,
+) -> Vec<Line<'static>> {
+    let t = theme();
+    let colors = t.series_colors();
+    let color = |s: usize| colors[s.min(colors.len() - 1)];
+    let time_format = match h.bucket.num_seconds() {
+        s if s < 60 => "%Y-%m-%d %H:%M:%S",
+        s if s < 86400 => "%Y-%m-%d %H:%M",
+        _ => "%Y-%m-%d",
+    };
+    let mut lines = Vec::new();
+
+    // the bar under the cursor
+    let (start, end) = h.range(cursor);
+    let mut about = vec![Span::styled(
+        format!(
+            "{} – {}  {} events",
+            start.format(time_format),
+            end.format(time_format),
+            h.total(cursor)
+        ),
+        Style::default()
+            .fg(t.highlight_color())
+            .add_modifier(Modifier::BOLD),
+    )];
+    for (s, &n) in h.counts.get(cursor).into_iter().flatten().enumerate() {
+        if n > 0 {
+            about.push(Span::raw("  "));
+            about.push(Span::styled(
+                format!("{} {}", h.series[s], n),
+                Style::default().fg(color(s)),
+            ));
+        }
+    }
+    lines.push(Line::from(about));
+
+    // bars: eighths of a cell, stacked in series order from the bottom
+    let chart_height = height.saturating_sub(6).max(1) as usize;
+    let bar_width = (inner_width / h.len().max(1)).clamp(1, 6);
+    let fill_width = if bar_width > 2 {
+        bar_width - 1
+    } else {
+        bar_width
+    };
+    let max = u64::from(h.max().max(1));
+    let scale = |n: u32| {
+        let e = u64::from(n) * chart_height as u64 * 8 / max;
+        if n > 0 {
+            e.max(1) as usize
+        } else {
+            0
+        }
+    };
+    let tops: Vec<Vec<usize>> = h
+        .counts
+        .iter()
+        .map(|c| {
+            let mut sum = 0;
+            c.iter()
+                .map(|&n| {
+                    sum += n;
+                    scale(sum)
+                })
+                .collect()
+        })
+        .collect();
+    for row in (0..chart_height).rev() {
+        let low = row * 8;
+        let mut spans = Vec::with_capacity(h.len());
+        for (i, tops) in tops.iter().enumerate() {
+            let top = tops.last().copied().unwrap_or(0);
+            let fill = top.saturating_sub(low).min(8);
+            let pad = " ".repeat(bar_width - fill_width);
+            if fill == 0 {
+                let guide = if i == cursor { "│" } else { " " };
+                spans.push(Span::styled(
+                    format!("{}{}", guide.repeat(fill_width), pad),
+                    Style::default().fg(t.highlight_color()),
+                ));
+                continue;
+            }
+            // the series at the middle of the filled part of this cell
+            let mid = low + (fill - 1) / 2;
+            let s = tops.iter().position(|&e| e > mid).unwrap_or(0);
+            let mut style = Style::default().fg(color(s));
+            if i == cursor {
+                style = style.add_modifier(Modifier::BOLD);
+            }
+            spans.push(Span::styled(
+                format!("{}{}", BAR_EIGHTHS[fill].repeat(fill_width), pad),
+                style,
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    // axis with the cursor marked, then the time span
+    let axis: String = (0..h.len())
+        .map(|i| {
+            let mark = if i == cursor { "▲" } else { "─" };
+            format!(
+                "{}{}",
+                mark.repeat(fill_width),
+                "─".repeat(bar_width - fill_width)
+            )
+        })
+        .collect();
+    lines.push(Line::from(Span::styled(
+        axis,
+        Style::default().fg(t.border_color()),
+    )));
+    let first = h.start.format(time_format).to_string();
+    let last = h
+        .range(h.len().saturating_sub(1))
+        .1
+        .format(time_format)
+        .to_string();
+    let gap = (h.len() * bar_width)
+        .saturating_sub(first.len() + last.len())
+        .max(2);
+    lines.push(Line::from(format!("{}{}{}", first, " ".repeat(gap), last)));
+
+    // legend
+    let mut legend = Vec::new();
+    for (s, name) in h.series.iter().enumerate() {
+        legend.push(Span::styled("■ ", Style::default().fg(color(s))));
+        legend.push(Span::raw(format!("{}  ", name)));
+    }
+    lines.push(Line::from(legend));
+    lines
+}
+
+fn draw_process_tree(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
+    use super::app::TreeLine;
+
+    let t = theme();
+    let width = area.width.saturating_sub(4) as usize;
+    // event lines are read from disk, so only build items for the visible window
+    let height = area.height.saturating_sub(2) as usize;
+    let (offset, mut window_state) = window(&mut app.tree_list_state, height);
+    let items: Vec<ListItem> = app
+        .tree_lines
+        .iter()
+        .skip(offset)
+        .take(height)
+        .map(|line| match *line {
+            TreeLine::Process { node, depth } => {
+                let p = app.processes.get(node);
+                let marker = if p.rows.is_empty() && app.processes.children(node).is_empty() {
+                    "  "
+                } else if app.tree_expanded.contains(&node) {
+                    "▾ "
+                } else {
+                    "▸ "
+                };
+                let mut text = format!("{}{}{}", "  ".repeat(depth), marker, p.label());
+                if let Some(created) = p.created() {
+                    text.push_str(&format!(
+                        "  {}",
+                        app.zone.format(created, "%Y-%m-%d %H:%M:%S")
+                    ));
+                }
+                if let Some(cmd) = &p.command_line {
+                    text.push_str(&format!("  {}", cmd));
+                }
+                ListItem::new(Line::from(Span::styled(
+                    truncate_for_display(&text, width),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )))
+            }
+            TreeLine::Activity { row, depth } => {
+                let text = match app.events.get(row as usize) {
+                    Some(ev) => format!(
+                        "{}· {}  {}  {}",
+                        "  ".repeat(depth),
+                        ev.display_time(&app.zone),
+                        ev.action_type.as_deref().unwrap_or("—"),
+                        activity_target(&ev)
+                    ),
+                    None => format!("{}· row {} unreadable", "  ".repeat(depth), row),
+                };
+                ListItem::new(Line::from(Span::raw(truncate_for_display(&text, width))))
+            }
+        })
+        .collect();
+
+    let root = app
+        .tree_root
+        .map(|r| app.processes.get(r).label())
+        .unwrap_or_default();
+    let title = format!(
+        " Esc back — Process tree: {} on {} ({} processes) ",
+        root,
+        app.tree_root
+            .map(|r| app.processes.device(&app.processes.get(r).key))
+            .unwrap_or(""),
+        app.processes.len()
+    );
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(t.border_color()))
+        .style(Style::default().bg(t.background_color()))
+        .title(Span::styled(
+            truncate_for_display(&title, width),
+            Style::default()
+                .fg(t.title_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        ));
+
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().fg(t.text_color()).bg(t.background_color()))
+        .highlight_style(
+            Style::default()
+                .fg(t.highlight_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    f.render_stateful_widget(list, area, &mut window_state);
+}
+
+/// what an event in the process tree touched: registry key, remote endpoint or file
+fn activity_target(ev: &TimelineEvent) -> String {
+    let value = |v: &Option<String>| {
+        v.as_deref()
+            .map(|s| s.trim().trim_matches('"'))
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+    if let Some(key) = value(&ev.registry_key) {
+        return match value(&ev.registry_value_name) {
+            Some(name) => format!("{}\\{}", key, name),
+            None => key,
+        };
+    }
+    let creates = ev
+        .action_type
+        .as_deref()
+        .is_some_and(|at| at.starts_with("ProcessCreated"));
+    if let Some(cmd) = value(&ev.process_command_line).filter(|_| creates) {
+        return cmd;
+    }
+    if let Some(ip) = value(&ev.remote_ip) {
+        let endpoint = match value(&ev.remote_port) {
+            Some(port) => format!("{}:{}", ip, port),
+            None => ip,
+        };
+        return match value(&ev.remote_url) {
+            Some(url) => format!("{} ({})", url, endpoint),
+            None => endpoint,
+        };
+    }
+    match (value(&ev.folder_path), value(&ev.file_name)) {
+        (Some(path), _) => path,
+        (None, Some(name)) => name,
+        (None, None) => value(&ev.process_command_line).unwrap_or_default(),
+    }
+}
+
+fn draw_library(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
+    let t = theme();
+    let width = area.width.saturating_sub(4) as usize;
+    let items: Vec<ListItem> = if app.library.is_empty() {
+        vec![ListItem::new(Line::from(Span::raw(
+            "No saved filters. [ n ] saves the current search, action types, time range and exclusions under a name.",
+        )))]
+    } else {
+        app.library
+            .filters
+            .iter()
+            .map(|filter| {
+                let name = Span::styled(
+                    filter.name.clone(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                );
+                let rest = format!("  ({})", filter.describe());
+                let rest = truncate_for_display(
+                    &rest,
+                    width.saturating_sub(unicode_width::UnicodeWidthStr::width(
+                        filter.name.as_str(),
+                    )),
+                );
+                ListItem::new(Line::from(vec![name, Span::raw(rest)]))
+            })
+            .collect()
+    };
+
+    let title = match &app.library.path {
+        Some(path) => format!(
+            " Esc back — Saved filters ({}) — {} ",
+            app.library.len(),
+            path.display()
+        ),
+        None => format!(
+            " Esc back — Saved filters ({}, not saved) ",
+            app.library.len()
+        ),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(t.border_color()))
+        .style(Style::default().bg(t.background_color()))
+        .title(Span::styled(
+            truncate_for_display(&title, width),
+            Style::default()
+                .fg(t.title_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        ));
+
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().fg(t.text_color()).bg(t.background_color()))
+        .highlight_style(
+            Style::default()
+                .fg(t.highlight_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    f.render_stateful_widget(list, area, &mut app.library_list_state);
+}
+
+fn draw_detections(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
+    let t = theme();
+    let width = area.width.saturating_sub(4) as usize;
+    let items: Vec<ListItem> = app
+        .sigma
+        .rules
+        .iter()
+        .zip(&app.detection_rows)
+        .map(|(rule, rows)| {
+            let status = match &rule.error {
+                Some(e) => format!("error: {}", e),
+                None if rule.unmapped.is_empty() => format!("{} events", rows.len()),
+                None => format!(
+                    "{} events, no column for {}",
+                    rows.len(),
+                    rule.unmapped.join(", ")
+                ),
+            };
+            let line = format!("{:<13} {}  ({})", rule.level, rule.title, status);
+            let style = if rule.error.is_some() {
+                Style::default().fg(t.border_color())
+            } else if !rows.is_empty() {
+                Style::default()
+                    .fg(t.detection_color())
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            ListItem::new(Line::from(Span::styled(
+                truncate_for_display(&line, width),
+                style,
+            )))
+        })
+        .collect();
+
+    let firing = app.detection_rows.iter().filter(|r| !r.is_empty()).count();
+    let mut title = format!(
+        " Esc back — Detections ({} of {} rules matched) ",
+        firing,
+        app.sigma.len()
+    );
+    if !app.sigma.failed.is_empty() {
+        title.push_str(&format!("— {} files not loaded ", app.sigma.failed.len()));
+    }
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(t.border_color()))
+        .style(Style::default().bg(t.background_color()))
+        .title(Span::styled(
+            truncate_for_display(&title, width),
+            Style::default()
+                .fg(t.title_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        ));
+
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().fg(t.text_color()).bg(t.background_color()))
+        .highlight_style(
+            Style::default()
+                .fg(t.highlight_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    f.render_stateful_widget(list, area, &mut app.detections_list_state);
+}
+
+fn draw_rejected_list(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
     let t = theme();
+    let items: Vec<ListItem> = app
+        .load_report
+        .rejected
+        .iter()
+        .map(|row| {
+            let line = if app.sources.len() > 1 {
+                format!("{} line {} | {}", row.source, row.line, row.error)
+            } else {
+                format!("line {} | {}", row.line, row.error)
+            };
+            let line = truncate_for_display(&line, area.width.saturating_sub(4) as usize);
+            ListItem::new(Line::from(Span::raw(line)))
+        })
+        .collect();
+
+    let shown = app.load_report.rejected.len();
+    let total = app.load_report.rejected_total;
+    let title = if total > shown {
+        format!(" Esc back — Rejected rows ({} of {} kept) ", shown, total)
+    } else {
+        format!(" Esc back — Rejected rows ({}) ", total)
+    };
     let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(t.border_color()))
         .style(Style::default().bg(t.background_color()))
+        .title(Span::styled(
+            title,
+            Style::default()
+                .fg(t.error_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        ));
+
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().fg(t.text_color()).bg(t.background_color()))
+        .highlight_style(
+            Style::default()
+                .fg(t.highlight_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    f.render_stateful_widget(list, area, &mut app.rejected_list_state);
+}
+
+/// detail panel for the selected rejected row (Rejected mode)
+fn draw_rejected_detail(f: &mut ratatui::Frame, area: Rect, app: &App) {
+    let t = theme();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(t.border_color()))
+        .style(Style::default().bg(t.background_color()))
+        .title(Span::styled(
+            " Rejected row ",
+            Style::default()
+                .fg(t.title_color())
+                .bg(t.background_color())
+                .add_modifier(Modifier::BOLD),
+        ));
+    let row = app
+        .rejected_list_state
+        .selected()
+        .and_then(|i| app.load_report.rejected.get(i));
+    let content = match row {
+        Some(row) => format!(
+            "Source: {}\nLine: {}\nByte offset: {}\nError: {}\n\nRaw record:\n{}",
+            row.source, row.line, row.byte_offset, row.error, row.snippet
+        ),
+        None => "No rejected rows.".to_string(),
+    };
+    let para = Paragraph::new(content)
+        .block(block)
+        .style(Style::default().fg(t.text_color()).bg(t.background_color()))
+        .wrap(Wrap { trim: false })
+        .scroll((app.detail_scroll, 0));
+    f.render_widget(para, area);
+}
+
+pub fn draw_detail(f: &mut ratatui::Frame, area: Rect, app: &mut App) {
+    if app.mode == super::app::Mode::Rejected {
+        draw_rejected_detail(f, area, app);
+        return;
+    }
+    if app.mode == super::app::Mode::Histogram {
+        draw_histogram(f, area, app);
+        return;
+    }
+    let t = theme();
+    let focused = app.mode == super::app::Mode::Detail;
+    let border = if focused {
+        t.highlight_color()
+    } else {
+        t.border_color()
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(border))
+        .style(Style::default().bg(t.background_color()))
         .title(Span::styled(
             " Detail ",
             Style::default()
@@ -334,11 +1276,85 @@ pub fn draw_detail(f: &mut ratatui::Frame, area: Rect, app: &App) {
                 .add_modifier(Modifier::BOLD),
         ));
 
-    let content = if let Some(ev) = app.selected_event() {
-        detail_content(ev, area.width.saturating_sub(4) as usize)
+    let selected = app.selected_event();
+    let (content, match_rows, cursor_rows) = match &selected {
+        Some(ev) => detail_content(
+            ev,
+            &app.zone,
+            &app.highlighter,
+            area.width.saturating_sub(4) as usize,
+            focused.then_some(app.detail_cursor),
+        ),
+        None => (vec![Line::from("Select an event.")], Vec::new(), 0..0),
+    };
+    // indicators the event touches and rules it matches go on top
+    let hits = selected
+        .as_ref()
+        .map(|ev| app.ioc.hits(ev))
+        .unwrap_or_default();
+    let detections = selected
+        .as_ref()
+        .map(|ev| app.sigma.matching(ev))
+        .unwrap_or_default();
+    let (content, match_rows, cursor_rows) = if hits.is_empty() && detections.is_empty() {
+        (content, match_rows, cursor_rows)
     } else {
-        "Select an event.".to_string()
+        let style = Style::default()
+            .fg(t.error_color())
+            .add_modifier(Modifier::BOLD);
+        let mut lines: Vec<Line> = hits
+            .iter()
+            .map(|&i| {
+                Line::from(Span::styled(
+                    format!("IOC hit: {}", app.ioc.indicators[i]),
+                    style,
+                ))
+            })
+            .collect();
+        let style = Style::default()
+            .fg(t.detection_color())
+            .add_modifier(Modifier::BOLD);
+        lines.extend(detections.iter().map(|&i| {
+            let rule = &app.sigma.rules[i];
+            Line::from(Span::styled(
+                format!("Sigma: {} ({})", rule.title, rule.level),
+                style,
+            ))
+        }));
+        let shift = lines.len() as u16;
+        lines.extend(content);
+        (
+            lines,
+            match_rows.into_iter().map(|r| r + shift).collect(),
+            cursor_rows.start + shift..cursor_rows.end + shift,
+        )
     };
+    // in the process tree, a process line is summarised above the event that created it
+    let (content, match_rows) = match app.tree_selected_process() {
+        Some(node) if app.mode == super::app::Mode::ProcessTree => {
+            let mut lines = process_summary(app, node);
+            let shift = lines.len() as u16;
+            if selected.is_some() {
+                lines.extend(content);
+            }
+            (lines, match_rows.into_iter().map(|r| r + shift).collect())
+        }
+        _ => (content, match_rows),
+    };
+    let total_lines = content.len() as u16;
+    app.detail_match_rows = match_rows;
+    // keep the field under the cursor in view
+    if focused && !cursor_rows.is_empty() {
+        let height = area.height.saturating_sub(2);
+        if cursor_rows.start < app.detail_scroll {
+            app.detail_scroll = cursor_rows.start;
+        } else if cursor_rows.end > app.detail_scroll + height {
+            app.detail_scroll = cursor_rows
+                .end
+                .saturating_sub(height)
+                .min(cursor_rows.start);
+        }
+    }
 
     let para = Paragraph::new(content)
         .block(block)
@@ -349,10 +1365,6 @@ pub fn draw_detail(f: &mut ratatui::Frame, area: Rect, app: &App) {
     f.render_widget(para, area);
 
     // scrollbar for detail
-    let total_lines = app
-        .selected_event()
-        .map(|e| e.detail_lines().len())
-        .unwrap_or(0) as u16;
     let mut scroll_state = ScrollbarState::new(total_lines.saturating_add(2) as usize)
         .position(app.detail_scroll as usize);
     let scrollbar = Scrollbar::default()
@@ -370,59 +1382,184 @@ pub fn draw_detail(f: &mut ratatui::Frame, area: Rect, app: &App) {
     f.render_stateful_widget(scrollbar, area, &mut scroll_state);
 }
 
-fn detail_content(ev: &TimelineEvent, width: usize) -> String {
-    let lines = ev.detail_lines();
-    let mut out = String::new();
-    for (label, value) in lines {
+/// process fields for the process tree's detail panel
+fn process_summary(app: &App, node: usize) -> Vec<Line<'static>> {
+    let p = app.processes.get(node);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::from(Span::styled(
+        format!("Process: {}", p.label()),
+        bold,
+    ))];
+    let mut field = |label: &str, value: String| {
+        lines.push(Line::from(format!("{}: {}", label, value)));
+    };
+    field("Device", app.processes.device(&p.key).to_string());
+    if let Some(created) = p.created() {
+        field("Created", app.zone.format(created, "%Y-%m-%d %H:%M:%S%.3f"));
+    }
+    if let Some(path) = &p.path {
+        field("Path", path.clone());
+    }
+    if let Some(cmd) = &p.command_line {
+        field("Command line", cmd.clone());
+    }
+    if let Some(parent) = app.processes.parent(node) {
+        field("Parent", app.processes.get(parent).label());
+    }
+    field(
+        "Children / events",
+        format!("{} / {}", app.processes.children(node).len(), p.rows.len()),
+    );
+    let note = if p.created_row.is_some() {
+        "Creation event:"
+    } else {
+        "(creation event not in the timeline)"
+    };
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(note.to_string(), bold)));
+    lines
+}
+
+/// "Label: value" per field, wrapped to `width`, with search matches marked; also returns the
+/// rows holding a match (for n / N)
+fn detail_content(
+    ev: &TimelineEvent,
+    zone: &DisplayZone,
+    highlighter: &Highlighter,
+    width: usize,
+    cursor: Option<usize>,
+) -> (Vec<Line<'static>>, Vec<u16>, Range<u16>) {
+    // each row keeps the index of the field it shows (for the cursor)
+    let mut lines: Vec<(usize, String, String)> = ev
+        .detail_lines()
+        .into_iter()
+        .enumerate()
+        .map(|(i, (label, value))| (i, label, value))
+        .collect();
+    // event time in the display zone with its offset; the recorded utc value follows
+    if let (Some(t), Some(pos)) = (
+        ev.event_time_parsed(),
+        lines.iter().position(|(_, l, _)| l == "Event Time"),
+    ) {
+        let raw = std::mem::replace(
+            &mut lines[pos].2,
+            zone.format(t, "%Y-%m-%d %H:%M:%S%.3f %:z"),
+        );
+        if *zone != DisplayZone::Utc {
+            let field = lines[pos].0;
+            lines.insert(pos + 1, (field, "Event Time (UTC)".to_string(), raw));
+        }
+    }
+    let mut out = Vec::new();
+    let mut match_rows = Vec::new();
+    let mut cursor_rows = 0..0;
+    let cursor_style = Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD);
+    for (field, label, value) in lines {
+        let under_cursor = cursor == Some(field);
+        if under_cursor && cursor_rows.is_empty() {
+            cursor_rows = out.len() as u16..out.len() as u16;
+        }
+        // one row per field before wrapping (same byte lengths, so ranges stay valid)
+        let value = value.replace(['\n', '\r', '\t'], " ");
         let full = format!("{}: {}", label, value);
-        for chunk in wrap_at_width(&full, width) {
-            out.push_str(&chunk);
-            out.push('\n');
+        let skip = label.len() + 2;
+        let marks: Vec<Range<usize>> = highlighter
+            .ranges(Some(&label), &value)
+            .into_iter()
+            .map(|r| r.start + skip..r.end + skip)
+            .collect();
+        for row in wrap_ranges(&full, width) {
+            let row_marks: Vec<Range<usize>> = marks
+                .iter()
+                .filter(|m| m.start < row.end && m.end > row.start)
+                .map(|m| m.start.max(row.start) - row.start..m.end.min(row.end) - row.start)
+                .collect();
+            if !row_marks.is_empty() {
+                match_rows.push(out.len() as u16);
+            }
+            let line = Line::from(marked_spans(&full[row], &row_marks));
+            out.push(if under_cursor {
+                line.style(cursor_style)
+            } else {
+                line
+            });
+        }
+        if under_cursor {
+            cursor_rows.end = out.len() as u16;
         }
     }
     if out.is_empty() {
-        out.push_str("(no fields)");
+        out.push(Line::from("(no fields)"));
     }
-    out
+    (out, match_rows, cursor_rows)
 }
 
-fn wrap_at_width(s: &str, width: usize) -> Vec<String> {
-    if width == 0 {
-        return vec![s.to_string()];
+/// `text` as spans with the byte ranges `marks` (sorted, disjoint) in the match style
+fn marked_spans(text: &str, marks: &[Range<usize>]) -> Vec<Span<'static>> {
+    let t = theme();
+    let style = Style::default()
+        .fg(t.command_bar_text_color())
+        .bg(t.match_color());
+    let mut spans = Vec::new();
+    let mut at = 0;
+    for m in marks {
+        if m.start > at {
+            spans.push(Span::raw(text[at..m.start].to_string()));
+        }
+        spans.push(Span::styled(text[m.clone()].to_string(), style));
+        at = m.end;
+    }
+    if at < text.len() || spans.is_empty() {
+        spans.push(Span::raw(text[at..].to_string()));
+    }
+    spans
+}
+
+/// byte ranges of `s` wrapped at spaces to `width` columns (words longer than a row are
+/// split); the text itself is untouched, so match ranges into it still apply
+fn wrap_ranges(s: &str, width: usize) -> Vec<Range<usize>> {
+    use unicode_width::UnicodeWidthStr;
+    if width == 0 || s.width() <= width {
+        return std::iter::once(0..s.len()).collect();
     }
     let mut out = Vec::new();
-    let mut line = String::new();
-    for word in s.split_whitespace() {
-        let trial = if line.is_empty() {
-            word.to_string()
-        } else {
-            format!("{} {}", line, word)
-        };
-        if trial.len() <= width {
-            line = trial;
-        } else {
-            if !line.is_empty() {
-                out.push(std::mem::take(&mut line));
-            }
-            if word.len() > width {
-                for c in word.chars() {
-                    if line.len() >= width {
-                        out.push(std::mem::take(&mut line));
-                    }
-                    line.push(c);
-                }
-            } else {
-                line = word.to_string();
+    let mut start = 0;
+    // end of the last word that fits on the current row
+    let mut end = 0;
+    for (word_start, word) in s
+        .split(' ')
+        .scan(0, |pos, w| {
+            let at = *pos;
+            *pos += w.len() + 1;
+            Some((at, w))
+        })
+        .filter(|(_, w)| !w.is_empty())
+    {
+        let word_end = word_start + word.len();
+        if s[start..word_end].width() <= width {
+            end = word_end;
+            continue;
+        }
+        if end > start {
+            out.push(start..end);
+        }
+        // split a word wider than a row
+        let mut row_start = word_start;
+        let mut w = 0;
+        for (i, c) in word.char_indices() {
+            let cw = unicode_width::UnicodeWidthChar::width(c).unwrap_or(1);
+            if w + cw > width && w > 0 {
+                out.push(row_start..word_start + i);
+                row_start = word_start + i;
+                w = 0;
             }
+            w += cw;
         }
+        start = row_start;
+        end = word_end;
     }
-    if !line.is_empty() {
-        out.push(line);
-    }
-    if out.is_empty() && s.is_empty() {
-        out.push(String::new());
-    } else if out.is_empty() {
-        out.push(s.to_string());
+    if end > start {
+        out.push(start..end);
     }
     out
 }
@@ -445,6 +1582,15 @@ fn mode_label(mode: super::app::Mode) -> &'static str {
         super::app::Mode::SearchInput => " SEARCH ",
         super::app::Mode::ActionTypeFilter => " FILTER ",
         super::app::Mode::TimeFilter => " TIME ",
+        super::app::Mode::Rejected => " REJECTS ",
+        super::app::Mode::SourceFilter => " SOURCE ",
+        super::app::Mode::Exclusions => " EXCLUDE ",
+        super::app::Mode::Detections => " SIGMA ",
+        super::app::Mode::Library => " SAVED ",
+        super::app::Mode::ProcessTree => " TREE ",
+        super::app::Mode::Detail => " DETAIL ",
+        super::app::Mode::Histogram => " CHART ",
+        super::app::Mode::Stacking => " STACK ",
     }
 }
 
@@ -453,8 +1599,18 @@ pub fn draw_command_bar(f: &mut ratatui::Frame, area: Rect, app: &App) {
 
     let t = theme();
     // split bar: fixed-width mode pill on the left, hints on the right
+    let zone_label = format!(" {} ", app.zone);
     let bar_chunks = Layout::default()
-        .constraints([Constraint::Length(10), Constraint::Min(10)].as_ref())
+        .constraints(
+            [
+                Constraint::Length(10),
+                Constraint::Min(10),
+                Constraint::Length(
+                    unicode_width::UnicodeWidthStr::width(zone_label.as_str()) as u16
+                ),
+            ]
+            .as_ref(),
+        )
         .direction(Direction::Horizontal)
         .split(area);
 
@@ -474,14 +1630,135 @@ pub fn draw_command_bar(f: &mut ratatui::Frame, area: Rect, app: &App) {
     // right side: hints (and search buffer in SEARCH mode)
     let (hint_text, hint_align) = match app.mode {
         Mode::SearchInput => (
+            match (&app.search_error, app.search_regex) {
+                (Some(err), false) => {
+                    format!("Search: {}_  |  query error: {}", app.search_input, err)
+                }
+                (Some(err), true) => format!("Regex: {}_  |  {}", app.search_input, err),
+                (None, false) => format!(
+                    "Search: {}_  [ Enter ] apply  [ Esc ] cancel  [ Tab ] regex  |  field:value \"phrase\" AND OR NOT ( ) *",
+                    app.search_input
+                ),
+                (None, true) => format!(
+                    "Regex: {}_  [ Enter ] apply  [ Esc ] cancel  [ Tab ] query  |  [field:]pattern, each field on its own",
+                    app.search_input
+                ),
+            },
+            ratatui::layout::Alignment::Left,
+        ),
+        Mode::ActionTypeFilter => (
             format!(
-                "Search: {}_  [ Enter ] apply  [ Esc ] cancel",
-                app.search_input
+                "Type to narrow: {}_  |  Up/Down move  Space include/exclude/off  Del clear marks  Enter apply  Esc back",
+                app.action_type_query
             ),
             ratatui::layout::Alignment::Left,
         ),
-        Mode::ActionTypeFilter => (
-            " Esc to go back  |  j/k move  Enter apply".to_string(),
+        Mode::SourceFilter => (
+            " Esc to go back (clears source)  |  j/k move  Enter apply".to_string(),
+            ratatui::layout::Alignment::Left,
+        ),
+        Mode::Exclusions => (
+            match &app.noise_input {
+                Some(input) => format!(
+                    "Exclude: {}_  [ Enter ] add  [ Esc ] cancel  |  query syntax as in search",
+                    input
+                ),
+                None => {
+                    let mut s = " Esc back  |  j/k move  Space on/off  d delete  |  exclude selected event's: a action type  p process  f file  |  n query".to_string();
+                    if let Some(ref err) = app.error {
+                        s.push_str("  |  ");
+                        s.push_str(err);
+                    } else if let Some(ref flash) = app.flash {
+                        s.push_str("  |  ");
+                        s.push_str(flash);
+                    }
+                    s
+                }
+            },
+            ratatui::layout::Alignment::Left,
+        ),
+        Mode::ProcessTree => (
+            match &app.flash {
+                Some(flash) => format!(
+                    " Esc back  |  j/k move  Enter/l expand or show event  h collapse  |  {}",
+                    flash
+                ),
+                None => " Esc back  |  j/k move  Enter/l expand or show event  h collapse  PgUp/PgDn scroll detail".to_string(),
+            },
+            ratatui::layout::Alignment::Left,
+        ),
+        Mode::Library => (
+            match &app.library_input {
+                Some(input) => format!(
+                    "Save as: {}_  [ Enter ] save  [ Esc ] cancel  |  search, action types, time range and exclusions are kept",
+                    input
+                ),
+                None => {
+                    let mut s =
+                        " Esc back  |  j/k move  Enter apply  n save current  d delete".to_string();
+                    if let Some(ref err) = app.error {
+                        s.push_str("  |  ");
+                        s.push_str(err);
+                    } else if let Some(ref flash) = app.flash {
+                        s.push_str("  |  ");
+                        s.push_str(flash);
+                    }
+                    s
+                }
+            },
+            ratatui::layout::Alignment::Left,
+        ),
+        Mode::Stacking => (
+            match (&app.stack_input, &app.stack_error) {
+                (Some(input), Some(err)) => format!(
+                    "Stack by: {}_  [ Enter ] count  [ Esc ] cancel  |  {}",
+                    input, err
+                ),
+                (Some(input), None) => format!(
+                    "Stack by: {}_  [ Enter ] count  [ Esc ] cancel  |  fields as in a search, separated by spaces",
+                    input
+                ),
+                (None, _) => {
+                    let mut s = " Esc back  |  j/k move  Enter filter to combination  s sort  n other fields"
+                        .to_string();
+                    if let Some(ref flash) = app.flash {
+                        s.push_str("  |  ");
+                        s.push_str(flash);
+                    }
+                    s
+                }
+            },
+            ratatui::layout::Alignment::Left,
+        ),
+        Mode::Histogram => (
+            match &app.flash {
+                Some(flash) => format!(
+                    " Esc back  |  h/l move  Enter zoom into bar  Backspace zoom out  |  {}",
+                    flash
+                ),
+                None => " Esc back  |  h/l move (list follows)  Enter zoom into bar  Backspace zoom out".to_string(),
+            },
+            ratatui::layout::Alignment::Left,
+        ),
+        Mode::Detail => (
+            match &app.flash {
+                Some(flash) => format!(
+                    " Esc/Tab back  |  j/k move  f filter to value  e exclude value  s search value  |  {}",
+                    flash
+                ),
+                None => " Esc/Tab back  |  j/k move  f filter to value  e exclude value  s search value everywhere".to_string(),
+            },
+            ratatui::layout::Alignment::Left,
+        ),
+        Mode::Detections => (
+            match &app.flash {
+                Some(flash) => format!(" Esc back  |  j/k move  Enter show hits  |  {}", flash),
+                None => " Esc back  |  j/k move  Enter show hits".to_string(),
+            },
+            ratatui::layout::Alignment::Left,
+        ),
+        Mode::Rejected => (
+            " Esc to go back  |  j/k move  PgUp/PgDn scroll detail".to_string(),
             ratatui::layout::Alignment::Left,
         ),
         Mode::TimeFilter => {
@@ -521,7 +1798,11 @@ pub fn draw_command_bar(f: &mut ratatui::Frame, area: Rect, app: &App) {
             let has_time = app.time_range_start.is_some() || app.time_range_end.is_some();
             let mut s = match (
                 !app.search.is_empty(),
-                app.action_type_filter.is_some(),
+                !app.action_type_filter.is_empty()
+                    || app.source_filter.is_some()
+                    || app.ioc_only
+                    || app.detection_filter.is_some()
+                    || app.process_filter.is_some(),
                 has_time,
             ) {
                 (true, true, true) => "[ x ] clear all  |  ".to_string(),
@@ -533,11 +1814,34 @@ pub fn draw_command_bar(f: &mut ratatui::Frame, area: Rect, app: &App) {
                 (false, false, true) => "[ x ] clear time  |  ".to_string(),
                 (false, false, false) => String::new(),
             };
-            s.push_str("[ j/k ] up/down  [ / ] search  [ a ] filter  [ t ] time  [ q ] quit");
+            s.push_str("[ j/k ] up/down  [ / ] search  [ a ] filter  [ e ] exclude  [ t ] time  [ Tab ] detail fields  [ h ] histogram  [ f ] stack  [ l ] saved filters  [ p ] process tree  [ o ] process events  [ z ] zone");
+            if !app.breadcrumbs.is_empty() {
+                s.push_str("  [ Backspace ] back");
+            }
+            if !app.search.is_empty() {
+                s.push_str("  [ n/N ] next/prev match");
+            }
+            if !app.ioc.is_empty() {
+                s.push_str("  [ i ] IOC hits");
+            }
+            if !app.sigma.is_empty() {
+                s.push_str("  [ d ] detections");
+            }
+            if app.sources.len() > 1 {
+                s.push_str("  [ s ] source");
+            }
+            s.push_str("  [ q ] quit");
+            if app.load_report.rejected_total > 0 {
+                s.push_str("  [ r ] rejected rows");
+            }
             if let Some(ref flash) = app.flash {
                 s.push_str("  |  ");
                 s.push_str(flash);
             }
+            if let Some(ref err) = app.error {
+                s.push_str("  |  ");
+                s.push_str(err);
+            }
             (s, ratatui::layout::Alignment::Left)
         }
     };
@@ -550,4 +1854,14 @@ pub fn draw_command_bar(f: &mut ratatui::Frame, area: Rect, app: &App) {
         .alignment(hint_align)
         .wrap(Wrap { trim: true });
     f.render_widget(hint_para, bar_chunks[1]);
+
+    // display zone, always visible so times are never read in the wrong zone
+    let zone_para = Paragraph::new(Line::from(Span::styled(
+        zone_label,
+        Style::default()
+            .fg(t.highlight_color())
+            .add_modifier(Modifier::BOLD),
+    )))
+    .style(Style::default().bg(t.border_color()));
+    f.render_widget(zone_para, bar_chunks[2]);
 }
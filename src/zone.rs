@@ -0,0 +1,180 @@
+// display time zone: event times are kept in utc; the list, the time filters and the relative
+// presets ("today", "last 24 hours") all work in one display zone
+
+use crate::error::Result;
+use crate::timeline::parse_time_parts;
+use anyhow::anyhow;
+use chrono::{Duration, FixedOffset, Local, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::fmt;
+use std::str::FromStr;
+
+/// zone used to show and enter times
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DisplayZone {
+    #[default]
+    Utc,
+    /// the machine's local zone
+    Local,
+    /// fixed offset, e.g. +02:00
+    Fixed(FixedOffset),
+    /// iana zone, e.g. Europe/Berlin (follows dst)
+    Named(Tz),
+}
+
+impl FromStr for DisplayZone {
+    type Err = anyhow::Error;
+
+    /// `utc`, `local`, an offset (`+02:00`, `-0530`, `+2`) or an iana name (`Europe/Berlin`)
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        match s.to_lowercase().as_str() {
+            "utc" | "z" | "gmt" => return Ok(DisplayZone::Utc),
+            "local" => return Ok(DisplayZone::Local),
+            _ => {}
+        }
+        if s.starts_with(['+', '-']) {
+            return parse_offset(s)
+                .map(DisplayZone::Fixed)
+                .ok_or_else(|| anyhow!("invalid utc offset {:?} (e.g. +02:00)", s));
+        }
+        s.parse::<Tz>().map(DisplayZone::Named).map_err(|_| {
+            anyhow!(
+                "unknown time zone {:?} (utc, local, +02:00, Europe/Berlin)",
+                s
+            )
+        })
+    }
+}
+
+impl fmt::Display for DisplayZone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DisplayZone::Utc => f.write_str("UTC"),
+            DisplayZone::Local => write!(f, "local (UTC{})", self.offset_at(now_utc())),
+            DisplayZone::Fixed(o) => write!(f, "UTC{}", o),
+            DisplayZone::Named(tz) => f.write_str(tz.name()),
+        }
+    }
+}
+
+impl DisplayZone {
+    /// offset from utc in effect at utc time `t`
+    pub fn offset_at(&self, t: NaiveDateTime) -> FixedOffset {
+        match self {
+            DisplayZone::Utc => Utc.fix(),
+            DisplayZone::Local => Local.offset_from_utc_datetime(&t).fix(),
+            DisplayZone::Fixed(o) => *o,
+            DisplayZone::Named(tz) => tz.offset_from_utc_datetime(&t).fix(),
+        }
+    }
+
+    /// wall-clock time in this zone for utc time `t`
+    pub fn to_local(&self, t: NaiveDateTime) -> NaiveDateTime {
+        t + self.offset_at(t)
+    }
+
+    /// utc time for wall-clock time `t` in this zone; in a dst overlap the earlier instant,
+    /// in a dst gap the instant the clocks jumped to
+    pub fn to_utc(&self, t: NaiveDateTime) -> NaiveDateTime {
+        fn conv<Z: TimeZone>(z: &Z, t: NaiveDateTime) -> NaiveDateTime {
+            match z.from_local_datetime(&t).earliest() {
+                Some(d) => d.naive_utc(),
+                None => z
+                    .from_local_datetime(&(t + Duration::hours(1)))
+                    .earliest()
+                    .map(|d| d.naive_utc() - Duration::hours(1))
+                    .unwrap_or(t),
+            }
+        }
+        match self {
+            DisplayZone::Utc => t,
+            DisplayZone::Local => conv(&Local, t),
+            DisplayZone::Fixed(o) => conv(o, t),
+            DisplayZone::Named(tz) => conv(tz, t),
+        }
+    }
+
+    /// current wall-clock time in this zone (reference for relative ranges)
+    pub fn now(&self) -> NaiveDateTime {
+        self.to_local(now_utc())
+    }
+
+    /// utc time `t` formatted as wall-clock time in this zone
+    pub fn format(&self, t: NaiveDateTime, fmt: &str) -> String {
+        self.offset_at(t)
+            .from_utc_datetime(&t)
+            .format(fmt)
+            .to_string()
+    }
+
+    /// time typed by the analyst as wall-clock time in this zone; an explicit `Z` or offset
+    /// suffix is honoured and converted
+    pub fn parse_input(&self, s: &str) -> Option<NaiveDateTime> {
+        let (t, offset) = parse_time_parts(s)?;
+        Some(match offset {
+            Some(o) => self.to_local(t - o),
+            None => t,
+        })
+    }
+}
+
+fn now_utc() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+/// `+02:00`, `+0200`, `+02` or `+2` (sign required)
+pub(crate) fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if digits.is_empty() || digits.len() > 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (h, m) = if digits.len() > 2 {
+        digits.split_at(digits.len() - 2)
+    } else {
+        (digits.as_str(), "0")
+    };
+    let (h, m): (i32, i32) = (h.parse().ok()?, m.parse().ok()?);
+    if h > 23 || m > 59 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (h * 3600 + m * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::parse_time;
+
+    #[test]
+    fn utc_parsing_and_zone_conversion() {
+        let utc = parse_time("2026-03-29T00:30:00Z").unwrap();
+        assert_eq!(parse_time("2026-03-29T02:30:00+02:00"), Some(utc));
+        assert_eq!(parse_time("2026-03-29 00:30:00"), Some(utc));
+
+        let berlin: DisplayZone = "Europe/Berlin".parse().unwrap();
+        assert_eq!(berlin.format(utc, "%H:%M %:z"), "01:30 +01:00");
+        // after the spring-forward jump
+        let later = utc + Duration::hours(2);
+        assert_eq!(berlin.format(later, "%H:%M %:z"), "04:30 +02:00");
+        assert_eq!(berlin.to_utc(berlin.to_local(later)), later);
+        // typed input is wall-clock in the zone unless it carries its own offset
+        assert_eq!(
+            berlin.parse_input("2026-03-29 01:30"),
+            Some(berlin.to_local(utc))
+        );
+        assert_eq!(
+            berlin.parse_input("2026-03-29T00:30:00Z"),
+            Some(berlin.to_local(utc))
+        );
+
+        let fixed: DisplayZone = "-0530".parse().unwrap();
+        assert_eq!(fixed.to_string(), "UTC-05:30");
+        assert!("Mars/Olympus".parse::<DisplayZone>().is_err());
+    }
+}